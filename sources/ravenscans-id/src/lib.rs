@@ -0,0 +1,80 @@
+// RavenScans ID Aidoku source (id, NSFW)
+// id: com.ravenscans.id
+// RavenScans' Indonesian sister site, on the same theme. Everything but the
+// site description lives in the shared template crate.
+
+use aidoku::{
+    error::Result,
+    prelude::*,
+    std::{net::Request, String, Vec},
+    Chapter, DeepLink, Filter, Listing, Manga, MangaPageResult, Page,
+};
+use wpmanga_template::{self as template, Site};
+
+// ------- Config -------
+// Name and language match aidoku.json, which carries the rest (id, version,
+// content rating) for the app.
+static SITE: Site = Site {
+    name: "RavenScans ID",
+    lang: "id",
+    base_url: "https://id.ravenscans.com",
+    scanlator: "Raven Scans ID",
+    title_suffixes: &["raven scans id", "raven scans", "ravenscans"],
+    boilerplate: &["baca di ravenscans", "baca hanya di ravenscans", "read at ravenscans"],
+    remote_config: Some("https://raw.githubusercontent.com/savoy12/ravenscans-aidoku/main/sources/ravenscans-id/remote.json"),
+    // None known yet; new ones go out through remote.json first
+    mirrors: &[],
+    // WIB
+    utc_offset: 7.0,
+};
+
+// ---- Source impl ----
+#[get_manga_list]
+fn get_manga_list(filters: Vec<Filter>, page: i32) -> Result<MangaPageResult> {
+    template::get_manga_list(&SITE, filters, page)
+}
+
+#[get_manga_listing]
+fn get_manga_listing(listing: Listing, page: i32) -> Result<MangaPageResult> {
+    template::get_manga_listing(&SITE, listing, page)
+}
+
+#[get_manga_details]
+fn get_manga_details(id: String) -> Result<Manga> {
+    template::get_manga_details(&SITE, id)
+}
+
+#[get_chapter_list]
+fn get_chapter_list(id: String) -> Result<Vec<Chapter>> {
+    template::get_chapter_list(&SITE, id)
+}
+
+#[get_page_list]
+fn get_page_list(id: String) -> Result<Vec<Page>> {
+    template::get_page_list(&SITE, id)
+}
+
+#[get_search_results]
+fn get_search_results(filters: Vec<Filter>, page: i32) -> Result<MangaPageResult> {
+    template::get_search_results(&SITE, filters, page)
+}
+
+#[initialize]
+fn initialize() {
+    template::initialize(&SITE);
+}
+
+#[handle_notification]
+fn handle_notification(notification: String) {
+    template::handle_notification(&SITE, notification);
+}
+
+#[modify_image_request]
+fn modify_image_request(request: Request) {
+    template::modify_image_request(&SITE, request);
+}
+
+#[handle_url]
+fn handle_url(url: String) -> Result<DeepLink> {
+    template::handle_url(&SITE, url)
+}
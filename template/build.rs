@@ -0,0 +1,176 @@
+// Compiles selectors.json into Rust constants (see theme.rs / config.rs),
+// rejecting the edits most likely to break parsing at runtime: missing or
+// unknown selector names, empty selectors and malformed selector syntax.
+// Also generates Selectors::field_mut and checks the struct in theme.rs
+// against FIELDS, so the three can't drift apart.
+
+use std::{env, fs, path::Path};
+
+use serde_json::{Map, Value};
+
+const CONFIG: &str = "selectors.json";
+const THEME_RS: &str = "src/theme.rs";
+
+// Must match theme::Selectors; checked by check_struct
+const FIELDS: &[&str] = &[
+    "list_item", "title", "cover", "list_genres", "card_rating", "href", "details_title", "manga_meta", "info_row",
+    "info_label", "info_value", "summary", "adult_badge", "details_adult_badge", "genres", "status", "manga_post_id",
+    "chapter_list", "chapter_name", "chapter_next", "chapter_date", "chapter_group", "chapter_lock", "chapter_parent",
+    "page_image", "page_noscript", "pagination_next", "page_numbers", "popular_today", "popular_weekly",
+    "popular_monthly", "popular_all_time", "genre_link", "bookmark_item", "related_item", "related_title",
+    "details_rating", "details_followers", "novel_text", "latest_item", "latest_chapter", "chapter_subpages", "latest_date",
+    "details_cover",
+];
+
+// (JSON name, Rust const)
+const THEMES: &[(&str, &str)] = &[("madara", "MADARA"), ("mangastream", "MANGASTREAM"), ("mixed", "MIXED")];
+const MARKERS: &[(&str, &str)] = &[("madara", "MADARA_MARKERS"), ("mangastream", "MANGASTREAM_MARKERS")];
+const IMAGES: &[(&str, &str)] = &[
+    // Ad servers that show up inside the reading container
+    ("ad_hosts", "AD_HOSTS"),
+    // Non-page images dropped into chapters: join/support banners, share icons
+    ("non_content_urls", "NON_CONTENT_URLS"),
+    // Attributes lazy-load plugins park the real URL in
+    ("lazy_attrs", "LAZY_ATTRS"),
+    // Stand-ins served until lazy loading kicks in
+    ("placeholders", "PLACEHOLDERS"),
+    // The banner an unreleased chapter shows as its only page
+    ("coming_soon", "COMING_SOON"),
+    // Credit, recruitment and "next chapter" pages at either end of a chapter
+    ("credit_urls", "CREDIT_URLS"),
+];
+
+// Balanced brackets and quotes, and no empty alternatives ("a, , b", "a,")
+fn check_selector(sel: &str) -> Result<(), String> {
+    if sel.trim().is_empty() {
+        return Err("is empty".into());
+    }
+    if sel.split(',').any(|part| part.trim().is_empty()) {
+        return Err("has an empty alternative".into());
+    }
+    let mut stack = Vec::new();
+    let mut quote = None;
+    for c in sel.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '(') => stack.push(c),
+            (None, ']' | ')') => {
+                let open = if c == ']' { '[' } else { '(' };
+                if stack.pop() != Some(open) {
+                    return Err(format!("has an unmatched '{c}'"));
+                }
+            }
+            _ => {}
+        }
+    }
+    if quote.is_some() {
+        return Err("has an unterminated string".into());
+    }
+    if !stack.is_empty() {
+        return Err("has an unclosed bracket".into());
+    }
+    if sel.trim_end().ends_with(['>', '+', '~']) {
+        return Err("ends with a combinator".into());
+    }
+    Ok(())
+}
+
+fn object<'a>(v: &'a Value, path: &str) -> &'a Map<String, Value> {
+    v.as_object().unwrap_or_else(|| panic!("{CONFIG}: {path} must be an object"))
+}
+
+fn strings(v: &Value, path: &str) -> Vec<String> {
+    let items = v.as_array().unwrap_or_else(|| panic!("{CONFIG}: {path} must be an array"));
+    items
+        .iter()
+        .map(|i| {
+            let s = i.as_str().unwrap_or_else(|| panic!("{CONFIG}: {path} must only hold strings"));
+            if s.is_empty() { panic!("{CONFIG}: {path} has an empty entry"); }
+            s.to_string()
+        })
+        .collect()
+}
+
+// Field names of `pub struct Selectors` in theme.rs, in order
+fn struct_fields(source: &str) -> Vec<String> {
+    let start = source.find("pub struct Selectors {").unwrap_or_else(|| panic!("{THEME_RS}: no `pub struct Selectors`"));
+    let body = &source[start..];
+    let body = &body[..body.find("\n}").unwrap_or_else(|| panic!("{THEME_RS}: Selectors isn't closed"))];
+    body.lines()
+        .filter_map(|l| l.trim().strip_prefix("pub ")?.split_once(':').map(|(name, _)| name.trim().to_string()))
+        .collect()
+}
+
+fn check_struct() {
+    let source = fs::read_to_string(THEME_RS).unwrap_or_else(|e| panic!("{THEME_RS}: {e}"));
+    let fields = struct_fields(&source);
+    if let Some(extra) = fields.iter().find(|f| !FIELDS.contains(&f.as_str())) {
+        panic!("{THEME_RS}: Selectors.{extra} is missing from FIELDS in build.rs");
+    }
+    if let Some(missing) = FIELDS.iter().find(|f| !fields.iter().any(|g| g == *f)) {
+        panic!("build.rs: FIELDS has {missing}, which Selectors in {THEME_RS} doesn't");
+    }
+}
+
+// Selectors::field_mut: a field by its name in the override JSON
+fn field_mut() -> String {
+    let mut out = String::from(
+        "impl Selectors {\n    pub(crate) fn field_mut(&mut self, name: &str) -> Option<&mut &'static str> {\n        Some(match name {\n",
+    );
+    for field in FIELDS {
+        out.push_str(&format!("            {field:?} => &mut self.{field},\n"));
+    }
+    out.push_str("            _ => return None,\n        })\n    }\n}\n\n");
+    out
+}
+
+fn str_list(name: &str, items: &[String]) -> String {
+    format!("pub const {name}: &[&str] = &{items:?};\n")
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={CONFIG}");
+    println!("cargo:rerun-if-changed={THEME_RS}");
+    check_struct();
+    let text = fs::read_to_string(CONFIG).unwrap_or_else(|e| panic!("{CONFIG}: {e}"));
+    let config: Value = serde_json::from_str(&text).unwrap_or_else(|e| panic!("{CONFIG}: {e}"));
+    let mut out = String::from("// Generated by build.rs from selectors.json; edit that instead.\n\n");
+
+    for (key, name) in THEMES {
+        let theme = object(&config["themes"][*key], &format!("themes.{key}"));
+        // Both directions: unknown keys here, missing ones in the loop below
+        if let Some(unknown) = theme.keys().find(|k| !FIELDS.contains(&k.as_str())) {
+            panic!("{CONFIG}: themes.{key}.{unknown} is not a selector");
+        }
+        out.push_str(&format!("pub const {name}: Selectors = Selectors {{\n"));
+        for field in FIELDS {
+            let path = format!("themes.{key}.{field}");
+            let sel = theme.get(*field).and_then(Value::as_str).unwrap_or_else(|| panic!("{CONFIG}: {path} is missing"));
+            if let Err(e) = check_selector(sel) {
+                panic!("{CONFIG}: {path} {e}: {sel:?}");
+            }
+            out.push_str(&format!("    {field}: {sel:?},\n"));
+        }
+        out.push_str("};\n\n");
+    }
+
+    for (key, name) in MARKERS {
+        let path = format!("markers.{key}");
+        let markers = strings(&config["markers"][*key], &path);
+        if let Some((m, e)) = markers.iter().find_map(|m| check_selector(m).err().map(|e| (m, e))) {
+            panic!("{CONFIG}: {path} {m:?} {e}");
+        }
+        // One selector group, so detection is a single scan per theme
+        out.push_str(&format!("pub const {name}: &str = {:?};\n", markers.join(", ")));
+    }
+    out.push('\n');
+    out.push_str(&field_mut());
+    for (key, name) in IMAGES {
+        out.push_str(&str_list(name, &strings(&config["images"][*key], &format!("images.{key}"))));
+    }
+
+    let dest = Path::new(&env::var("OUT_DIR").expect("OUT_DIR")).join("config.rs");
+    fs::write(dest, out).expect("writing config.rs");
+}
@@ -0,0 +1,67 @@
+// Member login. Chapters restricted to logged-in members render without
+// images for guests, so an empty page list is retried once after signing in
+// through wp-login.php. The app's cookie store keeps the session WordPress
+// sets; a session cookie pasted into the settings is sent with every request
+// instead (see http::request_headers).
+
+use aidoku::{
+    error::Result,
+    std::{String, Vec},
+    MangaPageResult,
+};
+use std::sync::Mutex;
+
+use crate::{
+    error::SourceError,
+    first,
+    http::{self, get_dom, post_dom},
+    parse_manga_items, settings, theme,
+    url::encode,
+};
+
+// One attempt per session, so wrong credentials don't hammer wp-login.php.
+// Reset with the other caches when the settings change.
+static TRIED: Mutex<bool> = Mutex::new(false);
+
+// True when a new session was just opened and the request is worth retrying
+pub fn log_in() -> bool {
+    let (username, password) = (settings::username(), settings::password());
+    if username.is_empty() || password.is_empty() { return false; }
+    let mut tried = TRIED.lock().unwrap_or_else(|e| e.into_inner());
+    if *tried { return false; }
+    *tried = true;
+
+    let body = format!("log={}&pwd={}&rememberme=forever", encode(&username), encode(&password));
+    let Ok(dom) = post_dom(&format!("{}/wp-login.php", settings::base_url()), &body) else {
+        debug!("account: wp-login.php unreachable");
+        return false;
+    };
+    // A rejected login lands back on the form, with #login_error above it
+    let ok = first(&dom, "#login_error, #loginform").is_none();
+    debug!("account: login {}", if ok { "succeeded" } else { "rejected" });
+    // Pages fetched as a guest are what the retry is meant to replace
+    if ok { http::clear_call_memo(); }
+    ok
+}
+
+// "My Bookmarks": the series followed on the site's account page. Madara
+// keeps bookmarks server-side; MangaStream keeps them in the browser, so
+// there's nothing to mirror there. The page isn't paginated or cached.
+pub fn bookmarks(page: i32) -> Result<MangaPageResult> {
+    if page > 1 { return Ok(MangaPageResult { manga: Vec::new(), has_more: false }); }
+    let link = format!("{}/user-settings/?tab=bookmark", settings::base_url());
+    let mut dom = get_dom(&link)?;
+    // WordPress marks pages rendered for a signed-in user with body.logged-in
+    if first(&dom, "body.logged-in").is_none() && log_in() {
+        dom = get_dom(&link)?;
+    }
+    if first(&dom, "body.logged-in").is_none() {
+        return Err(SourceError::Unsupported(String::from("Sign in under Settings > Account to see your bookmarks")).into());
+    }
+    let manga = parse_manga_items(&dom, theme::selectors(&dom), |s| s.bookmark_item);
+    Ok(MangaPageResult { manga, has_more: false })
+}
+
+pub fn reset() {
+    *TRIED.lock().unwrap_or_else(|e| e.into_inner()) = false;
+}
@@ -0,0 +1,64 @@
+// Theme live-search over admin-ajax: the dropdown under the site's search
+// box. One small JSON response instead of a full results page.
+
+use aidoku::{
+    error::Result,
+    std::{String, Vec},
+};
+use serde_json::Value;
+
+use crate::{error::SourceError, http::post_text, parse::decode_entities, settings, url};
+
+pub struct Hit {
+    pub title: String,
+    pub link: String,
+    pub cover: Option<String>,
+}
+
+// (form body, where the result list sits in the response)
+fn requests(query: &str) -> [(String, &'static str); 2] {
+    let q = url::encode(query);
+    [
+        // Madara: {"success":true,"data":[{"title","url"}]}
+        (format!("action=wp-manga-search-manga&title={q}"), "data"),
+        // MangaStream: {"series":[{"all":[{"post_title","post_link","post_image"}]}]}
+        (format!("action=ts_ac_do_search&ts_ac_query={q}"), "series"),
+    ]
+}
+
+fn hit(v: &Value) -> Option<Hit> {
+    let field = |keys: &[&str]| keys.iter().find_map(|k| v[*k].as_str()).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    Some(Hit {
+        title: decode_entities(&field(&["title", "post_title"])?),
+        link: field(&["url", "post_link", "link"])?,
+        cover: field(&["thumbnail", "post_image", "image"]),
+    })
+}
+
+fn hits(v: &Value) -> Vec<Hit> {
+    match v {
+        Value::Array(items) => items
+            .iter()
+            // MangaStream groups results under "all"
+            .flat_map(|i| match i["all"].as_array() {
+                Some(all) => all.iter().filter_map(hit).collect(),
+                None => hit(i).into_iter().collect::<Vec<_>>(),
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Err when neither theme's action answers with results JSON, so the caller
+// can stop trying
+pub fn search(query: &str) -> Result<Vec<Hit>> {
+    let url = format!("{}/wp-admin/admin-ajax.php", settings::base_url());
+    for (body, key) in requests(query) {
+        let Ok(text) = post_text(&url, &body) else { continue };
+        let Ok(v) = serde_json::from_str::<Value>(&text) else { continue };
+        // Madara sends success:false with a message for "no results"
+        if v["success"] == Value::Bool(false) { return Ok(Vec::new()); }
+        if v.get(key).is_some() { return Ok(hits(&v[key])); }
+    }
+    Err(SourceError::Missing("live search").into())
+}
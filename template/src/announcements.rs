@@ -0,0 +1,94 @@
+// "Announcements" listing: the site's news posts (domain moves, hiatuses,
+// reader problems) as one read-only entry whose chapters are the posts, read
+// as text. Built from the plain WordPress posts feed, which carries each
+// post's full content, so nothing but the feed is fetched.
+
+use aidoku::{
+    error::Result,
+    std::{String, Vec},
+    Chapter, Manga, MangaContentRating, MangaPageResult, MangaStatus, MangaViewer, Page,
+};
+
+use crate::{feed, http, parse, select, settings, site, text, url};
+
+// Manga ID of the entry; chapter IDs are this plus the post's path
+pub const ID: &str = "announcements";
+
+pub fn is_announcement(id: &str) -> bool {
+    id.starts_with(ID)
+}
+
+fn entry() -> Manga {
+    Manga {
+        id: ID.into(),
+        cover: String::new(),
+        title: format!("{} Announcements", site().name),
+        author: String::new(),
+        artist: String::new(),
+        description: String::from("News posts from the site: domain changes, hiatuses and reader issues."),
+        url: format!("{}/", settings::base_url()),
+        categories: Vec::new(),
+        status: MangaStatus::Ongoing,
+        nsfw: MangaContentRating::Safe,
+        viewer: MangaViewer::Scroll,
+    }
+}
+
+pub fn listing(page: i32) -> MangaPageResult {
+    let manga = if page > 1 { Vec::new() } else { vec![entry()] };
+    MangaPageResult { manga, has_more: false }
+}
+
+pub fn details() -> Manga {
+    entry()
+}
+
+fn news_feed() -> Result<String> {
+    http::get_text_cached(&url::news_feed_url())
+}
+
+// Newest first, like the feed
+pub fn chapters() -> Result<Vec<Chapter>> {
+    let xml = news_feed()?;
+    let chapters = feed::item_bodies(&xml)
+        .into_iter()
+        .filter_map(|item| {
+            let title = parse::decode_entities(feed::tag(item, "title")?);
+            let link = feed::tag(item, "link")?;
+            Some(Chapter {
+                id: format!("{ID}{}", url::path_id(link)),
+                title,
+                volume: String::new(),
+                chapter: String::new(),
+                date_updated: feed::pub_date(item),
+                scanlator: site().scanlator.into(),
+                url: link.into(),
+                lang: site().lang.into(),
+            })
+        })
+        .collect();
+    Ok(chapters)
+}
+
+// The post's paragraphs as a single text page
+pub fn pages(id: &str) -> Result<Vec<Page>> {
+    let path = id.strip_prefix(ID).unwrap_or(id);
+    let xml = news_feed()?;
+    let item = feed::item_bodies(&xml)
+        .into_iter()
+        .find(|item| feed::tag(item, "link").is_some_and(|l| url::path_id(l) == path));
+    let html = item
+        .and_then(|item| feed::tag(item, "content:encoded").or_else(|| feed::tag(item, "description")))
+        .map(parse::decode_entities)
+        .unwrap_or_default();
+    let body = match http::parse_html(html.as_bytes()) {
+        Ok(dom) => {
+            let paragraphs: Vec<String> = select(&dom, "p, li").iter().map(text).filter(|p| !p.is_empty()).collect();
+            if paragraphs.is_empty() { text(&dom) } else { paragraphs.join("\n\n") }
+        }
+        Err(_) => String::new(),
+    };
+    // Older posts drop off the feed; point at the site instead of a blank page
+    let text = if body.is_empty() { format!("This post is no longer in the feed. Read it at {}", url::id_url(path)) } else { body };
+    Ok(vec![Page { index: 0, url: String::new(), base64: String::new(), text }])
+}
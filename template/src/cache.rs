@@ -0,0 +1,103 @@
+// Persistent response cache in Aidoku defaults, keyed by URL, so browsing
+// back and forth doesn't refetch the same series pages and listings.
+// Parsed chapter lists for library refreshes are kept in memory.rs.
+
+use aidoku::std::{String, Vec};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::host::{defaults_get, defaults_set, StringRef};
+
+// Defaults aren't meant for bulk data, and the session cache in memory.rs
+// already covers browsing back and forth: keep a handful of recent pages,
+// and only ones small enough to be listings or ordinary series pages
+const MAX_ENTRIES: usize = 8;
+const MAX_BODY: usize = 192 * 1024;
+const INDEX_KEY: &str = "cache.index";
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub time: f64,
+    pub body: String,
+    // Validators for conditional refreshes
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+}
+
+fn key(url: &str) -> String {
+    format!("cache.{url}")
+}
+
+fn read(key: &str) -> Option<String> {
+    defaults_get(key).and_then(|v| v.as_string()).map(|v| v.read()).ok()
+}
+
+fn write(key: &str, value: &str) {
+    defaults_set(key, StringRef::from(value).0);
+}
+
+// Cached URLs, oldest first
+fn index() -> Vec<String> {
+    read(INDEX_KEY).and_then(|i| serde_json::from_str(&i).ok()).unwrap_or_default()
+}
+
+fn save_index(index: &[String]) {
+    if let Ok(json) = serde_json::to_string(index) { write(INDEX_KEY, &json); }
+}
+
+// Entry for `url` regardless of age; stale ones still carry validators
+pub fn lookup(url: &str) -> Option<Entry> {
+    serde_json::from_str(&read(&key(url))?).ok()
+}
+
+pub fn put(url: &str, entry: &Entry) {
+    let mut index = index();
+    index.retain(|u| u != url);
+    // Too big to keep: drop any older copy too, so its validators don't
+    // outlive the body they belong to
+    if entry.body.len() > MAX_BODY {
+        write(&key(url), "");
+        save_index(&index);
+        return;
+    }
+    let Ok(json) = serde_json::to_string(entry) else { return };
+    write(&key(url), &json);
+    index.push(url.into());
+    while index.len() > MAX_ENTRIES {
+        let evicted = index.remove(0);
+        write(&key(&evicted), "");
+    }
+    save_index(&index);
+}
+
+pub fn clear() {
+    for url in index() {
+        write(&key(&url), "");
+    }
+    save_index(&[]);
+    *LEGACY_DROPPED.lock().unwrap_or_else(|e| e.into_inner()) = false;
+    drop_legacy_chapters();
+    // The session copies in front of this cache go with it
+    crate::memory::clear();
+}
+
+// ---- Chapter lists ----
+// Earlier versions kept every series' parsed list here; those now live in
+// memory.rs. Whatever an old version stored goes on the next clear or the
+// first list put this session.
+const CHAPTERS_INDEX_KEY: &str = "chapters.index";
+static LEGACY_DROPPED: Mutex<bool> = Mutex::new(false);
+
+pub fn drop_legacy_chapters() {
+    let mut dropped = LEGACY_DROPPED.lock().unwrap_or_else(|e| e.into_inner());
+    if *dropped { return; }
+    *dropped = true;
+    let Some(index) = read(CHAPTERS_INDEX_KEY).filter(|i| !i.is_empty()) else { return };
+    for series in serde_json::from_str::<Vec<String>>(&index).unwrap_or_default() {
+        write(&format!("chapters.{series}"), "");
+        write(&format!("chapters.time.{series}"), "");
+    }
+    write(CHAPTERS_INDEX_KEY, "");
+}
@@ -0,0 +1,167 @@
+// Transport under the request layer. Every fetch goes through `client()`,
+// so retries, caching and parsing don't touch Aidoku's net imports directly
+// and a host-side harness can answer requests with saved responses instead.
+
+use aidoku::std::{
+    net::{HttpMethod, Request},
+    String, Vec,
+};
+use std::sync::Mutex;
+
+pub struct HttpRequest<'a> {
+    pub method: HttpMethod,
+    pub url: &'a str,
+    pub headers: &'a [(&'a str, &'a str)],
+    pub body: Option<&'a [u8]>,
+}
+
+// The parts of a response the request layer looks at
+pub struct HttpResponse {
+    pub status: i32,
+    pub data: Vec<u8>,
+    // Where the request ended up after redirects
+    pub url: Option<String>,
+    pub retry_after: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_encoding: Option<String>,
+}
+
+pub trait HttpClient: Sync {
+    // None when no response came back at all
+    fn send(&self, request: &HttpRequest) -> Option<HttpResponse>;
+}
+
+// The real thing: Aidoku's net module
+pub struct AidokuClient;
+
+impl HttpClient for AidokuClient {
+    fn send(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        let mut r = Request::new(request.url, request.method);
+        for (key, value) in request.headers {
+            r = r.header(key, value);
+        }
+        if let Some(body) = request.body {
+            r = r.body(body);
+        }
+        let response = r.send().ok()?;
+        Some(HttpResponse {
+            status: response.status_code(),
+            data: response.get_data().ok()?,
+            url: response.get_url(),
+            retry_after: response.get_header("Retry-After"),
+            etag: response.get_header("ETag"),
+            last_modified: response.get_header("Last-Modified"),
+            content_encoding: response.get_header("Content-Encoding"),
+        })
+    }
+}
+
+static CLIENT: Mutex<&'static dyn HttpClient> = Mutex::new(&AidokuClient);
+
+pub fn client() -> &'static dyn HttpClient {
+    *CLIENT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn set_client(client: &'static dyn HttpClient) {
+    *CLIENT.lock().unwrap_or_else(|e| e.into_inner()) = client;
+}
+
+// Saved responses for host-side tests: each URL answers with its fixture,
+// anything else with a 404, and every request is recorded in order along
+// with its headers.
+#[cfg(test)]
+type Sent = (String, Vec<(String, String)>);
+
+#[cfg(test)]
+pub struct MockClient {
+    routes: Mutex<Vec<(String, i32, Vec<u8>)>>,
+    requests: Mutex<Vec<Sent>>,
+}
+
+#[cfg(test)]
+impl MockClient {
+    pub const fn new() -> Self {
+        MockClient { routes: Mutex::new(Vec::new()), requests: Mutex::new(Vec::new()) }
+    }
+
+    pub fn serve(&self, url: &str, body: &[u8]) {
+        self.serve_status(url, 200, body);
+    }
+
+    pub fn serve_status(&self, url: &str, status: i32, body: &[u8]) {
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        routes.retain(|(u, _, _)| u != url);
+        routes.push((String::from(url), status, body.to_vec()));
+    }
+
+    // URLs requested since the last reset
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).iter().map(|(u, _)| u.clone()).collect()
+    }
+
+    // Headers of the last request to `url`
+    pub fn headers_for(&self, url: &str) -> Vec<(String, String)> {
+        let requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        requests.iter().rev().find(|(u, _)| u == url).map(|(_, h)| h.clone()).unwrap_or_default()
+    }
+
+    pub fn reset(&self) {
+        self.routes.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+#[cfg(test)]
+impl HttpClient for MockClient {
+    fn send(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        let headers = request.headers.iter().map(|(k, v)| (String::from(*k), String::from(*v))).collect();
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).push((String::from(request.url), headers));
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let (status, data) = routes
+            .iter()
+            .find(|(u, _, _)| u == request.url)
+            .map_or((404, Vec::new()), |(_, status, data)| (*status, data.clone()));
+        Some(HttpResponse {
+            status,
+            data,
+            url: Some(String::from(request.url)),
+            retry_after: None,
+            etag: None,
+            last_modified: None,
+            content_encoding: None,
+        })
+    }
+}
+
+#[cfg(test)]
+static MOCK: MockClient = MockClient::new();
+#[cfg(test)]
+static MOCK_HELD: Mutex<()> = Mutex::new(());
+
+// Installs the (emptied) mock for one test. The client, settings and caches
+// are process-wide, so tests that go through them hold the guard throughout.
+#[cfg(test)]
+pub fn mock() -> (std::sync::MutexGuard<'static, ()>, &'static MockClient) {
+    let held = MOCK_HELD.lock().unwrap_or_else(|e| e.into_inner());
+    MOCK.reset();
+    set_client(&MOCK);
+    (held, &MOCK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_serves_fixtures_and_records_requests() {
+        let (_held, mock) = mock();
+        mock.serve("https://example.com/manga/a/", b"<html></html>");
+        let get = |url| HttpRequest { method: HttpMethod::Get, url, headers: &[], body: None };
+        let found = client().send(&get("https://example.com/manga/a/")).unwrap();
+        assert_eq!((found.status, found.data.as_slice()), (200, b"<html></html>".as_slice()));
+        let missing = client().send(&get("https://example.com/manga/b/")).unwrap();
+        assert_eq!(missing.status, 404);
+        assert_eq!(mock.requests(), ["https://example.com/manga/a/", "https://example.com/manga/b/"]);
+    }
+}
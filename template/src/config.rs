@@ -0,0 +1,6 @@
+// Selector profiles, theme markers and image filter lists from
+// selectors.json, validated and turned into constants by build.rs.
+
+use crate::theme::Selectors;
+
+include!(concat!(env!("OUT_DIR"), "/config.rs"));
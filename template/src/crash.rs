@@ -0,0 +1,54 @@
+// A panic in WASM traps the module, and all Aidoku shows is a generic
+// "source error". The hook logs where it happened and keeps the message in
+// defaults. The next request logs it again and carries on: failing that
+// request would drop an unrelated series from a library refresh. The
+// Diagnostics listing shows the message until it's been read there.
+
+use aidoku::{prelude::*, std::String};
+use std::sync::{Mutex, Once};
+
+use crate::host::{defaults_get, defaults_set, StringRef};
+
+const KEY: &str = "crash.last";
+
+static INSTALL: Once = Once::new();
+// Logged once per crash, not on every call after it
+static LOGGED: Mutex<bool> = Mutex::new(false);
+
+fn read() -> String {
+    defaults_get(KEY).and_then(|v| v.as_string()).map(|v| v.read()).unwrap_or_default()
+}
+
+fn write(value: &str) {
+    defaults_set(KEY, StringRef::from(value).0);
+}
+
+pub fn install() {
+    INSTALL.call_once(|| {
+        std::panic::set_hook(Box::new(|info| {
+            let message = info.to_string();
+            println!("[{}] {message}", crate::site().name);
+            write(&message);
+            if let Ok(mut logged) = LOGGED.try_lock() { *logged = false; }
+        }));
+    });
+}
+
+// Logs the crash recorded by an earlier request, if any
+pub fn check() {
+    let mut logged = LOGGED.lock().unwrap_or_else(|e| e.into_inner());
+    if *logged { return; }
+    *logged = true;
+    let last = read();
+    if !last.is_empty() {
+        println!("[{}] the last request crashed: {last}", crate::site().name);
+    }
+}
+
+// The recorded crash, cleared once taken; for the Diagnostics report
+pub fn take() -> Option<String> {
+    let last = read();
+    if last.is_empty() { return None; }
+    write("");
+    Some(last)
+}
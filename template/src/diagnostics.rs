@@ -0,0 +1,149 @@
+// "Diagnostics" listing: loads the homepage, the first series on it and that
+// series' first chapter, and lists the selectors that matched nothing on
+// each. Every row is a listing card so the report can be screenshotted or
+// copied into an issue when the site changes under the source.
+
+use aidoku::{
+    std::{String, Vec},
+    Manga, MangaContentRating, MangaPageResult, MangaStatus, MangaViewer,
+};
+
+use std::sync::Mutex;
+
+use crate::{
+    crash, get_ajax_chapters, get_dom,
+    host::Node, parse_chapters, parse_manga_items, select, settings,
+    theme::{self, Selectors, Theme},
+    url,
+};
+
+// Report rows are tappable like any card; their IDs start with this so the
+// entry points answer them here instead of fetching /manga/diagnostics-N/
+const ID_PREFIX: &str = "diagnostics-";
+
+// Titles and links of the last report, so an opened row can show what it said
+static LAST: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+pub fn is_row(id: &str) -> bool {
+    id.starts_with(ID_PREFIX)
+}
+
+// Details of a report row: the row itself, or a pointer back to the listing
+// once the report is gone (the app was restarted)
+pub fn details(id: &str) -> Manga {
+    let n = id[ID_PREFIX.len()..].parse::<usize>().unwrap_or(0);
+    let last = LAST.lock().unwrap_or_else(|e| e.into_inner());
+    let mut m = match last.get(n) {
+        Some((title, link)) => row(n, title.clone(), link),
+        None => row(n, String::from("Diagnostics"), &settings::base_url()),
+    };
+    m.description = String::from("Open the Diagnostics listing again to rerun the checks.");
+    m
+}
+
+// Selectors each page is expected to match
+const HOME: &[&str] = &["list_item", "title", "href", "cover", "popular_today", "popular_weekly", "popular_monthly", "popular_all_time"];
+const SERIES: &[&str] = &["details_title", "manga_meta", "info_row", "summary", "genres", "status", "chapter_list", "chapter_date"];
+const CHAPTER: &[&str] = &["page_image", "chapter_parent"];
+
+fn row(n: usize, title: String, link: &str) -> Manga {
+    Manga {
+        id: format!("{ID_PREFIX}{n}"),
+        cover: String::new(),
+        title,
+        author: String::new(),
+        artist: String::new(),
+        description: String::new(),
+        url: link.into(),
+        categories: Vec::new(),
+        status: MangaStatus::Unknown,
+        nsfw: MangaContentRating::Safe,
+        viewer: MangaViewer::Default,
+    }
+}
+
+fn theme_name(theme: Theme) -> &'static str {
+    match theme {
+        Theme::Madara => "Madara",
+        Theme::MangaStream => "MangaStream",
+        Theme::Unknown => "unrecognized",
+    }
+}
+
+struct Report {
+    rows: Vec<Manga>,
+}
+
+impl Report {
+    fn finish(self) -> MangaPageResult {
+        *LAST.lock().unwrap_or_else(|e| e.into_inner()) = self.rows.iter().map(|m| (m.title.clone(), m.url.clone())).collect();
+        MangaPageResult { manga: self.rows, has_more: false }
+    }
+
+    fn push(&mut self, title: String, link: &str) {
+        let n = self.rows.len();
+        self.rows.push(row(n, title, link));
+    }
+
+    // One row for the page's theme, then one per selector with no matches
+    fn check(&mut self, page: &str, link: &str, dom: &Node, s: &Selectors, fields: &[&str]) {
+        self.push(format!("{page}: {} theme", theme_name(theme::detect(dom))), link);
+        let empty: Vec<&str> = fields
+            .iter()
+            .copied()
+            .filter(|f| s.field(f).is_some_and(|sel| select(dom, sel).is_empty()))
+            .collect();
+        if empty.is_empty() {
+            self.push(format!("\u{2713} {page}: all {} selectors matched", fields.len()), link);
+        }
+        for field in empty {
+            self.push(format!("\u{2717} {page}: {field} matched nothing"), link);
+        }
+    }
+}
+
+pub fn run() -> MangaPageResult {
+    let base = settings::base_url();
+    // Detected afresh: a theme switch is one of the things this looks for
+    theme::forget();
+    let mut report = Report { rows: Vec::new() };
+    report.push(format!("Base URL: {base}"), &base);
+    if let Some(crash) = crash::take() {
+        report.push(format!("\u{2717} Last crash: {crash}"), &base);
+    }
+
+    let Ok(home) = get_dom(&base) else {
+        report.push(String::from("\u{2717} Homepage couldn't be loaded"), &base);
+        return report.finish();
+    };
+    let s = theme::selectors(&home);
+    report.check("Homepage", &base, &home, s, HOME);
+
+    let Some(series) = parse_manga_items(&home, s, |s| s.list_item).into_iter().next() else {
+        report.push(String::from("\u{2717} No series on the homepage to check"), &base);
+        return report.finish();
+    };
+    let Ok(page) = get_dom(&series.url) else {
+        report.push(format!("\u{2717} {} couldn't be loaded", series.title), &series.url);
+        return report.finish();
+    };
+    let s = theme::selectors(&page);
+    report.check("Series", &series.url, &page, s, SERIES);
+
+    let mut chapters = parse_chapters(&page, s, &series.title);
+    if chapters.is_empty() {
+        chapters = get_ajax_chapters(&page, s, &series.url).map(|f| parse_chapters(&f, s, &series.title)).unwrap_or_default();
+        let found = if chapters.is_empty() { "nothing either" } else { "the list" };
+        report.push(format!("Series: AJAX chapter list returned {found}"), &series.url);
+    }
+    let Some(chapter) = chapters.first() else {
+        report.push(String::from("\u{2717} No chapter to check"), &series.url);
+        return report.finish();
+    };
+    let link = url::id_url(&chapter.id);
+    match get_dom(&link) {
+        Ok(dom) => report.check("Chapter", &link, &dom, theme::selectors(&dom), CHAPTER),
+        Err(_) => report.push(String::from("\u{2717} Chapter couldn't be loaded"), &link),
+    }
+    report.finish()
+}
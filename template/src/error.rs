@@ -0,0 +1,56 @@
+// Errors raised by the request/parse path, surfaced to Aidoku as messages
+// instead of trapping the WASM module.
+
+use aidoku::{error::AidokuError, std::String};
+use core::fmt;
+
+#[derive(Debug)]
+pub enum SourceError {
+    // The request never got a response (DNS, TLS, connection reset, ...)
+    Network(String),
+    // No usable response by settings::request_timeout(), so no more retries
+    // or mirrors were tried
+    Timeout(String),
+    // The server answered with an error status, after retries
+    Status(i32),
+    // Cloudflare/WAF interstitial instead of the real page
+    Challenge,
+    // The response body couldn't be parsed as HTML
+    Parse,
+    // Something the source expected to find wasn't there
+    Missing(&'static str),
+    // Strict mode: the named theme::Selectors field matched nothing where the
+    // page can't genuinely be empty
+    Layout(&'static str),
+    // A link Aidoku can't open as a series or chapter; says where to look instead
+    Unsupported(String),
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Network(url) => write!(f, "Couldn't reach the site ({url}). Check the connection or the base URL"),
+            SourceError::Timeout(url) => write!(f, "The site didn't answer in time ({url}); stopped retrying. It may be down, try again later"),
+            // Worded for what the user can do about each status
+            SourceError::Status(404 | 410) => write!(f, "Not found on the site. The series may have been removed or renamed"),
+            SourceError::Status(403) => write!(f, "Access denied (HTTP 403). Open the site in WebView, then try again"),
+            SourceError::Status(429) => write!(f, "Temporarily blocked for too many requests. Try again later"),
+            SourceError::Status(code @ 500..=599) => write!(f, "The site is having trouble (HTTP {code}). Try again later"),
+            SourceError::Status(code) => write!(f, "Site returned HTTP {code}"),
+            SourceError::Challenge => write!(f, "Blocked by Cloudflare. Open the site in WebView to solve the challenge"),
+            SourceError::Parse => write!(f, "Couldn't parse the page"),
+            SourceError::Missing(what) => write!(f, "Missing {what}"),
+            SourceError::Layout(field) => write!(
+                f,
+                "Nothing on the page matched the {field} selector. The site's layout has likely changed (this isn't a ban); see Selector overrides or turn off Strict mode"
+            ),
+            SourceError::Unsupported(hint) => write!(f, "{hint}"),
+        }
+    }
+}
+
+impl From<SourceError> for AidokuError {
+    fn from(e: SourceError) -> Self {
+        AidokuError::message(e.to_string())
+    }
+}
@@ -0,0 +1,92 @@
+// WordPress RSS feeds. Far lighter than the themed archive and unaffected by
+// theme changes, so "Latest" can be built from /feed/ instead.
+
+use aidoku::std::{String, Vec};
+
+use crate::parse::{self, decode_entities};
+
+pub struct FeedItem {
+    pub title: String,
+    pub link: String,
+    pub cover: Option<String>,
+    pub categories: Vec<String>,
+}
+
+// Text of the first <tag>...</tag> in `xml`, CDATA unwrapped. Scanned by
+// hand: the HTML parser treats <link> as a void element and loses its text.
+pub fn tag<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{name}");
+    let mut from = 0;
+    let start = loop {
+        let i = from + xml[from..].find(&open)?;
+        let after = i + open.len();
+        // "<link" must not match "<linkedin" etc.
+        match xml[after..].chars().next() {
+            Some('>') | Some(' ') | Some('/') => break xml[after..].find('>')? + after + 1,
+            _ => from = after,
+        }
+    };
+    let end = start + xml[start..].find(&format!("</{name}>"))?;
+    let body = xml[start..end].trim();
+    Some(body.strip_prefix("<![CDATA[").and_then(|b| b.strip_suffix("]]>")).unwrap_or(body).trim())
+}
+
+// First attribute value of `attr` inside `xml`, e.g. media:content url="..."
+fn attr_value<'a>(xml: &'a str, attr: &str) -> Option<&'a str> {
+    let key = format!("{attr}=\"");
+    let start = xml.find(&key)? + key.len();
+    Some(&xml[start..start + xml[start..].find('"')?])
+}
+
+// Feed image: media:content/thumbnail, else the first <img> in the content
+fn cover(item: &str) -> Option<String> {
+    let media = ["<media:content", "<media:thumbnail", "<enclosure"]
+        .iter()
+        .find_map(|t| item.find(t).and_then(|i| attr_value(&item[i..], "url")));
+    let inline = || {
+        let html = tag(item, "content:encoded").or_else(|| tag(item, "description"))?;
+        let html = decode_entities(html);
+        let i = html.find("<img")?;
+        attr_value(&html[i..], "src").map(String::from)
+    };
+    media.map(String::from).or_else(inline).filter(|c| !c.is_empty())
+}
+
+// Every <category>, which WordPress fills with the post's genres
+fn categories(item: &str) -> Vec<String> {
+    item.split("<category")
+        .skip(1)
+        .filter_map(|c| tag(&format!("<category{c}"), "category").map(decode_entities))
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+// Raw markup of each <item>
+pub fn item_bodies(xml: &str) -> Vec<&str> {
+    xml.split("<item").skip(1).filter_map(|item| item.find("</item>").map(|end| &item[..end])).collect()
+}
+
+// <pubDate>, RFC 822: "Mon, 06 May 2024 10:00:00 +0000" -> epoch seconds
+pub fn pub_date(item: &str) -> Option<f64> {
+    let date = tag(item, "pubDate")?;
+    let words: Vec<&str> = date.split_whitespace().filter(|w| !w.ends_with(',')).collect();
+    let [day, month, year, time, zone] = words[..] else { return None };
+    let midnight = parse::parse_date(&format!("{day} {month} {year}"))?;
+    let clock: Vec<f64> = time.split(':').filter_map(|n| n.parse().ok()).collect();
+    let seconds = clock.iter().zip([3600.0, 60.0, 1.0]).map(|(n, unit)| n * unit).sum::<f64>();
+    // "+0700" is 7 hours ahead of UTC; named zones ("GMT") count as UTC
+    let offset = zone.parse::<i32>().map_or(0.0, |z| f64::from(z / 100 * 3600 + z % 100 * 60));
+    Some(midnight + seconds - offset)
+}
+
+pub fn items(xml: &str) -> Vec<FeedItem> {
+    item_bodies(xml)
+        .into_iter()
+        .filter_map(|item| {
+            let title = decode_entities(tag(item, "title")?);
+            let link = tag(item, "link")?.trim().to_string();
+            if title.is_empty() || link.is_empty() { return None; }
+            Some(FeedItem { title, link, cover: cover(item), categories: categories(item) })
+        })
+        .collect()
+}
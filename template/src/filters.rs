@@ -0,0 +1,107 @@
+// Every search filter the template understands. Aidoku reports filters by
+// name and select/sort options by index only, so each source's
+// res/filters.json has to list these names (or their translations in
+// i18n.rs), with these options in this order.
+
+// The Madara query value of one option of a select or sort filter; its
+// title lives in filters.json
+pub struct Choice {
+    pub param: &'static str,
+}
+
+pub struct Select {
+    pub name: &'static str,
+    pub choices: &'static [Choice],
+}
+
+impl Select {
+    // Out-of-range indices fall back to the first choice ("Any")
+    pub fn param(&self, index: i32) -> &'static str {
+        let choice = usize::try_from(index).ok().and_then(|i| self.choices.get(i));
+        choice.or(self.choices.first()).map_or("", |c| c.param)
+    }
+}
+
+// The title is only there to keep these lists readable next to filters.json
+const fn choice(_title: &'static str, param: &'static str) -> Choice {
+    Choice { param }
+}
+
+// -> m_orderby
+pub const SORT: Select = Select {
+    name: "Sort",
+    choices: &[
+        choice("Latest", "latest"),
+        choice("Trending", "trending"),
+        choice("Most Views", "views"),
+        choice("Rating", "rating"),
+        choice("A-Z", "alphabet"),
+        choice("New", "new-manga"),
+    ],
+};
+
+// -> status[]
+pub const STATUS: Select = Select {
+    name: "Status",
+    choices: &[choice("Any", ""), choice("Ongoing", "on-going"), choice("Completed", "end"), choice("Hiatus", "on-hold")],
+};
+
+// -> type
+pub const TYPE: Select = Select {
+    name: "Type",
+    choices: &[choice("Any", ""), choice("Manga", "manga"), choice("Manhwa", "manhwa"), choice("Manhua", "manhua")],
+};
+
+// -> adult; 0 hides 18+ entries, 1 shows only them
+pub const ADULT: Select = Select {
+    name: "Adult Content",
+    choices: &[choice("Any", ""), choice("Exclude 18+", "0"), choice("Only 18+", "1")],
+};
+
+// Not a site parameter: Madara's search can't filter by score, so cards
+// rated below this (on a 5-star scale) are dropped after parsing
+pub const MIN_RATING: Select = Select {
+    name: "Minimum Rating",
+    choices: &[choice("Any", ""), choice("3+", "3"), choice("4+", "4"), choice("4.5+", "4.5")],
+};
+
+// Free-text filters besides the title and author ones Aidoku has built in
+pub const YEAR: &str = "Year";
+pub const ARTIST: &str = "Artist";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n;
+    use serde_json::Value;
+
+    const SOURCES: &[(&str, &str)] = &[
+        ("ravenscans", include_str!("../../sources/ravenscans/res/filters.json")),
+        ("ravenscans-id", include_str!("../../sources/ravenscans-id/res/filters.json")),
+    ];
+
+    fn find<'a>(filters: &'a [Value], kind: &str, name: &str) -> Option<&'a Value> {
+        filters.iter().find(|f| {
+            f["type"] == kind && f["name"].as_str().is_some_and(|n| i18n::canonical(n) == name)
+        })
+    }
+
+    // Options are reported by index, so a missing or reordered entry in a
+    // source's filters.json silently maps to the wrong query value
+    #[test]
+    fn filters_json_matches_template() {
+        for (source, json) in SOURCES {
+            let filters: Vec<Value> = serde_json::from_str(json).unwrap();
+            let selects = [("sort", &SORT), ("select", &STATUS), ("select", &TYPE), ("select", &ADULT), ("select", &MIN_RATING)];
+            for (kind, select) in selects {
+                let filter = find(&filters, kind, select.name)
+                    .unwrap_or_else(|| panic!("{source}: no {kind} filter named {}", select.name));
+                let options = filter["options"].as_array().map_or(0, Vec::len);
+                assert_eq!(options, select.choices.len(), "{source}: option count of {}", select.name);
+            }
+            for name in [YEAR, ARTIST] {
+                assert!(find(&filters, "text", name).is_some(), "{source}: no text filter named {name}");
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+// The site's genre taxonomy, scraped from its genre links and kept in
+// defaults. Aidoku's genre filters are fixed by res/filters.json, so this
+// can't add options to the list; it keeps the listed ones pointed at the
+// slugs the site currently uses, and resolves genres that only appear on
+// series pages (and in "Genre: X" listings) to their real archive.
+
+use aidoku::std::{String, Vec};
+use std::sync::Mutex;
+
+use crate::{
+    attr, get_dom_cached,
+    host::{current_date, defaults_get, defaults_set, StringRef},
+    select, settings, text, theme,
+};
+
+// Sites add genres rarely; once a day is plenty
+const TTL: f64 = 24.0 * 60.0 * 60.0;
+
+const BODY_KEY: &str = "genres.body";
+const TIME_KEY: &str = "genres.time";
+
+// (name, slug) pairs, in the order the site lists them
+static LOADED: Mutex<Option<Vec<(String, String)>>> = Mutex::new(None);
+
+fn read(key: &str) -> String {
+    defaults_get(key).and_then(|v| v.as_string()).map(|v| v.read()).unwrap_or_default()
+}
+
+fn write(key: &str, value: &str) {
+    defaults_set(key, StringRef::from(value).0);
+}
+
+// "Action (120)" -> "Action"; genre widgets append post counts
+fn strip_count(name: &str) -> &str {
+    let name = name.trim();
+    match name.strip_suffix(')').and_then(|n| n.rsplit_once('(')) {
+        Some((head, count)) if count.trim().chars().all(|c| c.is_ascii_digit()) => head.trim_end(),
+        _ => name,
+    }
+}
+
+fn scrape() -> Vec<(String, String)> {
+    let Ok(dom) = get_dom_cached(&settings::base_url()) else { return Vec::new() };
+    let s = theme::selectors(&dom);
+    let mut genres: Vec<(String, String)> = Vec::new();
+    for link in select(&dom, s.genre_link) {
+        let Some(href) = attr(&link, "href") else { continue };
+        let Some(slug) = href.trim_end_matches('/').rsplit('/').next().filter(|slug| !slug.is_empty()) else { continue };
+        let name = strip_count(&text(&link)).to_string();
+        if name.is_empty() || genres.iter().any(|(_, known)| known == slug) { continue; }
+        genres.push((name, slug.into()));
+    }
+    genres
+}
+
+fn load() -> Vec<(String, String)> {
+    let fetched = read(TIME_KEY).parse::<f64>().unwrap_or(0.0);
+    if current_date() - fetched < TTL {
+        if let Ok(stored) = serde_json::from_str(&read(BODY_KEY)) { return stored; }
+    }
+    let genres = scrape();
+    // An empty scrape (site down, menu moved) isn't stored over a good list
+    if genres.is_empty() {
+        return serde_json::from_str(&read(BODY_KEY)).unwrap_or_default();
+    }
+    if let Ok(body) = serde_json::to_string(&genres) {
+        write(BODY_KEY, &body);
+        write(TIME_KEY, &current_date().to_string());
+    }
+    genres
+}
+
+// Current slug for a genre name, matched case-insensitively
+pub fn resolve(name: &str) -> Option<String> {
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    let genres = loaded.get_or_insert_with(load);
+    let name = name.trim();
+    genres.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, slug)| slug.clone())
+}
+
+// Forget the list, e.g. after the base URL changed
+pub fn clear() {
+    *LOADED.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    write(TIME_KEY, "");
+}
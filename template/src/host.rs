@@ -0,0 +1,14 @@
+// The Aidoku imports the template uses besides net: the HTML parser, the
+// defaults store and the clock. Under `cargo test` there's no host to import
+// them from, so native.rs stands in with the same shapes.
+
+#[cfg(not(test))]
+pub use aidoku::std::{
+    current_date,
+    defaults::{defaults_get, defaults_set},
+    html::Node,
+    StringRef,
+};
+
+#[cfg(test)]
+pub use crate::native::{current_date, defaults_get, defaults_set, Node, StringRef};
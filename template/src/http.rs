@@ -0,0 +1,435 @@
+// Request layer: headers, retries and HTML parsing for every site request.
+
+use aidoku::{
+    error::Result,
+    std::{net::HttpMethod, String, Vec},
+};
+use std::sync::Mutex;
+
+use crate::{
+    cache,
+    client::{client, HttpRequest, HttpResponse},
+    error::SourceError,
+    host::{current_date, Node},
+    inflate, memory, settings,
+};
+
+// Gateway blips worth another try: bad gateway, unavailable
+const RETRY_STATUSES: &[i32] = &[502, 503];
+const MAX_RETRIES: u32 = 3;
+
+// Markers of Cloudflare's "Just a moment..." and Turnstile interstitials
+const CHALLENGE_MARKERS: &[&str] = &[
+    "<title>just a moment...</title>",
+    "cf-challenge",
+    "cf_chl_opt",
+    "challenges.cloudflare.com/turnstile",
+    "<title>attention required! | cloudflare</title>",
+];
+
+// Registrar and parking-service pages left on a lapsed domain
+const PARKED_MARKERS: &[&str] = &[
+    "this domain is for sale",
+    "this domain may be for sale",
+    "buy this domain",
+    "domain is parked",
+    "sedoparking.com",
+    "parkingcrew.net",
+    "bodis.com",
+];
+
+// Stripped-down mobile layouts (WPtouch, Jetpack's mobile theme, AMP) that
+// some setups serve to phone UAs, without the lists and widgets the theme
+// selectors look for
+const MOBILE_MARKERS: &[&str] = &["wptouch", "jetpack-mobile-theme", "<html amp", "<html \u{26a1}", "amp-boilerplate"];
+
+fn is_mobile_layout(body: &[u8]) -> bool {
+    // The markers are on <html>, in <head> or on <body>'s class
+    let head = &body[..body.len().min(64 * 1024)];
+    MOBILE_MARKERS.iter().any(|m| head.windows(m.len()).any(|w| w.eq_ignore_ascii_case(m.as_bytes())))
+}
+
+fn is_parked(body: &[u8]) -> bool {
+    // Parking pages are small; anything past this is a real site
+    if body.len() > 32 * 1024 { return false; }
+    PARKED_MARKERS.iter().any(|m| body.windows(m.len()).any(|w| w.eq_ignore_ascii_case(m.as_bytes())))
+}
+
+// Challenge pages come back as 403/503, or occasionally as a 200 that would
+// otherwise parse into an empty list
+fn is_challenge(status: i32, body: &[u8]) -> bool {
+    // Markers sit in <head>; no need to scan (or copy) the whole document
+    let head = &body[..body.len().min(8192)];
+    let has = |m: &str| head.windows(m.len()).any(|w| w.eq_ignore_ascii_case(m.as_bytes()));
+    CHALLENGE_MARKERS.iter().any(|m| has(m)) || (status == 403 && has("cloudflare"))
+}
+
+// Takes the buffer over when it's valid UTF-8, which it nearly always is;
+// only bad bytes force a lossy copy
+fn into_string(data: Vec<u8>) -> String {
+    String::from_utf8(data).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+// "https://host" part of a URL
+fn origin(url: &str) -> Option<&str> {
+    let start = url.find("://")? + 3;
+    let end = url[start..].find(['/', '?', '#']).map_or(url.len(), |i| start + i);
+    Some(&url[..end])
+}
+
+// The site hops domains with a 301 from the old one. When a request to the
+// current base lands on another origin, make that the base from now on.
+fn follow_domain_move(requested: &str, landed: &str) {
+    let base = settings::base_url();
+    let (Some(from), Some(to)) = (origin(requested), origin(landed)) else { return };
+    if from.eq_ignore_ascii_case(&base) && !to.eq_ignore_ascii_case(from) {
+        settings::remember_redirect(to);
+    }
+}
+
+// Header sets copied from real browsers. The WAF fingerprints the whole
+// combination, so each profile's UA, Accept headers and client hints come
+// from the same browser.
+struct Profile {
+    user_agent: &'static str,
+    // Sent with every request, images included
+    hints: &'static [(&'static str, &'static str)],
+    // Page requests only
+    document: &'static [(&'static str, &'static str)],
+}
+
+const SAFARI: Profile = Profile {
+    user_agent: "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1",
+    hints: &[],
+    document: &[
+        ("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"),
+        ("Accept-Language", "en-US,en;q=0.9"),
+    ],
+};
+
+const CHROME: Profile = Profile {
+    user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36",
+    hints: &[
+        ("sec-ch-ua", "\"Not/A)Brand\";v=\"8\", \"Chromium\";v=\"126\", \"Google Chrome\";v=\"126\""),
+        ("sec-ch-ua-mobile", "?0"),
+        ("sec-ch-ua-platform", "\"Windows\""),
+    ],
+    document: &[
+        ("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8"),
+        ("Accept-Language", "en-US,en;q=0.9"),
+        ("Upgrade-Insecure-Requests", "1"),
+    ],
+};
+
+// Set once the site has answered the phone profile with a mobile layout;
+// the rest of the session asks for the desktop one
+static FORCE_DESKTOP: Mutex<bool> = Mutex::new(false);
+
+fn profile() -> &'static Profile {
+    if *FORCE_DESKTOP.lock().unwrap_or_else(|e| e.into_inner()) { return &CHROME; }
+    match settings::header_profile().as_str() {
+        "chrome" => &CHROME,
+        _ => &SAFARI,
+    }
+}
+
+// False when the session was already on desktop headers
+fn switch_to_desktop() -> bool {
+    if core::ptr::eq(profile(), &CHROME) { return false; }
+    *FORCE_DESKTOP.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    true
+}
+
+// Back to the configured profile, e.g. after it was changed
+pub fn reset_layout() {
+    *FORCE_DESKTOP.lock().unwrap_or_else(|e| e.into_inner()) = false;
+}
+
+// The user's UA override, else the profile's
+pub fn user_agent() -> String {
+    let custom = settings::user_agent();
+    if custom.is_empty() { profile().user_agent.into() } else { custom }
+}
+
+// Headers sent with every request, images included: UA and client hints,
+// plus the user's Cloudflare and session cookies
+pub fn request_headers() -> Vec<(&'static str, String)> {
+    let mut headers = vec![("User-Agent", user_agent())];
+    headers.extend(profile().hints.iter().map(|(k, v)| (*k, String::from(*v))));
+    let cookie = [settings::cookie(), settings::session_cookie()]
+        .into_iter()
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ");
+    if !cookie.is_empty() { headers.push(("Cookie", cookie)); }
+    headers
+}
+
+struct Reply {
+    status: i32,
+    data: Vec<u8>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+// The base domain is down or parked: repeat the request on each mirror and
+// switch the base to the first one that really answers
+fn failover(request: &HttpRequest, deadline: f64) -> Option<HttpResponse> {
+    let base = settings::base_url();
+    let path = request.url.strip_prefix(base.as_str())?;
+    for mirror in settings::mirrors().into_iter().filter(|m| !m.eq_ignore_ascii_case(&base)) {
+        if current_date() >= deadline { break; }
+        let url = format!("{mirror}{path}");
+        let Some(response) = client().send(&HttpRequest { url: &url, ..*request }) else { continue };
+        if response.status >= 500 || is_parked(&response.data) { continue; }
+        debug!("mirror: {base} unreachable, switching to {mirror}");
+        settings::remember_redirect(&mirror);
+        return Some(response);
+    }
+    None
+}
+
+// Set once a request times out, so later requests in the same entry-point
+// call fail straight away instead of waiting on the same hung origin for
+// each sub-page or fallback. Cleared by begin_call.
+static TIMED_OUT: Mutex<bool> = Mutex::new(false);
+
+// Start of an entry-point call
+pub fn begin_call() {
+    clear_call_memo();
+    *TIMED_OUT.lock().unwrap_or_else(|e| e.into_inner()) = false;
+}
+
+// Send with retries. The legacy API has no sleep import, so there is no
+// backoff: a 502/503 is retried straight away, and a 429 or any response
+// asking to come back later (Retry-After) fails with its status for the
+// caller to retry, rather than spinning on the clock.
+// net::Request has no timeout and can't be cancelled, so a request that
+// hangs waits for the app's own network timeout. What the deadline bounds is
+// everything around it: no retry or mirror starts once it's passed.
+// `document` is false for image bytes, which get neither the page Accept
+// headers nor the default Referer, and are never inflated or taken for a
+// mobile layout
+fn send(
+    method: HttpMethod,
+    url: &str,
+    body: Option<&[u8]>,
+    extra: &[(&str, &str)],
+    document: bool,
+) -> core::result::Result<Reply, SourceError> {
+    if *TIMED_OUT.lock().unwrap_or_else(|e| e.into_inner()) {
+        return Err(SourceError::Timeout(url.into()));
+    }
+    let deadline = current_date() + settings::request_timeout();
+    let referer = format!("{}/", settings::base_url());
+    let mut attempt = 0;
+    loop {
+        // Rebuilt per attempt, since a mobile layout switches the profile
+        let common = request_headers();
+        let mut headers: Vec<(&str, &str)> = common.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        if document {
+            headers.extend_from_slice(profile().document);
+            headers.push(("Referer", &referer));
+        }
+        // The caller's headers replace any of the same name
+        for (name, value) in extra {
+            headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+            headers.push((name, value));
+        }
+        let request = HttpRequest { method, url, headers: &headers, body };
+
+        let response = client().send(&request).filter(|r| !is_parked(&r.data)).or_else(|| failover(&request, deadline));
+        let Some(response) = response else {
+            if current_date() < deadline { return Err(SourceError::Network(url.into())); }
+            debug!("timeout: {url}");
+            *TIMED_OUT.lock().unwrap_or_else(|e| e.into_inner()) = true;
+            return Err(SourceError::Timeout(url.into()));
+        };
+        let (status, mut data) = (response.status, response.data);
+        debug!("{status} {url} ({} bytes)", data.len());
+        // The host negotiates compression and normally decodes it; a document
+        // it passed through still compressed is inflated here. Images are
+        // left alone, whatever the header claims.
+        let compressed = response.content_encoding.as_deref().is_some_and(|e| {
+            let e = e.trim();
+            e.eq_ignore_ascii_case("gzip") || e.eq_ignore_ascii_case("deflate")
+        });
+        if document && compressed {
+            if let Some(decoded) = inflate::decode(&data) { data = decoded; }
+        }
+
+        // Retrying won't get past a challenge; the user has to solve it
+        if is_challenge(status, &data) {
+            return Err(SourceError::Challenge);
+        }
+        if document && is_mobile_layout(&data) && switch_to_desktop() {
+            debug!("layout: mobile variant served, switching to desktop headers");
+            continue;
+        }
+        // A retry that would start past the deadline isn't made
+        if RETRY_STATUSES.contains(&status)
+            && response.retry_after.is_none()
+            && attempt < MAX_RETRIES
+            && current_date() < deadline
+        {
+            attempt += 1;
+            continue;
+        }
+        if status >= 400 {
+            return Err(SourceError::Status(status));
+        }
+        if let Some(landed) = response.url {
+            follow_domain_move(url, &landed);
+        }
+        return Ok(Reply { status, data, etag: response.etag, last_modified: response.last_modified });
+    }
+}
+
+// Bodies of the plain GETs made during the current entry-point call, so
+// fallback chains that land on the same URL twice fetch it once. Cleared at
+// the start of each call and after a login; capped in case one call walks a
+// long pagination.
+const MAX_CALL_MEMO: usize = 16;
+static CALL_MEMO: Mutex<Vec<(String, Vec<u8>)>> = Mutex::new(Vec::new());
+
+pub fn clear_call_memo() {
+    CALL_MEMO.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+fn get_once(url: &str) -> Result<Vec<u8>> {
+    if let Some((_, body)) = CALL_MEMO.lock().unwrap_or_else(|e| e.into_inner()).iter().find(|(u, _)| u == url) {
+        debug!("memo hit {url}");
+        return Ok(body.clone());
+    }
+    let data = send(HttpMethod::Get, url, None, &[], true)?.data;
+    let mut memo = CALL_MEMO.lock().unwrap_or_else(|e| e.into_inner());
+    if memo.len() == MAX_CALL_MEMO { memo.remove(0); }
+    memo.push((url.into(), data.clone()));
+    Ok(data)
+}
+
+// Helper: GET and parse HTML
+pub fn get_dom(url: &str) -> Result<Node> {
+    parse_html(&get_once(url)?)
+}
+
+// GET for pages that may have been taken down: None on 404/410, so the
+// caller can look elsewhere
+pub fn get_dom_unless_gone(url: &str) -> Result<Option<Node>> {
+    match send(HttpMethod::Get, url, None, &[], true) {
+        Ok(reply) => parse_html(&reply.data).map(Some),
+        Err(SourceError::Status(404 | 410)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// GET through the response cache; for series pages and listings, which are
+// revisited constantly while browsing. Expired entries are revalidated with
+// If-None-Match / If-Modified-Since, and a 304 keeps the cached body.
+fn fetch_cached(url: &str) -> Result<Vec<u8>> {
+    let ttl = settings::cache_ttl();
+    if ttl <= 0.0 {
+        return get_once(url);
+    }
+    if let Some(body) = memory::get(url, ttl) {
+        debug!("memory hit {url}");
+        return Ok(body);
+    }
+    let entry = fetch_entry(url, ttl)?;
+    memory::put(url, entry.time, entry.body.as_bytes());
+    Ok(entry.body.into_bytes())
+}
+
+fn fetch_entry(url: &str, ttl: f64) -> Result<cache::Entry> {
+    let cached = cache::lookup(url);
+    match cached {
+        Some(entry) if current_date() - entry.time < ttl => {
+            debug!("cache hit {url}");
+            return Ok(entry);
+        }
+        _ => {}
+    }
+
+    let mut conditional = Vec::new();
+    if let Some(entry) = &cached {
+        if let Some(etag) = &entry.etag { conditional.push(("If-None-Match", etag.as_str())); }
+        if let Some(date) = &entry.last_modified { conditional.push(("If-Modified-Since", date.as_str())); }
+    }
+    let reply = send(HttpMethod::Get, url, None, &conditional, true)?;
+    if let (304, Some(mut entry)) = (reply.status, cached) {
+        entry.time = current_date();
+        cache::put(url, &entry);
+        return Ok(entry);
+    }
+    let entry = cache::Entry {
+        time: current_date(),
+        body: into_string(reply.data),
+        etag: reply.etag,
+        last_modified: reply.last_modified,
+    };
+    cache::put(url, &entry);
+    Ok(entry)
+}
+
+pub fn get_dom_cached(url: &str) -> Result<Node> {
+    parse_html(&fetch_cached(url)?)
+}
+
+// Raw text through the cache, for feeds and other non-HTML documents
+pub fn get_text_cached(url: &str) -> Result<String> {
+    Ok(into_string(fetch_cached(url)?))
+}
+
+// Seconds the last series page is reused for; long enough to cover the
+// details -> chapter list pair, short enough that a refresh refetches
+const SHARED_TTL: f64 = 30.0;
+
+struct SharedPage {
+    url: String,
+    time: f64,
+    data: Vec<u8>,
+}
+
+static LAST_SERIES: Mutex<Option<SharedPage>> = Mutex::new(None);
+
+// Opening a series calls get_manga_details and get_chapter_list on the same
+// URL back to back; the second call reuses the first one's response
+pub fn get_series_dom(url: &str) -> Result<Node> {
+    let mut last = LAST_SERIES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(page) = last.as_ref().filter(|p| p.url == url && current_date() - p.time < SHARED_TTL) {
+        return parse_html(&page.data);
+    }
+    let data = fetch_cached(url)?;
+    let dom = parse_html(&data)?;
+    *last = Some(SharedPage { url: url.into(), time: current_date(), data });
+    Ok(dom)
+}
+
+pub fn forget_series() {
+    *LAST_SERIES.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+// Image bytes with the full header set and the chapter as Referer, for hosts
+// that turn away Aidoku's own image requests
+pub fn get_image(url: &str, referer: &str) -> Result<Vec<u8>> {
+    let extra = [("Accept", "image/avif,image/webp,image/apng,image/*,*/*;q=0.8"), ("Referer", referer)];
+    Ok(send(HttpMethod::Get, url, None, &extra, false)?.data)
+}
+
+// Form POST (admin-ajax), returning the raw body
+pub fn post_text(url: &str, body: &str) -> Result<String> {
+    let extra = [
+        ("Content-Type", "application/x-www-form-urlencoded"),
+        ("X-Requested-With", "XMLHttpRequest"),
+    ];
+    Ok(into_string(send(HttpMethod::Post, url, Some(body.as_bytes()), &extra, true)?.data))
+}
+
+// Helper: form POST and parse the HTML fragment it returns
+pub fn post_dom(url: &str, body: &str) -> Result<Node> {
+    parse_html(post_text(url, body)?.as_bytes())
+}
+
+pub fn parse_html(data: &[u8]) -> Result<Node> {
+    Node::new(data).map_err(|_| SourceError::Parse.into())
+}
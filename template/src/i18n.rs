@@ -0,0 +1,64 @@
+// Translated listing and filter names. Aidoku reports listings and filters
+// by the name in the source's JSON, and the template matches on the English
+// names, so a source written in another language maps its names back
+// through here, and the few messages the template shows itself go the other
+// way. Settings are read by key only; their titles and subtitles can be
+// translated freely in each source's settings.json.
+//
+// A new language is one more column: add it to Strings and to every row.
+
+use crate::site;
+
+struct Strings {
+    en: &'static str,
+    id: &'static str,
+}
+
+const fn row(en: &'static str, id: &'static str) -> Strings {
+    Strings { en, id }
+}
+
+const STRINGS: &[Strings] = &[
+    // Listings
+    row("Latest", "Terbaru"),
+    row("Updated Today", "Update Hari Ini"),
+    row("Popular", "Populer"),
+    row("Popular Today", "Populer Hari Ini"),
+    row("Popular Weekly", "Populer Mingguan"),
+    row("Popular Monthly", "Populer Bulanan"),
+    row("Popular All Time", "Populer Sepanjang Masa"),
+    row("New Series", "Seri Baru"),
+    row("Completed", "Tamat"),
+    row("All Series", "Semua Seri"),
+    row("Projects", "Proyek"),
+    row("Random", "Acak"),
+    row("Related Series", "Seri Terkait"),
+    row("Announcements", "Pengumuman"),
+    row("My Bookmarks", "Bookmark Saya"),
+    row("Diagnostics", "Diagnostik"),
+    // Filters; see filters.rs
+    row("Sort", "Urutkan"),
+    row("Type", "Tipe"),
+    row("Adult Content", "Konten Dewasa"),
+    row("Minimum Rating", "Rating Minimum"),
+    row("Year", "Tahun"),
+    row("Artist", "Artis"),
+    // Deep-link hints; see handle_url. {listing}, {query} and {site} are
+    // filled in after translation.
+    row("Open \"{listing}\" in {site}'s listings", "Buka \"{listing}\" di daftar {site}"),
+    row("Search {site} for \"{query}\"", "Cari \"{query}\" di {site}"),
+];
+
+// The English name for a translated one; anything else comes back as is
+pub fn canonical(name: &str) -> &str {
+    STRINGS.iter().find(|s| s.id == name).map_or(name, |s| s.en)
+}
+
+// The English name in the source's language, when there's a translation
+pub fn local(en: &str) -> &str {
+    let row = STRINGS.iter().find(|s| s.en == en);
+    match site().lang {
+        "id" => row.map_or(en, |s| s.id),
+        _ => en,
+    }
+}
@@ -0,0 +1,313 @@
+// DEFLATE decoding (RFC 1951) with the gzip and zlib wrappers, for page
+// bodies that arrive still compressed under a gzip/deflate Content-Encoding,
+// and for the image data of scrambled PNG pages (png.rs). Most hosts decode
+// bodies on their own and this rarely runs; a compression crate would be a
+// lot of WASM for it. Modelled on zlib's puff.c: simple, not fast.
+
+use aidoku::std::Vec;
+
+const MAX_BITS: usize = 15;
+
+// Base lengths and extra bits for length codes 257..285, distance codes 0..29
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193,
+    12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+// Order the code length code lengths are stored in
+const CLEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+}
+
+impl Bits<'_> {
+    // Next `n` bits, least significant first; None past the end of the input
+    fn take(&mut self, n: u32) -> Option<u32> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos)?;
+            self.pos += 1;
+            self.buf |= u32::from(byte) << self.count;
+            self.count += 8;
+        }
+        let v = self.buf & ((1u32 << n) - 1);
+        self.buf >>= n;
+        self.count -= n;
+        Some(v)
+    }
+
+    fn align(&mut self) {
+        self.buf = 0;
+        self.count = 0;
+    }
+}
+
+// Canonical Huffman code: how many codes of each length, and the symbols in
+// code order
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Option<Huffman> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &l in lengths {
+            counts[l as usize] += 1;
+        }
+        // Over-subscribed sets can't be decoded; incomplete ones are allowed
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 { return None; }
+        }
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &l) in lengths.iter().enumerate() {
+            if l != 0 {
+                symbols[offsets[l as usize] as usize] = symbol as u16;
+                offsets[l as usize] += 1;
+            }
+        }
+        Some(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Option<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..=MAX_BITS {
+            code |= bits.take(1)? as i32;
+            let count = i32::from(self.counts[len]);
+            if code - count < first {
+                return self.symbols.get((index + code - first) as usize).copied();
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+fn stored(bits: &mut Bits, out: &mut Vec<u8>) -> Option<()> {
+    bits.align();
+    let header = bits.data.get(bits.pos..bits.pos + 4)?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+    if len != !nlen { return None; }
+    bits.pos += 4;
+    out.extend_from_slice(bits.data.get(bits.pos..bits.pos + len as usize)?);
+    bits.pos += len as usize;
+    Some(())
+}
+
+fn codes(bits: &mut Bits, out: &mut Vec<u8>, lengths: &Huffman, distances: &Huffman) -> Option<()> {
+    loop {
+        let symbol = lengths.decode(bits)?;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Some(()),
+            _ => {
+                let i = usize::from(symbol - 257);
+                let len = usize::from(*LENGTH_BASE.get(i)?) + bits.take(u32::from(LENGTH_EXTRA[i]))? as usize;
+                let d = usize::from(distances.decode(bits)?);
+                let dist = usize::from(*DIST_BASE.get(d)?) + bits.take(u32::from(DIST_EXTRA[d]))? as usize;
+                if dist > out.len() { return None; }
+                // Byte by byte: a match can overlap the bytes it produces
+                let start = out.len() - dist;
+                for k in 0..len {
+                    out.push(out[start + k]);
+                }
+            }
+        }
+    }
+}
+
+fn fixed(bits: &mut Bits, out: &mut Vec<u8>) -> Option<()> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    codes(bits, out, &Huffman::new(&lengths)?, &Huffman::new(&[5u8; 30])?)
+}
+
+fn dynamic(bits: &mut Bits, out: &mut Vec<u8>) -> Option<()> {
+    let nlen = bits.take(5)? as usize + 257;
+    let ndist = bits.take(5)? as usize + 1;
+    let ncode = bits.take(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 { return None; }
+
+    let mut clens = [0u8; 19];
+    for &i in &CLEN_ORDER[..ncode] {
+        clens[i] = bits.take(3)? as u8;
+    }
+    let clen_code = Huffman::new(&clens)?;
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut i = 0;
+    while i < nlen + ndist {
+        let symbol = clen_code.decode(bits)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (*lengths.get(i.checked_sub(1)?)?, 3 + bits.take(2)? as usize),
+            17 => (0, 3 + bits.take(3)? as usize),
+            _ => (0, 11 + bits.take(7)? as usize),
+        };
+        if i + repeat > nlen + ndist { return None; }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+    // Without an end-of-block code the block can't terminate
+    if lengths[256] == 0 { return None; }
+    codes(bits, out, &Huffman::new(&lengths[..nlen])?, &Huffman::new(&lengths[nlen..])?)
+}
+
+// Raw DEFLATE stream
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut bits = Bits { data, pos: 0, buf: 0, count: 0 };
+    let mut out = Vec::with_capacity(data.len() * 4);
+    loop {
+        let last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => stored(&mut bits, &mut out)?,
+            1 => fixed(&mut bits, &mut out)?,
+            2 => dynamic(&mut bits, &mut out)?,
+            _ => return None,
+        }
+        if last { return Some(out); }
+    }
+}
+
+// gzip member: fixed header, optional extra/name/comment/CRC fields, then
+// the stream (the trailing CRC and size aren't checked)
+fn gunzip(data: &[u8]) -> Option<Vec<u8>> {
+    let flags = *data.get(3)?;
+    let mut pos = 10;
+    if flags & 4 != 0 {
+        let extra = u16::from_le_bytes([*data.get(pos)?, *data.get(pos + 1)?]);
+        pos += 2 + extra as usize;
+    }
+    for flag in [8, 16] {
+        if flags & flag != 0 {
+            pos += data.get(pos..)?.iter().position(|&b| b == 0)? + 1;
+        }
+    }
+    if flags & 2 != 0 { pos += 2; }
+    inflate(data.get(pos..)?)
+}
+
+// zlib stream with any window size; PNG encoders shrink it for small images
+pub fn zlib(data: &[u8]) -> Option<Vec<u8>> {
+    match data {
+        [cmf, flg, rest @ ..] if cmf & 0x0f == 8 && cmf >> 4 <= 7 && flg & 0x20 == 0 => {
+            if (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 != 0 { return None; }
+            inflate(rest)
+        }
+        _ => None,
+    }
+}
+
+// A gzip or zlib body decoded; None when it's neither (or is corrupt), in
+// which case the caller keeps the bytes as they came
+pub fn decode(data: &[u8]) -> Option<Vec<u8>> {
+    match data {
+        [0x1f, 0x8b, 8, ..] => gunzip(data),
+        // zlib with the usual 32K window; the header checksum is divisible by 31
+        [0x78, flg, rest @ ..] if (0x7800 | u16::from(*flg)) % 31 == 0 => inflate(rest),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &[u8] = b"<html><body>Chapter 1</body></html>";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn chapter_rows() -> Vec<u8> {
+        (0..40).flat_map(|i| format!("<li><a href=\"/manga/solo-leveling/chapter-{i}/\">Chapter {i}</a></li>\n").into_bytes()).collect()
+    }
+
+    // Vectors from Python's zlib and gzip modules
+    const STORED: &str = "010900f6ff3c703e68693c2f703e";
+    const FIXED: &str = "b3c928c9cdb1b349ca4fa9b473ce482c28492d5230b4d107f36df4c19200";
+    const DYNAMIC: &[&str] = &[
+        "9dd5bd6a02611085e13e5721f6f239e7f80b9b6d72254bd8a8b0d16042ae5f21cdd92ebcdd34f3560f33dd74e9bb6171",
+        "be8f1fafcbf6395c4f43fbbe4db7d534fe8ed3e57a6aefe7e1eb67bcafd66dd9bffdcd8b75d786be6bcfdd97eedf818a",
+        "40918022201270044c029b086c48601b812d09ec22b023817d04f6247088c081048e1138224849b198c51946a4b19263",
+        "218f95200b89ac2459c86425ca422a2b5916725909b390cc4a9a856c56e22ca453a95348a752a7d8ad9c1d4ba453a953",
+        "48a752a7904ea54e219d4a9d423a953a85742a750ae954ea14d2e9d469a4d3a9d348a753a7d92f9f3d73a4d3a9d348a7",
+        "53a7914ea74e239d4e9d463a9d3a8d743a753a743e00",
+    ];
+    const ZLIB: &str = "78dab3c928c9cdb1b349ca4fa9b473ce482c28492d5230b4d107f36df4c19200ddfa0c25";
+    const GZIP: &str = "1f8b0800000000000203b3c928c9cdb1b349ca4fa9b473ce482c28492d5230b4d107f36df4c19200c1720c8c23000000";
+    // With the FNAME field set
+    const GZIP_NAMED: &str = "1f8b0808000000000003706167652e68746d6c00b3c928c9cdb1b349ca4fa9b473ce482c28492d5230b4d107f36df4c19200c1720c8c23000000";
+
+    #[test]
+    fn raw_blocks() {
+        assert_eq!(inflate(&hex(STORED)).as_deref(), Some(b"<p>hi</p>".as_slice()));
+        assert_eq!(inflate(&hex(FIXED)).as_deref(), Some(HTML));
+        assert_eq!(inflate(&hex(&DYNAMIC.concat())), Some(chapter_rows()));
+    }
+
+    #[test]
+    fn wrappers() {
+        assert_eq!(decode(&hex(ZLIB)).as_deref(), Some(HTML));
+        assert_eq!(decode(&hex(GZIP)).as_deref(), Some(HTML));
+        assert_eq!(decode(&hex(GZIP_NAMED)).as_deref(), Some(HTML));
+        // Plain bodies aren't touched
+        assert_eq!(decode(HTML), None);
+        assert_eq!(decode(b""), None);
+    }
+
+    #[test]
+    fn truncated_and_corrupt() {
+        let gzip = hex(GZIP);
+        for len in 0..gzip.len() - 8 {
+            assert_eq!(decode(&gzip[..len]), None, "gzip cut at {len}");
+        }
+        let dynamic = hex(&DYNAMIC.concat());
+        for len in 0..dynamic.len() - 1 {
+            assert_ne!(inflate(&dynamic[..len]), Some(chapter_rows()), "dynamic cut at {len}");
+        }
+        // Reserved block type
+        assert_eq!(inflate(&[0x07, 0x00]), None);
+        // Stored block whose length check doesn't match
+        let mut stored = hex(STORED);
+        stored[3] ^= 1;
+        assert_eq!(inflate(&stored), None);
+        // Distance reaching back past the start of the output
+        assert_eq!(inflate(&[0x03, 0x02, 0x00]), None);
+    }
+
+    // Random bytes, also behind a valid gzip or zlib header so they reach
+    // the block decoders
+    #[test]
+    fn garbage_never_panics() {
+        for seed in 0..5000 {
+            let data = crate::tests::noise(seed, seed as usize % 200);
+            let _ = decode(&data);
+            let _ = decode(&[&[0x1f, 0x8b, 8, (seed % 32) as u8, 0, 0, 0, 0, 0, 3], data.as_slice()].concat());
+            let _ = decode(&[&[0x78, 0x9c], data.as_slice()].concat());
+        }
+        let dynamic = hex(&DYNAMIC.concat());
+        for (i, flip) in (0..dynamic.len() * 8).map(|bit| (bit / 8, 1u8 << (bit % 8))) {
+            let mut corrupt = dynamic.clone();
+            corrupt[i] ^= flip;
+            let _ = inflate(&corrupt);
+        }
+    }
+}
@@ -0,0 +1,900 @@
+// JPEG decoding (baseline and progressive) and baseline encoding, for
+// scrambled pages that have to be reassembled as pixels (see tiles.rs).
+// Huffman-coded 8-bit images only: no arithmetic coding, lossless or
+// hierarchical modes. Like inflate.rs this is simple, not fast: a float IDCT
+// and one bit at a time past the lookup table.
+
+use aidoku::std::Vec;
+
+use crate::tiles::{Image, MAX_PIXELS};
+
+// Natural (row-major) index of each zigzag position
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55,
+    62, 63,
+];
+
+// cos((2x + 1)uπ/16) scaled by C(u)/2, indexed [x][u]; both passes of the
+// 2-D transform use it, in either direction
+fn cosines() -> [[f32; 8]; 8] {
+    let mut table = [[0f32; 8]; 8];
+    for (x, row) in table.iter_mut().enumerate() {
+        for (u, c) in row.iter_mut().enumerate() {
+            let scale = if u == 0 { core::f32::consts::FRAC_1_SQRT_2 } else { 1.0 };
+            *c = scale / 2.0 * ((2 * x + 1) as f32 * u as f32 * core::f32::consts::PI / 16.0).cos();
+        }
+    }
+    table
+}
+
+// ---- Decoding ----
+
+// Entropy-coded bits, with the 0xFF00 stuffing removed. At a marker the
+// input stops and zeros are read, as libjpeg does with a truncated scan.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+    at_marker: bool,
+}
+
+impl Reader<'_> {
+    fn fill(&mut self) {
+        while self.count <= 24 {
+            let mut byte = 0;
+            if !self.at_marker {
+                match self.data.get(self.pos) {
+                    Some(0xff) if self.data.get(self.pos + 1) == Some(&0) => {
+                        byte = 0xff;
+                        self.pos += 2;
+                    }
+                    Some(0xff) | None => self.at_marker = true,
+                    Some(&b) => {
+                        byte = b;
+                        self.pos += 1;
+                    }
+                }
+            }
+            self.buf |= u32::from(byte) << (24 - self.count);
+            self.count += 8;
+        }
+    }
+
+    fn peek(&mut self, n: u32) -> u32 {
+        self.fill();
+        self.buf >> (32 - n)
+    }
+
+    fn skip(&mut self, n: u32) {
+        self.buf <<= n;
+        self.count -= n;
+    }
+
+    // Next `n` (at most 16) bits, most significant first
+    fn bits(&mut self, n: u32) -> u32 {
+        if n == 0 { return 0; }
+        let v = self.peek(n);
+        self.skip(n);
+        v
+    }
+
+    // A coefficient of `size` bits, negative ones stored one's-complement
+    fn receive(&mut self, size: u8) -> i32 {
+        if size == 0 { return 0; }
+        let v = self.bits(u32::from(size)) as i32;
+        if v < 1 << (size - 1) { v - (1 << size) + 1 } else { v }
+    }
+
+    // Past the RSTn that ends a restart interval, with the bit buffer empty
+    fn restart(&mut self) {
+        self.buf = 0;
+        self.count = 0;
+        self.at_marker = false;
+        while let (Some(&a), Some(&b)) = (self.data.get(self.pos), self.data.get(self.pos + 1)) {
+            if a == 0xff && (0xd0..=0xd7).contains(&b) {
+                self.pos += 2;
+                return;
+            }
+            // Some other marker: the scan is cut short
+            if a == 0xff && b != 0 && b != 0xff {
+                self.at_marker = true;
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+struct Huffman {
+    // (code length, symbol) for codes of up to 8 bits, by the next 8 bits
+    lookup: [(u8, u8); 256],
+    maxcode: [i32; 17],
+    mincode: [i32; 17],
+    valptr: [usize; 17],
+    values: Vec<u8>,
+}
+
+impl Huffman {
+    // From a DHT segment's code counts per length and its symbols, F.2.2.3
+    fn new(counts: &[u8], values: &[u8]) -> Option<Huffman> {
+        let mut h = Huffman { lookup: [(0, 0); 256], maxcode: [-1; 17], mincode: [0; 17], valptr: [0; 17], values: values.into() };
+        let (mut code, mut k) = (0i32, 0usize);
+        for len in 1..=16 {
+            let count = i32::from(counts[len - 1]);
+            h.valptr[len] = k;
+            h.mincode[len] = code;
+            for _ in 0..count {
+                if len <= 8 {
+                    let shift = 8 - len;
+                    for low in 0..1 << shift {
+                        h.lookup[((code << shift) | low) as usize] = (len as u8, *values.get(k)?);
+                    }
+                }
+                code += 1;
+                k += 1;
+            }
+            if count > 0 { h.maxcode[len] = code - 1; }
+            if code > 1 << len { return None; }
+            code <<= 1;
+        }
+        Some(h)
+    }
+
+    fn decode(&self, r: &mut Reader) -> Option<u8> {
+        let (len, symbol) = self.lookup[r.peek(8) as usize];
+        if len > 0 {
+            r.skip(u32::from(len));
+            return Some(symbol);
+        }
+        let mut code = 0i32;
+        for len in 1..=16 {
+            code = (code << 1) | r.bits(1) as i32;
+            if code <= self.maxcode[len] {
+                return self.values.get(self.valptr[len] + (code - self.mincode[len]) as usize).copied();
+            }
+        }
+        None
+    }
+}
+
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quant: usize,
+    // Blocks per row and column, padded out to whole MCUs
+    bw: usize,
+    bh: usize,
+    // Quantized coefficients, 64 per block in zigzag order
+    coefs: Vec<i16>,
+    dc: usize,
+    ac: usize,
+    pred: i32,
+}
+
+struct Frame {
+    width: usize,
+    height: usize,
+    progressive: bool,
+    hmax: usize,
+    vmax: usize,
+    mcux: usize,
+    mcuy: usize,
+    components: Vec<Component>,
+}
+
+// Spectral selection and successive approximation of a scan
+struct Scan {
+    progressive: bool,
+    ss: usize,
+    se: usize,
+    ah: u8,
+    al: u8,
+}
+
+fn frame(seg: &[u8], progressive: bool) -> Option<Frame> {
+    let [8, h1, h0, w1, w0, n, rest @ ..] = seg else { return None };
+    let (height, width) = (usize::from(u16::from_be_bytes([*h1, *h0])), usize::from(u16::from_be_bytes([*w1, *w0])));
+    // A zero height would come later in a DNL segment, which isn't supported
+    if width == 0 || height == 0 || width * height > MAX_PIXELS { return None; }
+    if ![1, 3, 4].contains(n) { return None; }
+    let specs = rest.get(..3 * usize::from(*n))?;
+    let sampling = |b: u8| (usize::from(b >> 4), usize::from(b & 15));
+    let (hmax, vmax) = specs.chunks(3).fold((1, 1), |(h, v), c| {
+        let (ch, cv) = sampling(c[1]);
+        (h.max(ch), v.max(cv))
+    });
+    let (mcux, mcuy) = (width.div_ceil(8 * hmax), height.div_ceil(8 * vmax));
+    let mut components = Vec::new();
+    for c in specs.chunks(3) {
+        let (h, v) = sampling(c[1]);
+        if !(1..=4).contains(&h) || !(1..=4).contains(&v) || c[2] > 3 { return None; }
+        let (bw, bh) = (mcux * h, mcuy * v);
+        components.push(Component {
+            id: c[0],
+            h,
+            v,
+            quant: usize::from(c[2]),
+            bw,
+            bh,
+            coefs: vec![0; bw * bh * 64],
+            dc: 0,
+            ac: 0,
+            pred: 0,
+        });
+    }
+    Some(Frame { width, height, progressive, hmax, vmax, mcux, mcuy, components })
+}
+
+fn quant_tables(mut seg: &[u8], tables: &mut [[u16; 64]; 4]) -> Option<()> {
+    while let [pq_tq, rest @ ..] = seg {
+        let table = tables.get_mut(usize::from(pq_tq & 15))?;
+        let wide = pq_tq >> 4 != 0;
+        let size = if wide { 128 } else { 64 };
+        let values = rest.get(..size)?;
+        for (k, q) in table.iter_mut().enumerate() {
+            *q = if wide { u16::from_be_bytes([values[2 * k], values[2 * k + 1]]) } else { u16::from(values[k]) };
+        }
+        seg = &rest[size..];
+    }
+    Some(())
+}
+
+fn huffman_tables(mut seg: &[u8], dc: &mut [Option<Huffman>; 4], ac: &mut [Option<Huffman>; 4]) -> Option<()> {
+    while let [tc_th, rest @ ..] = seg {
+        let counts = rest.get(..16)?;
+        let total: usize = counts.iter().map(|&c| usize::from(c)).sum();
+        let table = Huffman::new(counts, rest.get(16..16 + total)?)?;
+        let slot = usize::from(tc_th & 15);
+        match tc_th >> 4 {
+            0 => *dc.get_mut(slot)? = Some(table),
+            1 => *ac.get_mut(slot)? = Some(table),
+            _ => return None,
+        }
+        seg = &rest[16 + total..];
+    }
+    Some(())
+}
+
+// One block's share of a scan, G.1.2 for the progressive cases
+fn block(
+    r: &mut Reader,
+    coefs: &mut [i16],
+    pred: &mut i32,
+    dc: Option<&Huffman>,
+    ac: Option<&Huffman>,
+    scan: &Scan,
+    eobrun: &mut u32,
+) -> Option<()> {
+    if !scan.progressive {
+        let size = dc?.decode(r)?;
+        *pred = pred.wrapping_add(r.receive(size));
+        coefs[0] = *pred as i16;
+        let ac = ac?;
+        let mut k = 1;
+        while k < 64 {
+            let rs = ac.decode(r)?;
+            let (run, size) = (usize::from(rs >> 4), rs & 15);
+            if size == 0 {
+                if run != 15 { break; }
+                k += 16;
+                continue;
+            }
+            k += run;
+            if k > 63 { break; }
+            coefs[k] = r.receive(size) as i16;
+            k += 1;
+        }
+        return Some(());
+    }
+
+    if scan.ss == 0 {
+        if scan.ah == 0 {
+            let size = dc?.decode(r)?;
+            *pred = pred.wrapping_add(r.receive(size));
+            coefs[0] = (*pred << scan.al) as i16;
+        } else if r.bits(1) == 1 {
+            coefs[0] |= 1 << scan.al;
+        }
+        return Some(());
+    }
+
+    let ac = ac?;
+    if scan.ah == 0 {
+        if *eobrun > 0 {
+            *eobrun -= 1;
+            return Some(());
+        }
+        let mut k = scan.ss;
+        while k <= scan.se {
+            let rs = ac.decode(r)?;
+            let (run, size) = (u32::from(rs >> 4), rs & 15);
+            if size == 0 {
+                if run < 15 {
+                    *eobrun = (1 << run) - 1 + r.bits(run);
+                    break;
+                }
+                k += 16;
+                continue;
+            }
+            k += run as usize;
+            if k > 63 { break; }
+            coefs[k] = (r.receive(size) << scan.al) as i16;
+            k += 1;
+        }
+        return Some(());
+    }
+
+    // Refinement: one more bit for each coefficient that's already nonzero,
+    // newly nonzero ones placed by run length (libjpeg's decode_mcu_AC_refine)
+    let (p1, m1) = (1i16 << scan.al, -1i16 << scan.al);
+    let refine = |r: &mut Reader, c: &mut i16| {
+        if r.bits(1) == 1 && *c & p1 == 0 {
+            *c = c.wrapping_add(if *c >= 0 { p1 } else { m1 });
+        }
+    };
+    let mut k = scan.ss;
+    if *eobrun == 0 {
+        while k <= scan.se {
+            let rs = ac.decode(r)?;
+            let (mut run, size) = (i32::from(rs >> 4), rs & 15);
+            let mut value = 0;
+            if size != 0 {
+                value = if r.bits(1) == 1 { p1 } else { m1 };
+            } else if run != 15 {
+                *eobrun = (1 << run) + r.bits(run as u32);
+                break;
+            }
+            while k <= scan.se {
+                if coefs[k] != 0 {
+                    refine(r, &mut coefs[k]);
+                } else {
+                    run -= 1;
+                    if run < 0 { break; }
+                }
+                k += 1;
+            }
+            if value != 0 && k <= scan.se { coefs[k] = value; }
+            k += 1;
+        }
+    }
+    if *eobrun > 0 {
+        while k <= scan.se {
+            if coefs[k] != 0 { refine(r, &mut coefs[k]); }
+            k += 1;
+        }
+        *eobrun -= 1;
+    }
+    Some(())
+}
+
+// Decodes the scan whose header is `header` and whose data starts at `pos`;
+// returns where the next marker is
+fn scan(
+    data: &[u8],
+    pos: usize,
+    header: &[u8],
+    frame: &mut Frame,
+    dc: &[Option<Huffman>; 4],
+    ac: &[Option<Huffman>; 4],
+    restart: usize,
+) -> Option<usize> {
+    let n = usize::from(*header.first()?);
+    if n == 0 || n > frame.components.len() { return None; }
+    let mut members = Vec::new();
+    for spec in header.get(1..1 + 2 * n)?.chunks(2) {
+        let c = frame.components.iter().position(|c| c.id == spec[0])?;
+        frame.components[c].dc = usize::from(spec[1] >> 4).min(3);
+        frame.components[c].ac = usize::from(spec[1] & 15).min(3);
+        frame.components[c].pred = 0;
+        members.push(c);
+    }
+    let [ss, se, a] = *header.get(1 + 2 * n..4 + 2 * n)? else { return None };
+    let scan = Scan { progressive: frame.progressive, ss: usize::from(ss), se: usize::from(se), ah: a >> 4, al: a & 15 };
+    if scan.progressive {
+        let valid = scan.ss <= scan.se && scan.se <= 63 && (scan.ss > 0 || scan.se == 0) && (scan.ss == 0 || n == 1);
+        if !valid || scan.al > 13 { return None; }
+    }
+
+    // A single-component scan covers just that component's blocks, one per
+    // MCU; an interleaved one goes MCU by MCU over all of them
+    let single = n == 1;
+    let (across, down) = if single {
+        let c = &frame.components[members[0]];
+        ((frame.width * c.h).div_ceil(frame.hmax).div_ceil(8), (frame.height * c.v).div_ceil(frame.vmax).div_ceil(8))
+    } else {
+        (frame.mcux, frame.mcuy)
+    };
+    let mut r = Reader { data, pos, buf: 0, count: 0, at_marker: false };
+    let mut eobrun = 0;
+    for m in 0..across * down {
+        if restart > 0 && m > 0 && m % restart == 0 {
+            r.restart();
+            eobrun = 0;
+            for &c in &members {
+                frame.components[c].pred = 0;
+            }
+        }
+        let (mx, my) = (m % across, m / across);
+        for &ci in &members {
+            let c = &mut frame.components[ci];
+            let (dc, ac) = (dc[c.dc].as_ref(), ac[c.ac].as_ref());
+            let (h, v) = if single { (1, 1) } else { (c.h, c.v) };
+            for by in my * v..my * v + v {
+                for bx in mx * h..mx * h + h {
+                    let at = (by * c.bw + bx) * 64;
+                    block(&mut r, &mut c.coefs[at..at + 64], &mut c.pred, dc, ac, &scan, &mut eobrun)?;
+                }
+            }
+        }
+    }
+
+    // The bit reader stops at a marker or reads ahead into padding
+    let mut p = r.pos;
+    loop {
+        if *data.get(p)? == 0xff && data.get(p + 1).is_some_and(|&b| b != 0 && !(0xd0..=0xd7).contains(&b)) {
+            return Some(p);
+        }
+        p += 1;
+    }
+}
+
+// Dequantized, inverse-transformed samples of each component, one plane each
+fn planes(frame: &Frame, quant: &[[u16; 64]; 4]) -> Vec<Vec<u8>> {
+    let cos = cosines();
+    frame
+        .components
+        .iter()
+        .map(|c| {
+            let q = &quant[c.quant];
+            let stride = c.bw * 8;
+            let mut plane = vec![0u8; stride * c.bh * 8];
+            for (b, coefs) in c.coefs.chunks(64).enumerate() {
+                let mut f = [0f32; 64];
+                for k in 0..64 {
+                    f[ZIGZAG[k]] = f32::from(coefs[k]) * f32::from(q[k]);
+                }
+                // Rows, then columns
+                let mut tmp = [0f32; 64];
+                for v in 0..8 {
+                    for x in 0..8 {
+                        tmp[v * 8 + x] = (0..8).map(|u| cos[x][u] * f[v * 8 + u]).sum();
+                    }
+                }
+                let (x0, y0) = (b % c.bw * 8, b / c.bw * 8);
+                for y in 0..8 {
+                    for x in 0..8 {
+                        let s: f32 = (0..8).map(|v| cos[y][v] * tmp[v * 8 + x]).sum();
+                        plane[(y0 + y) * stride + x0 + x] = (s + 128.0).round().clamp(0.0, 255.0) as u8;
+                    }
+                }
+            }
+            plane
+        })
+        .collect()
+}
+
+fn clamp(v: f32) -> u8 {
+    v.round().clamp(0.0, 255.0) as u8
+}
+
+fn ycc_to_rgb(y: u8, cb: u8, cr: u8) -> [u8; 3] {
+    let (y, cb, cr) = (f32::from(y), f32::from(cb) - 128.0, f32::from(cr) - 128.0);
+    [clamp(y + 1.402 * cr), clamp(y - 0.344_136 * cb - 0.714_136 * cr), clamp(y + 1.772 * cb)]
+}
+
+// `adobe` is the APP14 transform flag: 0 for untransformed RGB or CMYK, 1
+// for YCbCr, 2 for YCCK
+fn pixels(frame: &Frame, planes: &[Vec<u8>], adobe: Option<u8>) -> Image {
+    let (width, height) = (frame.width, frame.height);
+    // Subsampled components are interpolated between sample centres, as
+    // libjpeg's fancy upsampling does, rather than repeated in blocks
+    let sample = |c: usize, x: usize, y: usize| {
+        let comp = &frame.components[c];
+        let stride = comp.bw * 8;
+        if comp.h == frame.hmax && comp.v == frame.vmax { return planes[c][y * stride + x]; }
+        let axis = |p: usize, n: usize, max: usize, size: usize| {
+            let last = (size * n).div_ceil(max) - 1;
+            let f = ((p as f32 + 0.5) * n as f32 / max as f32 - 0.5).max(0.0);
+            let i = (f as usize).min(last);
+            (i, (i + 1).min(last), f - i as f32)
+        };
+        let (x0, x1, fx) = axis(x, comp.h, frame.hmax, width);
+        let (y0, y1, fy) = axis(y, comp.v, frame.vmax, height);
+        let at = |x: usize, y: usize| f32::from(planes[c][y * stride + x]);
+        let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * fx;
+        let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * fx;
+        clamp(top + (bottom - top) * fy)
+    };
+    if frame.components.len() == 1 {
+        let pixels = (0..height).flat_map(|y| (0..width).map(move |x| (y, x))).map(|(y, x)| sample(0, x, y)).collect();
+        return Image { width, height, channels: 1, pixels };
+    }
+    let ids: Vec<u8> = frame.components.iter().map(|c| c.id).collect();
+    let rgb = adobe == Some(0) || ids == b"RGB";
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let (a, b, c) = (sample(0, x, y), sample(1, x, y), sample(2, x, y));
+            let color = if frame.components.len() == 4 {
+                // Adobe writes CMYK inverted, so each channel times K is RGB
+                let [c, m, ye] = if adobe == Some(2) { ycc_to_rgb(a, b, c) } else { [a, b, c] };
+                let k = u16::from(sample(3, x, y));
+                [c, m, ye].map(|v| (u16::from(v) * k / 255) as u8)
+            } else if rgb {
+                [a, b, c]
+            } else {
+                ycc_to_rgb(a, b, c)
+            };
+            pixels.extend_from_slice(&color);
+        }
+    }
+    Image { width, height, channels: 3, pixels }
+}
+
+pub fn decode(data: &[u8]) -> Option<Image> {
+    if data.get(..2)? != [0xff, 0xd8] { return None; }
+    let mut pos = 2;
+    let mut quant = [[0u16; 64]; 4];
+    let mut dc: [Option<Huffman>; 4] = [None, None, None, None];
+    let mut ac: [Option<Huffman>; 4] = [None, None, None, None];
+    let mut frame: Option<Frame> = None;
+    let mut restart = 0;
+    let mut adobe = None;
+    loop {
+        // Markers may be preceded by any number of 0xFF fill bytes
+        while *data.get(pos)? != 0xff { pos += 1; }
+        while data.get(pos) == Some(&0xff) { pos += 1; }
+        let marker = *data.get(pos)?;
+        pos += 1;
+        match marker {
+            0xd9 => break,
+            // Stray RSTn and TEM have no length
+            0xd0..=0xd7 | 0x01 => continue,
+            _ => {}
+        }
+        let len = usize::from(u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]));
+        let seg = data.get(pos + 2..pos + len.max(2))?;
+        match marker {
+            0xdb => quant_tables(seg, &mut quant)?,
+            0xc4 => huffman_tables(seg, &mut dc, &mut ac)?,
+            0xdd => restart = usize::from(u16::from_be_bytes([*seg.first()?, *seg.get(1)?])),
+            0xee if seg.starts_with(b"Adobe") => adobe = seg.get(11).copied(),
+            0xc0..=0xc2 if frame.is_some() => return None,
+            0xc0 | 0xc1 => frame = Some(self::frame(seg, false)?),
+            0xc2 => frame = Some(self::frame(seg, true)?),
+            // Lossless, hierarchical and arithmetic-coded frames
+            0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf => return None,
+            0xda => {
+                pos = scan(data, pos + len, seg, frame.as_mut()?, &dc, &ac, restart)?;
+                continue;
+            }
+            _ => {}
+        }
+        pos += len;
+    }
+    let frame = frame?;
+    Some(pixels(&frame, &planes(&frame, &quant), adobe))
+}
+
+// ---- Encoding ----
+
+// Annex K tables: quantization in natural order, Huffman as code counts per
+// length and symbols
+const LUMA_QUANT: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56, 14, 17, 22, 29, 51, 87, 80,
+    62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113, 92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95,
+    98, 112, 100, 103, 99,
+];
+const CHROMA_QUANT: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99, 47, 66, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99,
+];
+const DC_COUNTS: [[u8; 16]; 2] = [[0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0], [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0]];
+const DC_SYMBOLS: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+const AC_COUNTS: [[u8; 16]; 2] = [
+    [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d],
+    [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77],
+];
+const AC_SYMBOLS: [[u8; 162]; 2] = [
+    [
+        0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07, 0x22, 0x71, 0x14, 0x32,
+        0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52, 0xd1, 0xf0, 0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16,
+        0x17, 0x18, 0x19, 0x1a, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45,
+        0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+        0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92, 0x93, 0x94,
+        0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6,
+        0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8,
+        0xd9, 0xda, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+        0xf9, 0xfa,
+    ],
+    [
+        0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71, 0x13, 0x22, 0x32, 0x81,
+        0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33, 0x52, 0xf0, 0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34,
+        0xe1, 0x25, 0xf1, 0x17, 0x18, 0x19, 0x1a, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44,
+        0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+        0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92,
+        0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4,
+        0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6,
+        0xd7, 0xd8, 0xd9, 0xda, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+        0xf9, 0xfa,
+    ],
+];
+
+// (code, length) by symbol
+fn codes(counts: &[u8; 16], symbols: &[u8]) -> [(u16, u8); 256] {
+    let mut table = [(0, 0); 256];
+    let (mut code, mut k) = (0u16, 0);
+    for (len, &count) in counts.iter().enumerate() {
+        for _ in 0..count {
+            table[usize::from(symbols[k])] = (code, len as u8 + 1);
+            code += 1;
+            k += 1;
+        }
+        code <<= 1;
+    }
+    table
+}
+
+// libjpeg's quality scaling of the Annex K tables
+fn scaled(base: &[u16; 64], quality: u8) -> [u16; 64] {
+    let quality = u32::from(quality.clamp(1, 100));
+    let scale = if quality < 50 { 5000 / quality } else { 200 - 2 * quality };
+    base.map(|q| ((u32::from(q) * scale + 50) / 100).clamp(1, 255) as u16)
+}
+
+struct Writer {
+    out: Vec<u8>,
+    acc: u32,
+    count: u32,
+}
+
+impl Writer {
+    fn put(&mut self, code: u16, len: u8) {
+        self.acc = (self.acc << len) | u32::from(code);
+        self.count += u32::from(len);
+        while self.count >= 8 {
+            self.count -= 8;
+            let byte = (self.acc >> self.count) as u8;
+            self.out.push(byte);
+            if byte == 0xff { self.out.push(0); }
+        }
+        self.acc &= (1 << self.count) - 1;
+    }
+
+    // Pads the last byte with ones
+    fn flush(&mut self) {
+        if self.count > 0 {
+            let pad = 8 - self.count as u8;
+            self.put((1 << pad) - 1, pad);
+        }
+    }
+
+    fn segment(&mut self, marker: u8, body: &[u8]) {
+        self.out.extend_from_slice(&[0xff, marker]);
+        self.out.extend_from_slice(&(body.len() as u16 + 2).to_be_bytes());
+        self.out.extend_from_slice(body);
+    }
+}
+
+// Bits needed for |v|, the JPEG size category
+fn category(v: i32) -> u8 {
+    (32 - v.unsigned_abs().leading_zeros()) as u8
+}
+
+struct Channel {
+    quant: [u16; 64],
+    dc: [(u16, u8); 256],
+    ac: [(u16, u8); 256],
+    pred: i32,
+}
+
+// Forward DCT, quantization and Huffman coding of one 8x8 block of samples
+fn encode_block(w: &mut Writer, samples: &[f32; 64], ch: &mut Channel, cos: &[[f32; 8]; 8]) {
+    let mut tmp = [0f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            tmp[y * 8 + u] = (0..8).map(|x| cos[x][u] * (samples[y * 8 + x] - 128.0)).sum();
+        }
+    }
+    let mut q = [0i32; 64];
+    for (k, &n) in ZIGZAG.iter().enumerate() {
+        let (v, u) = (n / 8, n % 8);
+        let f: f32 = (0..8).map(|y| cos[y][v] * tmp[y * 8 + u]).sum();
+        q[k] = (f / f32::from(ch.quant[n])).round() as i32;
+    }
+
+    let diff = q[0] - ch.pred;
+    ch.pred = q[0];
+    let size = category(diff);
+    let (code, len) = ch.dc[usize::from(size)];
+    w.put(code, len);
+    if size > 0 {
+        let bits = if diff < 0 { diff - 1 } else { diff };
+        w.put((bits & ((1 << size) - 1)) as u16, size);
+    }
+    let mut run = 0;
+    for &v in &q[1..] {
+        if v == 0 {
+            run += 1;
+            continue;
+        }
+        while run > 15 {
+            let (code, len) = ch.ac[0xf0];
+            w.put(code, len);
+            run -= 16;
+        }
+        let size = category(v);
+        let (code, len) = ch.ac[usize::from((run << 4) | size)];
+        w.put(code, len);
+        let bits = if v < 0 { v - 1 } else { v };
+        w.put((bits & ((1 << size) - 1)) as u16, size);
+        run = 0;
+    }
+    if run > 0 {
+        let (code, len) = ch.ac[0];
+        w.put(code, len);
+    }
+}
+
+// Baseline JPEG: grayscale as one component, RGB as YCbCr with 4:2:0 chroma.
+// None when a side is past what the format can store.
+pub fn encode(image: &Image, quality: u8) -> Option<Vec<u8>> {
+    let (width, height) = (image.width, image.height);
+    if width == 0 || height == 0 || width > 0xffff || height > 0xffff { return None; }
+    let color = image.channels == 3;
+    let tables = [scaled(&LUMA_QUANT, quality), scaled(&CHROMA_QUANT, quality)];
+    let mut w = Writer { out: Vec::with_capacity(width * height / 4), acc: 0, count: 0 };
+    w.out.extend_from_slice(&[0xff, 0xd8]);
+    w.segment(0xe0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+    let mut dqt = Vec::new();
+    for (id, table) in tables.iter().enumerate().take(if color { 2 } else { 1 }) {
+        dqt.push(id as u8);
+        dqt.extend(ZIGZAG.iter().map(|&n| table[n] as u8));
+    }
+    w.segment(0xdb, &dqt);
+    let specs: &[[u8; 3]] = if color { &[[1, 0x22, 0], [2, 0x11, 1], [3, 0x11, 1]] } else { &[[1, 0x11, 0]] };
+    let mut sof = vec![8];
+    sof.extend_from_slice(&(height as u16).to_be_bytes());
+    sof.extend_from_slice(&(width as u16).to_be_bytes());
+    sof.push(specs.len() as u8);
+    sof.extend(specs.iter().flatten());
+    w.segment(0xc0, &sof);
+    let mut dht = Vec::new();
+    for id in 0..if color { 2 } else { 1 } {
+        dht.push(id as u8);
+        dht.extend_from_slice(&DC_COUNTS[id]);
+        dht.extend_from_slice(&DC_SYMBOLS[..DC_COUNTS[id].iter().map(|&c| usize::from(c)).sum()]);
+        dht.push(0x10 | id as u8);
+        dht.extend_from_slice(&AC_COUNTS[id]);
+        dht.extend_from_slice(&AC_SYMBOLS[id]);
+    }
+    w.segment(0xc4, &dht);
+    let mut sos = vec![specs.len() as u8];
+    for (i, spec) in specs.iter().enumerate() {
+        sos.extend_from_slice(&[spec[0], if i == 0 { 0x00 } else { 0x11 }]);
+    }
+    sos.extend_from_slice(&[0, 63, 0]);
+    w.segment(0xda, &sos);
+
+    let channel = |id: usize| Channel {
+        quant: tables[id],
+        dc: codes(&DC_COUNTS[id], &DC_SYMBOLS),
+        ac: codes(&AC_COUNTS[id], &AC_SYMBOLS[id]),
+        pred: 0,
+    };
+    let mut channels = if color { vec![channel(0), channel(1), channel(1)] } else { vec![channel(0)] };
+    let cos = cosines();
+    // Edge MCUs repeat the last row and column
+    let ycc = |x: usize, y: usize| {
+        let i = (y.min(height - 1) * width + x.min(width - 1)) * image.channels;
+        if !color { return [f32::from(image.pixels[i]), 0.0, 0.0]; }
+        let [r, g, b] = [0, 1, 2].map(|c| f32::from(image.pixels[i + c]));
+        [
+            0.299 * r + 0.587 * g + 0.114 * b,
+            -0.168_736 * r - 0.331_264 * g + 0.5 * b + 128.0,
+            0.5 * r - 0.418_688 * g - 0.081_312 * b + 128.0,
+        ]
+    };
+    let mcu = if color { 16 } else { 8 };
+    for my in (0..height).step_by(mcu) {
+        for mx in (0..width).step_by(mcu) {
+            if !color {
+                let samples = core::array::from_fn(|i| ycc(mx + i % 8, my + i / 8)[0]);
+                encode_block(&mut w, &samples, &mut channels[0], &cos);
+                continue;
+            }
+            for (by, bx) in [(0, 0), (0, 8), (8, 0), (8, 8)] {
+                let samples = core::array::from_fn(|i| ycc(mx + bx + i % 8, my + by + i / 8)[0]);
+                encode_block(&mut w, &samples, &mut channels[0], &cos);
+            }
+            // Chroma averaged over each 2x2 square
+            for (c, channel) in channels.iter_mut().enumerate().skip(1) {
+                let samples = core::array::from_fn(|i| {
+                    let (x, y) = (mx + i % 8 * 2, my + i / 8 * 2);
+                    [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)].iter().map(|&(x, y)| ycc(x, y)[c]).sum::<f32>() / 4.0
+                });
+                encode_block(&mut w, &samples, channel, &cos);
+            }
+        }
+    }
+    w.flush();
+    w.out.extend_from_slice(&[0xff, 0xd9]);
+    Some(w.out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The pattern the fixtures were written from (at quality 95): 45x29, red
+    // across, green down, blue diagonally
+    const W: usize = 45;
+    const H: usize = 29;
+
+    fn rgb(x: usize, y: usize) -> [u8; 3] {
+        [(x * 255 / (W - 1)) as u8, (y * 255 / (H - 1)) as u8, ((x + y) * 255 / (W + H - 2)) as u8]
+    }
+
+    fn pattern() -> Image {
+        let pixels = (0..H).flat_map(|y| (0..W).flat_map(move |x| rgb(x, y))).collect();
+        Image { width: W, height: H, channels: 3, pixels }
+    }
+
+    // Mean and worst difference per sample
+    fn error(a: &Image, b: &Image) -> (f32, u8) {
+        assert_eq!((a.width, a.height, a.channels), (b.width, b.height, b.channels));
+        let diffs = a.pixels.iter().zip(&b.pixels).map(|(x, y)| x.abs_diff(*y));
+        let (sum, max) = diffs.fold((0u32, 0u8), |(s, m), d| (s + u32::from(d), m.max(d)));
+        (sum as f32 / a.pixels.len() as f32, max)
+    }
+
+    #[test]
+    fn decodes_other_encoders() {
+        let expected = pattern();
+        for (name, data) in [
+            ("baseline 4:2:0", include_bytes!("../tests/fixtures/tiles_420.jpg").as_slice()),
+            ("baseline 4:4:4", include_bytes!("../tests/fixtures/tiles_444.jpg")),
+            ("progressive", include_bytes!("../tests/fixtures/tiles_progressive.jpg")),
+            ("restart markers, 2:1", include_bytes!("../tests/fixtures/tiles_restart.jpg")),
+        ] {
+            let image = decode(data).unwrap_or_else(|| panic!("{name}"));
+            let (mean, max) = error(&image, &expected);
+            assert!(mean < 1.5 && max < 12, "{name}: mean {mean}, max {max}");
+        }
+        let image = decode(include_bytes!("../tests/fixtures/tiles_gray.jpg")).unwrap();
+        assert_eq!(image.channels, 1);
+        let gray = expected.pixels.chunks(3).map(|p| p[0] / 2 + p[1] / 2).collect();
+        let (mean, max) = error(&image, &Image { width: W, height: H, channels: 1, pixels: gray });
+        assert!(mean < 1.0 && max < 8, "gray: mean {mean}, max {max}");
+    }
+
+    #[test]
+    fn round_trip() {
+        let image = pattern();
+        let (mean, max) = error(&decode(&encode(&image, 90).unwrap()).unwrap(), &image);
+        assert!(mean < 1.5 && max < 12, "rgb: mean {mean}, max {max}");
+        let gray = Image { width: W, height: H, channels: 1, pixels: image.pixels.iter().step_by(3).copied().collect() };
+        let (mean, max) = error(&decode(&encode(&gray, 90).unwrap()).unwrap(), &gray);
+        assert!(mean < 1.0 && max < 10, "gray: mean {mean}, max {max}");
+        // Smaller at lower quality
+        assert!(encode(&image, 50).unwrap().len() < encode(&image, 90).unwrap().len());
+    }
+
+    #[test]
+    fn garbage_never_panics() {
+        for data in [include_bytes!("../tests/fixtures/tiles_progressive.jpg").as_slice(), include_bytes!("../tests/fixtures/tiles_restart.jpg")] {
+            for len in (0..data.len()).step_by(5) {
+                decode(&data[..len]);
+            }
+            let mut flipped = data.to_vec();
+            for i in (2..flipped.len()).step_by(11) {
+                flipped[i] ^= 0xa5;
+                decode(&flipped);
+            }
+        }
+    }
+}
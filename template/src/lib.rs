@@ -0,0 +1,1639 @@
+// Shared implementation for WordPress manga sites on the Madara and
+// MangaStream themes. A source crate describes its site with a `Site` and
+// forwards each Aidoku entry point to the function of the same name here.
+
+#![allow(unused)]
+use aidoku::{
+    error::Result,
+    prelude::*,
+    std::{net, net::Request, String, Vec},
+    Chapter, DeepLink, Filter, FilterType, Listing, Manga, MangaPageResult, MangaStatus, MangaContentRating,
+    MangaViewer, Page, Source
+};
+
+// println! to Aidoku's log viewer when the "debugLogging" setting is on.
+// Defined ahead of the modules so all of them can use it.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::settings::debug_logging() {
+            aidoku::prelude::println!("[{}] {}", $crate::site().name, format_args!($($arg)*));
+        }
+    };
+}
+
+mod account;
+mod ajax;
+mod announcements;
+mod cache;
+mod client;
+mod config;
+mod crash;
+mod diagnostics;
+mod error;
+mod feed;
+mod filters;
+mod genres;
+mod host;
+mod http;
+mod i18n;
+mod inflate;
+mod jpeg;
+mod memory;
+mod meta;
+#[cfg(test)]
+mod native;
+mod parse;
+mod png;
+mod reader;
+mod remote;
+mod rest;
+mod settings;
+mod sitemap;
+mod theme;
+mod tiles;
+mod url;
+mod wayback;
+
+#[cfg(test)]
+mod tests;
+
+use host::Node;
+use http::{get_dom, get_dom_cached, get_series_dom, post_dom};
+use memory::CachedChapter;
+use config::{AD_HOSTS, COMING_SOON, CREDIT_URLS, LAZY_ATTRS, NON_CONTENT_URLS, PLACEHOLDERS};
+use theme::{Selectors, Theme};
+use url::SearchParams;
+
+pub use client::{set_client, AidokuClient, HttpClient, HttpRequest, HttpResponse};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+// ------- Config -------
+// What differs between sites using the template
+pub struct Site {
+    // Display name, used in messages
+    pub name: &'static str,
+    // Language of chapters without a language marker; see parse::chapter_lang
+    pub lang: &'static str,
+    // Used unless the "baseUrl" setting points elsewhere; see settings::base_url
+    pub base_url: &'static str,
+    // Chapters without a per-chapter group label are the site's own releases
+    pub scanlator: &'static str,
+    // Lowercase site names the theme appends to page titles ("Title – Raven Scans")
+    pub title_suffixes: &'static [&'static str],
+    // Lowercase line prefixes the site adds to summaries ("read at ...")
+    pub boilerplate: &'static [&'static str],
+    // Pinned URL of the maintainer's hot-fix JSON; see remote.rs
+    pub remote_config: Option<&'static str>,
+    // Alternate domains, tried in order when the base URL is down or parked;
+    // see settings::mirrors
+    pub mirrors: &'static [&'static str],
+    // Hours east of UTC of WordPress's timezone setting, which chapter dates
+    // are displayed in
+    pub utc_offset: f64,
+}
+
+static SITE: Mutex<Option<&'static Site>> = Mutex::new(None);
+
+// Stand-in for a missing register() call: requests fail with a message
+// instead of the module trapping on a panic
+static UNREGISTERED: Site = Site {
+    name: "",
+    lang: "en",
+    base_url: "",
+    scanlator: "",
+    title_suffixes: &[],
+    boilerplate: &[],
+    remote_config: None,
+    mirrors: &[],
+    utc_offset: 0.0,
+};
+
+// Every entry point registers its site first, so this is always set by the
+// time anything reads it
+pub(crate) fn site() -> &'static Site {
+    SITE.lock().unwrap_or_else(|e| e.into_inner()).unwrap_or(&UNREGISTERED)
+}
+
+fn register(site: &'static Site) {
+    *SITE.lock().unwrap_or_else(|e| e.into_inner()) = Some(site);
+    crash::install();
+    http::begin_call();
+    remote::begin_call();
+    settings::forget_base_url();
+}
+
+// register() for the entry points that fetch: also logs a crash from the
+// previous request
+fn enter(site: &'static Site) {
+    register(site);
+    crash::check();
+}
+
+// Trimmed in place rather than copied into a second String
+pub(crate) fn text(node: &Node) -> String {
+    let mut s = node.text().read();
+    s.truncate(s.trim_end().len());
+    let lead = s.len() - s.trim_start().len();
+    s.drain(..lead);
+    s
+}
+
+// One host call per lookup; an empty attribute counts as missing
+pub(crate) fn attr(node: &Node, name: &str) -> Option<String> {
+    Some(node.attr(name).read()).filter(|v| !v.is_empty())
+}
+
+// Matches of `selector` under `node`, in document order
+pub(crate) fn select(node: &Node, selector: &str) -> Vec<Node> {
+    node.select(selector).array().filter_map(|v| v.as_node().ok()).collect()
+}
+
+pub(crate) fn first(node: &Node, selector: &str) -> Option<Node> {
+    node.select(selector).array().find_map(|v| v.as_node().ok())
+}
+
+// "Slice of Life" -> "slice-of-life", matching the site's genre slugs
+fn slugify(s: &str) -> String {
+    s.trim().to_lowercase().split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+// "martial-arts" -> "Martial Arts", the inverse of slugify for links
+fn slug_title(slug: &str) -> String {
+    slug.split('-')
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut c = w.chars();
+            c.next().map(|f| f.to_uppercase().chain(c).collect::<String>()).unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Comparable form of a series URL: no scheme, www, query or trailing slash
+fn normalize_href(href: &str) -> String {
+    let h = abs(href.trim()).to_lowercase();
+    let h = h.split(['?', '#']).next().unwrap_or_default();
+    let h = h.trim_start_matches("https://").trim_start_matches("http://").trim_start_matches("www.");
+    h.trim_end_matches('/').to_string()
+}
+
+// Links read without their page's URL resolve against the site root
+fn abs(href: &str) -> String {
+    url::resolve(href, &settings::base_url())
+}
+
+// Page width requested from the CDN in data saver mode
+const RESIZED_WIDTH: u32 = 720;
+
+// Anything narrower or shorter than this is an icon, not a page
+const MIN_PAGE_SIZE: u32 = 100;
+
+fn is_ad_class(class: &str) -> bool {
+    class.to_lowercase().split_whitespace().any(|c| {
+        c.contains("banner") || c.contains("social") || c.contains("share")
+            || c == "ad" || c == "ads"
+            || c.starts_with("ad-") || c.starts_with("ads-")
+            || c.ends_with("-ad") || c.ends_with("-ads")
+    })
+}
+
+// Skip ads/banners: known ad hosts and non-content URL patterns, or tiny
+// declared dimensions. Images inside ad blocks are caught by ad_block_images.
+fn is_ad_image(img: &Node, extra_hosts: &[String]) -> bool {
+    let urls = [attr(img, "src"), attr(img, "data-src")];
+    let bad_url = |u: &String| {
+        let u = u.to_lowercase();
+        AD_HOSTS.iter().any(|h| u.contains(h))
+            || extra_hosts.iter().any(|h| u.contains(&h.to_lowercase()))
+            || NON_CONTENT_URLS.iter().any(|p| u.contains(p))
+    };
+    if urls.iter().flatten().any(bad_url) {
+        return true;
+    }
+    let tiny = |a: &str| attr(img, a).and_then(|v| v.trim_end_matches("px").parse::<u32>().ok()).is_some_and(|v| v < MIN_PAGE_SIZE);
+    tiny("width") || tiny("height")
+}
+
+// URLs of images whose parent or grandparent is tagged as an ad/banner/share
+// block. The node API can't walk up, so this walks down from the blocks.
+// Class names are matched per token so "reading-content" doesn't count as "ad".
+fn ad_block_images(dom: &Node) -> HashSet<String> {
+    let candidates = "[class*=ad], [class*=banner], [class*=social], [class*=share]";
+    select(dom, candidates)
+        .into_iter()
+        .filter(|n| is_ad_class(&attr(n, "class").unwrap_or_default()))
+        .flat_map(|block| select(&block, "> img, > * > img"))
+        .filter_map(|img| image_url(&img))
+        .collect()
+}
+
+// Rating from genres/tags plus the site's 18+ badge; untagged entries are
+// all-ages, so the source isn't stuck behind Aidoku's NSFW gate.
+fn content_rating(categories: &[String], adult_badge: bool) -> MangaContentRating {
+    let has = |tags: &[&str]| categories.iter().any(|c| tags.iter().any(|t| c.eq_ignore_ascii_case(t)));
+    if adult_badge || has(&["adult", "smut", "hentai", "18+"]) { MangaContentRating::Nsfw }
+    else if has(&["mature", "ecchi"]) { MangaContentRating::Suggestive }
+    else { MangaContentRating::Safe }
+}
+
+// Entries the user asked not to see in listings and search: adult ones when
+// hidden, titles containing a blocked keyword, and blocked genres
+struct Blocklist {
+    nsfw: bool,
+    keywords: Vec<String>,
+    // Slugs, so "Martial Arts" and "martial-arts" both match
+    genres: Vec<String>,
+}
+
+impl Blocklist {
+    fn load() -> Self {
+        let genres = settings::genre_blocklist().iter().map(|g| slugify(g)).collect();
+        Blocklist { nsfw: settings::hide_nsfw(), keywords: settings::title_blocklist(), genres }
+    }
+
+    // The blocked genres among `categories`, as the site spells them
+    fn blocked_genres<'a>(&self, categories: &'a [String]) -> Vec<&'a str> {
+        if self.genres.is_empty() { return Vec::new(); }
+        categories.iter().filter(|c| self.genres.contains(&slugify(c))).map(|c| c.as_str()).collect()
+    }
+
+    fn hides(&self, m: &Manga) -> bool {
+        if self.nsfw && matches!(m.nsfw, MangaContentRating::Nsfw) { return true; }
+        if !self.blocked_genres(&m.categories).is_empty() { return true; }
+        if self.keywords.is_empty() { return false; }
+        let title = m.title.to_lowercase();
+        self.keywords.iter().any(|k| title.contains(k.as_str()))
+    }
+}
+
+fn has_adult_badge(node: &Node, s: &Selectors) -> bool {
+    first(node, s.adult_badge).is_some()
+}
+
+// Japanese manga reads right-to-left; everything else here is a webtoon.
+// The "viewer" setting overrides the detection when set.
+fn viewer_for_type(kind: &str) -> MangaViewer {
+    match settings::viewer().as_str() {
+        "scroll" => return MangaViewer::Scroll,
+        "rtl" => return MangaViewer::Rtl,
+        "ltr" => return MangaViewer::Ltr,
+        "vertical" => return MangaViewer::Vertical,
+        _ => {}
+    }
+    if kind.eq_ignore_ascii_case("manga") { MangaViewer::Rtl } else { MangaViewer::Scroll }
+}
+
+// Map common status strings
+fn map_status(s: &str) -> MangaStatus {
+    let s = s.to_lowercase().replace(['-', '_'], " ");
+    // "Season End" is a break between seasons, not the end of the series
+    if s.contains("season end") || s.contains("hiatus") || s.contains("on hold") || s.contains("paused") { MangaStatus::Hiatus }
+    else if s.contains("dropped") || s.contains("cancel") || s.contains("discontinued") { MangaStatus::Cancelled }
+    else if s.contains("ongoing") || s.contains("on going") || s.contains("publishing") { MangaStatus::Ongoing }
+    else if s.contains("completed") || s.contains("complete") || s.contains("finished") || s.trim() == "end" { MangaStatus::Completed }
+    else { MangaStatus::Unknown }
+}
+
+// Value of the first info row whose label starts with one of `labels`
+// ("Author(s)", "Artist", ...). Rows without a separate label node, like
+// .imptdt's "Status <i>Ongoing</i>", are matched on their own text.
+fn info_row(dom: &Node, s: &Selectors, labels: &[&str]) -> Option<String> {
+    for row in select(dom, s.info_row) {
+        let label = first(&row, s.info_label).map(|l| text(&l)).unwrap_or_else(|| text(&row)).to_lowercase();
+        if !labels.iter().any(|l| label.starts_with(l)) { continue; }
+
+        let Some(value) = first(&row, s.info_value).map(|v| text(&v)) else { continue };
+        let value = value.trim().trim_start_matches(':').trim();
+        if !value.is_empty() && value != "-" && !value.eq_ignore_ascii_case("updating") {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+// Announced chapters the site lists before they're out
+fn is_upcoming(name: &str) -> bool {
+    name.to_lowercase().contains("coming soon")
+}
+
+// Coin/lock marker on a chapter entry, or a premium class on the entry itself
+// `markers` is false when the page has no lock markers at all, which skips
+// the per-chapter query
+fn is_locked(chapter: &Node, s: &Selectors, markers: bool) -> bool {
+    let class = attr(chapter, "class").unwrap_or_default();
+    class.split_whitespace().any(|c| c == "premium" || c == "locked" || c == "premium-block")
+        || (markers && first(chapter, s.chapter_lock).is_some())
+}
+
+// A "next" link, or any numbered page link past the current one
+fn has_next_page(dom: &Node, s: &Selectors, page: i32) -> bool {
+    if first(dom, s.pagination_next).is_some() {
+        return true;
+    }
+    let current = page.max(1);
+    select(dom, s.page_numbers)
+        .into_iter()
+        .filter_map(|n| text(&n).parse::<i32>().ok())
+        .any(|n| n > current)
+}
+
+fn is_placeholder(url: &str) -> bool {
+    let lower = url.to_lowercase();
+    if lower.starts_with("data:") {
+        // Blank pixels are tiny gif/svg payloads; real inline pages are not
+        return lower.starts_with("data:image/gif") || lower.starts_with("data:image/svg") || url.len() < 256;
+    }
+    PLACEHOLDERS.iter().any(|p| lower.contains(p))
+}
+
+// Best real image URL of an <img>: lazy attributes, then srcset (largest),
+// then src (usually the placeholder when any of the others is set),
+// skipping anything that is a placeholder
+fn image_url(img: &Node) -> Option<String> {
+    let srcset = || attr(img, "data-srcset").or_else(|| attr(img, "srcset")).and_then(|s| parse::best_srcset(&s));
+    LAZY_ATTRS
+        .iter()
+        .filter_map(|a| attr(img, a))
+        .chain(srcset())
+        .chain(attr(img, "src"))
+        .map(|u| u.trim().to_string())
+        .find(|u| !u.is_empty() && !is_placeholder(u))
+        .map(|u| abs(&u))
+}
+
+fn extract_cover(node: &Node, s: &Selectors) -> Option<String> {
+    let img = first(node, s.cover)?;
+    // undo CDN resizing so covers aren't blurry
+    image_url(&img).map(|u| parse::full_size_image(&u))
+}
+
+// Last resort for a series with no cover image: the first page of its first
+// chapter, the list's last row. Costs a chapter fetch, and lists loaded over
+// AJAX aren't in the page, so it's only tried once nothing else worked.
+fn first_page_cover(dom: &Node, s: &Selectors) -> Option<String> {
+    let row = select(dom, s.chapter_list).into_iter().last()?;
+    let href = attr(&row, "href").or_else(|| first(&row, "a").and_then(|a| attr(&a, "href")))?;
+    let chapter = get_dom(&url::list_style(&abs(&href))).ok()?;
+    debug!("cover: none on the series page, using the first page of {href}");
+    page_urls(&chapter).into_iter().find(|u| u.starts_with("http"))
+}
+
+// ---- Source impl ----
+// A paginated grid: its cards and whether another page follows, from one
+// theme detection
+fn listing_page(dom: &Node, page: i32) -> MangaPageResult {
+    let s = theme::selectors(dom);
+    MangaPageResult { manga: parse_manga_items(dom, s, |s| s.list_item), has_more: has_next_page(dom, s, page) }
+}
+
+// Strict mode: a result that came back empty where the page can't really be
+// empty is reported as a miss on the `field` selector. Otherwise the empty
+// result stands and the caller's fallbacks apply.
+fn strict_miss(field: &'static str) -> Result<()> {
+    if settings::strict_mode() {
+        debug!("strict: {field} matched nothing");
+        return Err(error::SourceError::Layout(field).into());
+    }
+    Ok(())
+}
+
+// Parse listing/search cards into Manga entries; `items` picks the card
+// selector (the listing grid or one of the homepage widgets)
+fn parse_manga_items(dom: &Node, s: &Selectors, items: fn(&Selectors) -> &'static str) -> Vec<Manga> {
+    let mut mangas: Vec<Manga> = Vec::new();
+    // LIST_ITEM is a union of selectors, so the same card can match twice
+    let mut seen: HashSet<String> = HashSet::new();
+    let blocklist = Blocklist::load();
+
+    let items = select(dom, items(s));
+    let matched = items.len();
+    for item in items {
+        let title_node = first(&item, s.title);
+        let title = title_node.as_ref().map(|n| parse::clean_title(&text(n))).unwrap_or_default();
+
+        let href_node = first(&item, s.href);
+        let href = href_node.and_then(|n| attr(&n, "href")).unwrap_or_default();
+
+        if title.is_empty() || href.is_empty() { continue; }
+        if !seen.insert(normalize_href(&href)) { continue; }
+
+        // Search cards list their genres; listing cards usually don't
+        let categories: Vec<String> = select(&item, s.list_genres)
+            .into_iter()
+            .map(|g| text(&g))
+            .filter(|g| !g.is_empty())
+            .collect();
+
+        let cover = extract_cover(&item, s);
+        let nsfw = content_rating(&categories, has_adult_badge(&item, s));
+        let id = url::manga_id(&href);
+        let manga = Manga {
+            url: url::id_url(&id),
+            id,
+            cover: cover.unwrap_or_default(),
+            title,
+            author: String::new(),
+            artist: String::new(),
+            description: String::new(),
+            categories,
+            status: MangaStatus::Unknown,
+            nsfw,
+            viewer: viewer_for_type(""), // typical for webtoon/manhua
+        };
+        if !blocklist.hides(&manga) { mangas.push(manga); }
+    }
+
+    debug!("cards: {matched} matched, {} kept", mangas.len());
+    mangas
+}
+
+// Card scores by series ID, on the 5-star scale of the rating filter
+fn card_ratings(dom: &Node, s: &Selectors) -> Vec<(String, f32)> {
+    let scale = if theme::theme(dom) == Theme::MangaStream { 2.0 } else { 1.0 };
+    select(dom, s.list_item)
+        .iter()
+        .filter_map(|item| {
+            let href = first(item, s.href).and_then(|n| attr(&n, "href"))?;
+            let score = text(&first(item, s.card_rating)?).parse::<f32>().ok()?;
+            Some((url::manga_id(&href), score / scale))
+        })
+        .collect()
+}
+
+// Cleared along with the other caches, so a site that gains the API (or a
+// new domain that has it) gets another try
+static REST_UNAVAILABLE: Mutex<bool> = Mutex::new(false);
+
+// Plain title searches go through the REST API when the site allows it
+fn rest_search(params: &SearchParams) -> Option<MangaPageResult> {
+    let mut unavailable = REST_UNAVAILABLE.lock().unwrap_or_else(|e| e.into_inner());
+    if *unavailable || !params.is_plain_query() { return None; }
+    let Ok(posts) = rest::search(&params.query, params.page) else {
+        debug!("search: REST API unavailable, not trying it again");
+        *unavailable = true;
+        return None;
+    };
+    let blocklist = Blocklist::load();
+    let has_more = posts.len() >= rest::PER_PAGE;
+    let manga = posts
+        .into_iter()
+        .map(|p| light_manga(&p.link, &p.title, p.cover, p.categories))
+        .filter(|m| !blocklist.hides(m))
+        .collect();
+    Some(MangaPageResult { manga, has_more })
+}
+
+static LIVE_SEARCH_UNAVAILABLE: Mutex<bool> = Mutex::new(false);
+
+// The theme's live search has no paging, so it only stands in for page 1
+fn live_search(params: &SearchParams) -> Option<MangaPageResult> {
+    let mut unavailable = LIVE_SEARCH_UNAVAILABLE.lock().unwrap_or_else(|e| e.into_inner());
+    if *unavailable || params.page > 1 || !params.is_plain_query() { return None; }
+    let Ok(hits) = ajax::search(&params.query) else {
+        debug!("search: live search unavailable, not trying it again");
+        *unavailable = true;
+        return None;
+    };
+    // Empty may just mean the dropdown is stricter than the results page
+    if hits.is_empty() {
+        debug!("search: live search found nothing, trying the results page");
+        return None;
+    }
+    let blocklist = Blocklist::load();
+    let manga = hits
+        .into_iter()
+        .map(|h| light_manga(&h.link, &h.title, h.cover, Vec::new()))
+        .filter(|m| !blocklist.hides(m))
+        .collect();
+    Some(MangaPageResult { manga, has_more: false })
+}
+
+// The site's search is word-order sensitive and buries exact matches, which
+// Aidoku's migration matching depends on; order each page by similarity
+fn rank(mut result: MangaPageResult, query: &str) -> MangaPageResult {
+    if query.trim().is_empty() { return result; }
+    let mut scored: Vec<(f32, Manga)> = result.manga.into_iter().map(|m| (parse::title_similarity(query, &m.title), m)).collect();
+    // Stable, so equally close titles keep the site's order
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(core::cmp::Ordering::Equal));
+    result.manga = scored.into_iter().map(|(_, m)| m).collect();
+    result
+}
+
+fn search(params: SearchParams) -> Result<MangaPageResult> {
+    let query = params.query.clone();
+    let fallback = params.page <= 1 && params.is_plain_query();
+    let mut result = search_unranked(params)?;
+    if fallback && result.manga.is_empty() {
+        // Best effort: a missing sitemap leaves the empty result as it was
+        debug!("search: no results for {query:?}, matching the sitemap catalog");
+        if let Ok(local) = local_search(&query) { result = local; }
+    }
+    Ok(rank(result, &query))
+}
+
+fn search_unranked(params: SearchParams) -> Result<MangaPageResult> {
+    if let Some(result) = rest_search(&params).or_else(|| live_search(&params)) {
+        return Ok(result);
+    }
+    let viewer = viewer_for_type(params.kind);
+    let dom = get_dom_cached(&params.url())?;
+    let MangaPageResult { manga: mut mangas, has_more } = listing_page(&dom, params.page);
+    if mangas.is_empty() && params.page == 1 && params.is_browse() { strict_miss("list_item")?; }
+    // Madara has no genre exclusion, so drop excluded genres client-side.
+    // Cards that don't list their genres can't be filtered and are kept.
+    mangas.retain(|m| !m.categories.iter().any(|c| params.excluded.contains(&slugify(c))));
+    // Some setups drop the genre query once there's a search term; enforce it
+    // on the cards that list their genres
+    if !params.query.is_empty() && !params.genres.is_empty() {
+        mangas.retain(|m| {
+            let slugs: Vec<String> = m.categories.iter().map(|c| slugify(c)).collect();
+            slugs.is_empty() || params.genres.iter().all(|g| slugs.contains(g))
+        });
+    }
+    // Same for the rating floor; unrated cards are kept
+    if params.min_rating > 0.0 {
+        let ratings = card_ratings(&dom, theme::selectors(&dom));
+        mangas.retain(|m| ratings.iter().find(|(id, _)| *id == m.id).is_none_or(|(_, r)| *r >= params.min_rating));
+    }
+    for m in mangas.iter_mut() {
+        // "Only 18+" results are adult whether or not the card says so
+        if params.adult == "1" { m.nsfw = MangaContentRating::Nsfw; }
+        m.viewer = viewer;
+    }
+
+    Ok(MangaPageResult { manga: mangas, has_more })
+}
+
+// Browsing without a listing selected is a filtered search
+pub fn get_manga_list(site: &'static Site, filters: Vec<Filter>, page: i32) -> Result<MangaPageResult> {
+    enter(site);
+    search(SearchParams::from_filters(filters, page))
+}
+
+// Listings declared in aidoku.json
+pub fn get_manga_listing(site: &'static Site, listing: Listing, page: i32) -> Result<MangaPageResult> {
+    enter(site);
+    let name = i18n::canonical(&listing.name);
+    // Popular widgets live on the homepage and aren't paginated
+    let widget: Option<fn(&Selectors) -> &'static str> = match name {
+        "Popular Today" => Some(|s| s.popular_today),
+        "Popular Weekly" => Some(|s| s.popular_weekly),
+        "Popular Monthly" => Some(|s| s.popular_monthly),
+        "Popular All Time" => Some(|s| s.popular_all_time),
+        _ => None,
+    };
+    if let Some(items) = widget {
+        if page > 1 { return Ok(MangaPageResult { manga: Vec::new(), has_more: false }); }
+        let dom = get_dom_cached(&settings::base_url())?;
+        let manga = parse_manga_items(&dom, theme::selectors(&dom), items);
+        return Ok(MangaPageResult { manga, has_more: false });
+    }
+
+    if name == "A-Z" {
+        return get_az_page(page);
+    }
+    if name == "All Series" {
+        return get_catalog_page(page);
+    }
+    if name == "Related Series" {
+        return Ok(get_related_page(page));
+    }
+    if name == "Updated Today" {
+        return get_updated_today(page);
+    }
+    if name == "Announcements" {
+        return Ok(announcements::listing(page));
+    }
+    if name == "My Bookmarks" {
+        return account::bookmarks(page);
+    }
+    if name == "Diagnostics" {
+        if page > 1 { return Ok(MangaPageResult { manga: Vec::new(), has_more: false }); }
+        return Ok(diagnostics::run());
+    }
+    // Random ordering reshuffles every request, so paging it makes no sense
+    if name == "Random" {
+        if page > 1 { return Ok(MangaPageResult { manga: Vec::new(), has_more: false }); }
+        let dom = get_dom(&SearchParams { sort: Some("random"), ..Default::default() }.url())?;
+        let manga = parse_manga_items(&dom, theme::selectors(&dom), |s| s.list_item);
+        return Ok(MangaPageResult { manga, has_more: false });
+    }
+    // "Genre: Martial Arts" -> /manga-genre/martial-arts/
+    if let Some(genre) = name.strip_prefix("Genre: ") {
+        let slug = genres::resolve(genre).unwrap_or_else(|| slugify(genre));
+        let dom = get_dom_cached(&url::archive_url(&format!("manga-genre/{slug}"), page))?;
+        return Ok(listing_page(&dom, page));
+    }
+    // In-house translations, kept apart from aggregated series
+    if name == "Projects" {
+        let dom = get_dom_cached(&url::archive_url("project", page))?;
+        return Ok(listing_page(&dom, page));
+    }
+
+    let params = match name {
+        "Popular" => SearchParams { page, sort: Some("trending"), ..Default::default() },
+        // Newly added series rather than newly updated ones
+        "New Series" => SearchParams { page, sort: Some("new-manga"), ..Default::default() },
+        // Finished series, most recently updated first
+        "Completed" => SearchParams { page, sort: Some("latest"), status: "end", ..Default::default() },
+        _ => SearchParams { page, sort: Some("latest"), ..Default::default() },
+    };
+    let latest = !matches!(name, "Popular" | "New Series" | "Completed");
+    if latest && settings::latest_from_feed() {
+        return get_feed_page(page);
+    }
+    // The homepage block follows the site's own update order; the search
+    // sort misses series and lags behind it
+    if latest {
+        let result = get_latest_page(page)?;
+        if page > 1 || !result.manga.is_empty() { return Ok(result); }
+        debug!("{}: no latest block on the homepage, using the search listing", name);
+    }
+    let result = listing_page(&get_dom_cached(&params.url())?, page);
+    // An empty first page usually means the theme changed under us
+    if page == 1 && result.manga.is_empty() {
+        strict_miss("list_item")?;
+        if latest {
+            debug!("{}: listing page empty, falling back to the feed", name);
+            return get_feed_page(page);
+        }
+    }
+    Ok(result)
+}
+
+// "Updated Today": the homepage's latest-updates cards whose newest chapter
+// went up today, a calendar day in the site's timezone. The block is in
+// update order, so its pages are walked until a card from an earlier day.
+const MAX_TODAY_PAGES: i32 = 5;
+
+fn get_updated_today(page: i32) -> Result<MangaPageResult> {
+    if page > 1 { return Ok(MangaPageResult { manga: Vec::new(), has_more: false }); }
+    let offset = settings::utc_offset() * 3600.0;
+    let day = |epoch: f64| ((epoch + offset) / 86400.0).floor();
+    let today = day(host::current_date());
+    let mut manga: Vec<Manga> = Vec::new();
+    for home in 1..=MAX_TODAY_PAGES {
+        let dom = get_dom_cached(&url::home_url(home))?;
+        let s = theme::selectors(&dom);
+        // Cards without a readable date can't be placed, and are left out
+        let dates: Vec<(String, f64)> = select(&dom, s.latest_item)
+            .iter()
+            .filter_map(|item| {
+                let href = first(item, s.href).and_then(|n| attr(&n, "href"))?;
+                // Madara's "new" tag keeps the time in its title, with the
+                // surrounding date text left empty
+                let date = select(item, s.latest_date)
+                    .iter()
+                    .find_map(|n| attr(n, "title").and_then(|d| parse::parse_upload_date(&d)).or_else(|| parse::parse_upload_date(&text(n))))?;
+                Some((url::manga_id(&href), date))
+            })
+            .collect();
+        let older = dates.iter().any(|(_, d)| day(*d) < today);
+        let cards = parse_manga_items(&dom, s, |s| s.latest_item);
+        manga.extend(cards.into_iter().filter(|m| dates.iter().any(|(id, d)| *id == m.id && day(*d) == today)));
+        if older || !has_next_page(&dom, s, home) { break; }
+    }
+    Ok(MangaPageResult { manga, has_more: false })
+}
+
+// Homepage "Latest Update" cards. Until the details load, each card's
+// description names the chapter that put it there.
+fn get_latest_page(page: i32) -> Result<MangaPageResult> {
+    let dom = get_dom_cached(&url::home_url(page))?;
+    let s = theme::selectors(&dom);
+    let newest: Vec<(String, String)> = select(&dom, s.latest_item)
+        .iter()
+        .filter_map(|item| {
+            let href = first(item, s.href).and_then(|n| attr(&n, "href"))?;
+            let chapter = first(item, s.latest_chapter).map(|n| text(&n)).filter(|c| !c.is_empty())?;
+            Some((url::manga_id(&href), chapter))
+        })
+        .collect();
+    let mut manga = parse_manga_items(&dom, s, |s| s.latest_item);
+    for m in manga.iter_mut() {
+        if let Some((_, chapter)) = newest.iter().find(|(id, _)| *id == m.id) {
+            m.description = format!("Latest: {chapter}");
+        }
+    }
+    Ok(MangaPageResult { manga, has_more: has_next_page(&dom, s, page) })
+}
+
+// Card for an entry from a feed or the REST API: whatever the listing
+// carried, the rest fills in on the details page
+fn light_manga(link: &str, title: &str, cover: Option<String>, categories: Vec<String>) -> Manga {
+    let id = url::manga_id(link);
+    Manga {
+        url: url::id_url(&id),
+        id,
+        cover: cover.map(|c| parse::full_size_image(&c)).unwrap_or_default(),
+        title: parse::clean_title(title),
+        author: String::new(),
+        artist: String::new(),
+        description: String::new(),
+        nsfw: content_rating(&categories, false),
+        categories,
+        status: MangaStatus::Unknown,
+        viewer: viewer_for_type(""),
+    }
+}
+
+fn get_feed_page(page: i32) -> Result<MangaPageResult> {
+    let blocklist = Blocklist::load();
+    let mut seen: HashSet<String> = HashSet::new();
+    let items = feed::items(&http::get_text_cached(&url::feed_url(page))?);
+    // Feeds page until they run dry; there's no total to compare against
+    let has_more = !items.is_empty();
+    let manga = items
+        .into_iter()
+        .filter(|item| seen.insert(normalize_href(&item.link)))
+        .map(|item| light_manga(&item.link, &item.title, item.cover, item.categories))
+        .filter(|m| !blocklist.hides(m))
+        .collect();
+    Ok(MangaPageResult { manga, has_more })
+}
+
+// Sitemap catalog, fetched on page 1 and paged through locally. Entries
+// carry only the slug-derived title; details load when one is opened.
+static CATALOG: Mutex<Vec<String>> = Mutex::new(Vec::new());
+const CATALOG_PAGE_SIZE: usize = 50;
+
+fn load_catalog(catalog: &mut Vec<String>) -> Result<()> {
+    *catalog = sitemap::series_urls()?;
+    catalog.sort_by_key(|u| u.to_lowercase());
+    catalog.dedup_by(|a, b| normalize_href(a) == normalize_href(b));
+    Ok(())
+}
+
+fn catalog_manga(href: &str) -> Manga {
+    let id = url::manga_id(href);
+    let slug = id.trim_matches('/').rsplit('/').next().unwrap_or_default();
+    light_manga(href, &slug_title(slug), None, Vec::new())
+}
+
+fn get_catalog_page(page: i32) -> Result<MangaPageResult> {
+    let mut catalog = CATALOG.lock().unwrap_or_else(|e| e.into_inner());
+    if page <= 1 || catalog.is_empty() {
+        load_catalog(&mut catalog)?;
+    }
+    let start = (page.max(1) as usize - 1) * CATALOG_PAGE_SIZE;
+    let blocklist = Blocklist::load();
+    let manga = catalog
+        .iter()
+        .skip(start)
+        .take(CATALOG_PAGE_SIZE)
+        .map(|h| catalog_manga(h))
+        .filter(|m| !blocklist.hides(m))
+        .collect();
+    Ok(MangaPageResult { manga, has_more: start + CATALOG_PAGE_SIZE < catalog.len() })
+}
+
+// Below this a catalog title isn't considered a match for the query
+const LOCAL_MATCH: f32 = 0.5;
+
+// Site search finds nothing for many titles with punctuation; match the
+// query against the catalog's titles instead
+fn local_search(query: &str) -> Result<MangaPageResult> {
+    let mut catalog = CATALOG.lock().unwrap_or_else(|e| e.into_inner());
+    if catalog.is_empty() {
+        load_catalog(&mut catalog)?;
+    }
+    let needle = parse::normalize_title(query);
+    let blocklist = Blocklist::load();
+    let manga = catalog
+        .iter()
+        .map(|h| catalog_manga(h))
+        .filter(|m| {
+            parse::normalize_title(&m.title).contains(&needle) || parse::title_similarity(query, &m.title) >= LOCAL_MATCH
+        })
+        .filter(|m| !blocklist.hides(m))
+        .take(CATALOG_PAGE_SIZE)
+        .collect();
+    Ok(MangaPageResult { manga, has_more: false })
+}
+
+// A-Z directory walk: Aidoku page N maps to some (letter, letter page) pair,
+// so remember where the previous page ended and continue from there.
+const AZ_LETTERS: &[&str] = &[
+    ".", "0-9", "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M",
+    "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z",
+];
+
+struct AzCursor {
+    page: i32,
+    letter: usize,
+    letter_page: i32,
+    // whether the current letter had a next page
+    more: bool,
+}
+
+static AZ_CURSOR: Mutex<AzCursor> = Mutex::new(AzCursor { page: 0, letter: 0, letter_page: 0, more: false });
+
+fn get_az_page(page: i32) -> Result<MangaPageResult> {
+    let mut c = AZ_CURSOR.lock().unwrap_or_else(|e| e.into_inner());
+    // Aidoku requests pages in order; anything else restarts the walk
+    if page <= 1 || page != c.page + 1 {
+        *c = AzCursor { page: 0, letter: 0, letter_page: 0, more: true };
+    }
+    c.page = page.max(1);
+
+    loop {
+        if c.more {
+            c.letter_page += 1;
+        } else {
+            c.letter += 1;
+            c.letter_page = 1;
+        }
+        let Some(letter) = AZ_LETTERS.get(c.letter) else {
+            return Ok(MangaPageResult { manga: Vec::new(), has_more: false });
+        };
+
+        let dom = get_dom_cached(&url::az_url(letter, c.letter_page))?;
+        let MangaPageResult { manga, has_more } = listing_page(&dom, c.letter_page);
+        c.more = has_more;
+        // Skip letters with no entries instead of returning an empty page
+        if manga.is_empty() && !c.more { continue; }
+
+        let has_more = c.more || c.letter + 1 < AZ_LETTERS.len();
+        return Ok(MangaPageResult { manga, has_more });
+    }
+}
+
+// The canonical path when it's a different series path in the same section
+// ("/manga/old-slug/" -> "/manga/new-slug/"). SEO plugins that point every
+// page at the homepage or an archive are ignored.
+fn renamed_id(id: &str, canonical: &str) -> Option<String> {
+    let (old, new) = (url::path_id(&url::id_url(id)), url::path_id(canonical));
+    let section = |p: &str| p.trim_matches('/').split('/').next().map(String::from);
+    let depth = |p: &str| p.trim_matches('/').split('/').count();
+    let renamed = normalize_href(&old) != normalize_href(&new) && section(&old) == section(&new) && depth(&old) == depth(&new);
+    if renamed { debug!("details: {old} moved to {new}"); }
+    renamed.then(|| url::manga_id(&new))
+}
+
+pub fn get_manga_details(site: &'static Site, id: String) -> Result<Manga> {
+    enter(site);
+    if id == announcements::ID { return Ok(announcements::details()); }
+    if diagnostics::is_row(&id) { return Ok(diagnostics::details(&id)); }
+    let dom = get_series_dom(&url::id_url(&id))?;
+    // A renamed series 301s to its new slug; hand Aidoku the ID it lives under now
+    let id = meta::canonical_url(&dom).and_then(|c| renamed_id(&id, &c)).unwrap_or(id);
+    let url = url::id_url(&id);
+    let s = theme::selectors(&dom);
+    let info = first(&dom, s.manga_meta);
+    // Structured data, used wherever the selectors come up empty
+    let fallback = meta::series_meta(&dom);
+
+    // Title
+    let title = first(&dom, s.details_title)
+        .map(|n| parse::clean_title(&text(&n)))
+        .filter(|t| !t.is_empty())
+        .or_else(|| fallback.title.as_deref().map(parse::clean_title));
+    if title.is_none() { strict_miss("details_title")?; }
+    let title = title.unwrap_or_else(|| "Unknown".into());
+
+    // Description
+    let mut description = info
+        .as_ref()
+        .and_then(|n| first(n, s.summary))
+        .map(|n| parse::clean_description(&n.html().read()))
+        .filter(|d| !d.is_empty())
+        .or_else(|| fallback.description.as_deref().map(parse::clean_description))
+        .unwrap_or_default();
+
+    // "★ 4.3/5 · 1.2K followers" above the summary
+    let header = details_header(&dom, s);
+    if !header.is_empty() {
+        description = if description.is_empty() { header } else { format!("{header}\n\n{description}") };
+    }
+
+    // Genres
+    let mut genres = Vec::new();
+    if let Some(meta) = &info {
+        for g in select(meta, s.genres) {
+            let t = text(&g);
+            if !t.is_empty() { genres.push(t); }
+        }
+    }
+
+    // Status: the theme's status cell, else whichever info row is labelled so
+    let status = info
+        .as_ref()
+        .and_then(|n| first(n, s.status))
+        .map(|n| text(&n))
+        .or_else(|| info_row(&dom, s, &["status"]))
+        .map(|v| map_status(&v))
+        .unwrap_or(MangaStatus::Unknown);
+
+    // Extra metadata, one "Label: value" line each under the summary.
+    // Alternative titles matter most: many Korean series are only known by them.
+    let mut meta: Vec<String> = Vec::new();
+    // Opened from the library or a link, so it got past the listing filter
+    let blocked = Blocklist::load().blocked_genres(&genres).join(", ");
+    if !blocked.is_empty() {
+        meta.push(format!("Blocked genres: {blocked}"));
+    }
+    if let Some(alt) = info_row(&dom, s, &["alternative", "alt title", "other name"]) {
+        meta.push(format!("Alternative: {alt}"));
+    }
+    if let Some(v) = info_row(&dom, s, &["serialization"]) {
+        meta.push(format!("Serialization: {v}"));
+    }
+    if let Some(v) = info_row(&dom, s, &["released", "release"]) {
+        meta.push(format!("Released: {v}"));
+    }
+    // Dates are shown as YYYY-MM-DD whatever format the site used
+    for (labels, name) in [(&["posted"][..], "Posted"), (&["updated"][..], "Updated")] {
+        if let Some(v) = info_row(&dom, s, labels) {
+            let v = parse::parse_date(&v).map(parse::format_date).unwrap_or(v);
+            meta.push(format!("{name}: {v}"));
+        }
+    }
+    // "Trackers: MAL 12345, AniList 678", for cross-referencing and migration
+    let trackers = meta::trackers(&dom);
+    if !trackers.is_empty() {
+        let ids: Vec<String> = trackers.iter().map(|t| format!("{} {}", t.name, t.id)).collect();
+        meta.push(format!("Trackers: {}", ids.join(", ")));
+    }
+    let related = related_series(&dom, s);
+    if !related.is_empty() {
+        let titles: Vec<&str> = related.iter().map(|r| r.title.as_str()).collect();
+        meta.push(format!("Related: {}", titles.join(", ")));
+    }
+    *LAST_RELATED.lock().unwrap_or_else(|e| e.into_inner()) = related;
+    if !meta.is_empty() {
+        if !description.is_empty() { description.push_str("\n\n"); }
+        description.push_str(&meta.join("\n"));
+    }
+
+    // Only the badge next to the series title; sidebar cards carry their own
+    let adult_badge = first(&dom, s.details_adult_badge).is_some();
+    let nsfw = content_rating(&genres, adult_badge);
+
+    // Viewer from the series type: RTL for manga, scroll for manhwa/manhua
+    let viewer = viewer_for_type(&info_row(&dom, s, &["type"]).unwrap_or_default());
+
+    // Author / Artist
+    let author = info_row(&dom, s, &["author"]).unwrap_or_default();
+    let artist = info_row(&dom, s, &["artist"]).unwrap_or_default();
+
+    // Cover: og:image, structured data, the thumbnail block, then the first
+    // page of the first chapter
+    let cover = first(&dom, "meta[property='og:image']")
+        .and_then(|m| attr(&m, "content"))
+        .filter(|c| !c.is_empty() && !is_placeholder(c))
+        .or(fallback.cover)
+        .or_else(|| first(&dom, s.details_cover).and_then(|img| image_url(&img)))
+        .or_else(|| first_page_cover(&dom, s))
+        .map(|c| parse::full_size_image(&c))
+        .unwrap_or_default();
+
+    Ok(Manga {
+        id,
+        cover,
+        title,
+        author,
+        artist,
+        description,
+        url,
+        categories: genres,
+        status,
+        nsfw,
+        viewer,
+    })
+}
+
+// Score and follower count from a series page, whichever are there
+fn details_header(dom: &Node, s: &Selectors) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    // Madara rates out of 5, MangaStream out of 10
+    let scale = if theme::theme(dom) == Theme::MangaStream { 10 } else { 5 };
+    let score = first(dom, s.details_rating).and_then(|n| text(&n).parse::<f32>().ok()).filter(|r| *r > 0.0);
+    if let Some(score) = score {
+        parts.push(format!("\u{2605} {score:.1}/{scale}"));
+    }
+    // "1.2K Users bookmarked This", "Followed by 1.2K people"
+    let followers = first(dom, s.details_followers).map(|n| text(&n)).unwrap_or_default();
+    if let Some(count) = followers.split_whitespace().find(|w| w.starts_with(|c: char| c.is_ascii_digit())) {
+        parts.push(format!("{count} followers"));
+    }
+    parts.join(" \u{b7} ")
+}
+
+// A card from a series page's related block
+struct Related {
+    href: String,
+    title: String,
+    cover: Option<String>,
+}
+
+fn related_series(dom: &Node, s: &Selectors) -> Vec<Related> {
+    let mut seen: HashSet<String> = HashSet::new();
+    select(dom, s.related_item)
+        .iter()
+        .filter_map(|item| {
+            let title = first(item, s.related_title).map(|n| parse::clean_title(&text(&n))).filter(|t| !t.is_empty())?;
+            let href = first(item, s.href).and_then(|n| attr(&n, "href"))?;
+            Some(Related { href, title, cover: extract_cover(item, s) })
+        })
+        .filter(|r| seen.insert(normalize_href(&r.href)))
+        .collect()
+}
+
+// Related block of the series opened last, for the "Related Series" listing;
+// Aidoku has no per-series listings
+static LAST_RELATED: Mutex<Vec<Related>> = Mutex::new(Vec::new());
+
+fn get_related_page(page: i32) -> MangaPageResult {
+    if page > 1 { return MangaPageResult { manga: Vec::new(), has_more: false }; }
+    let blocklist = Blocklist::load();
+    let manga = LAST_RELATED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|r| light_manga(&r.href, &r.title, r.cover.clone(), Vec::new()))
+        .filter(|m| !blocklist.hides(m))
+        .collect();
+    MangaPageResult { manga, has_more: false }
+}
+
+// Mixed raw/translated series: keep the preferred language's chapters. Lists
+// in a single language, or with none in the preferred one, are left alone.
+fn filter_language(chapters: &mut Vec<Chapter>) {
+    let lang = settings::chapter_language();
+    if lang == "all" || chapters.iter().all(|c| c.lang == chapters[0].lang) { return; }
+    if chapters.iter().any(|c| c.lang == lang) {
+        chapters.retain(|c| c.lang == lang);
+    }
+}
+
+fn cached_chapter(c: CachedChapter) -> Chapter {
+    Chapter {
+        url: url::id_url(&c.id),
+        id: c.id,
+        title: c.title,
+        volume: c.volume,
+        chapter: c.chapter,
+        date_updated: c.date_updated,
+        scanlator: c.scanlator,
+        lang: c.lang,
+    }
+}
+
+fn chapter_to_cache(c: &Chapter) -> CachedChapter {
+    CachedChapter {
+        id: c.id.clone(),
+        title: c.title.clone(),
+        volume: c.volume.clone(),
+        chapter: c.chapter.clone(),
+        date_updated: c.date_updated,
+        scanlator: c.scanlator.clone(),
+        lang: c.lang.clone(),
+    }
+}
+
+pub fn get_chapter_list(site: &'static Site, id: String) -> Result<Vec<Chapter>> {
+    enter(site);
+    if id == announcements::ID { return announcements::chapters(); }
+    if diagnostics::is_row(&id) { return Ok(Vec::new()); }
+    // Library refreshes ask for every series at once; inside the TTL the last
+    // parse is served as is
+    if let Some(cached) = memory::chapters(&id, settings::chapter_cache_ttl()) {
+        debug!("chapters: {} for {id}, from the chapter cache", cached.len());
+        let mut chapters: Vec<Chapter> = cached.into_iter().map(cached_chapter).collect();
+        filter_language(&mut chapters);
+        if settings::oldest_first() { chapters.reverse(); }
+        return Ok(chapters);
+    }
+    let url = url::id_url(&id);
+    let dom = get_series_dom(&url)?;
+    let s = theme::selectors(&dom);
+    let series = first(&dom, s.details_title)
+        .map(|n| parse::clean_title(&text(&n)))
+        .unwrap_or_default();
+
+    // An expired stored list still says what's already known: parsing stops at
+    // its newest unlocked chapter (locked ones may have opened up since) and
+    // the older chapters are taken from it
+    let known = if settings::chapter_cache_ttl() > 0.0 { memory::stored_chapters(&id) } else { None };
+    let anchor = known
+        .iter()
+        .flatten()
+        .find(|c| !c.title.starts_with('\u{1F512}'))
+        .map(|c| url::path_id(&c.id));
+
+    let (mut chapters, mut reached) = parse_chapters_until(&dom, s, &series, anchor.as_deref());
+    // Some Madara setups load the list over admin-ajax and ship none in the page
+    if chapters.is_empty() && !reached {
+        // The AJAX fragment has no theme markers; keep the series page's theme
+        debug!("chapters: none on {url}, loading them over AJAX");
+        (chapters, reached) = parse_chapters_until(&get_ajax_chapters(&dom, s, &url)?, s, &series, anchor.as_deref());
+    } else if !reached {
+        reached = crawl_chapter_pages(&dom, s, &series, &mut chapters, anchor.as_deref())?;
+    }
+    // A series with nothing at all (a brand-new one too) counts as a miss
+    if chapters.is_empty() && !reached { strict_miss("chapter_list")?; }
+    // Only a list that joins up with the stored one is merged; otherwise the
+    // full parse stands, so chapters removed from the site don't linger
+    if let (true, Some(known)) = (reached, known) {
+        debug!("chapters: {} new for {id}, the rest from the stored list", chapters.len());
+        chapters.extend(known.into_iter().map(cached_chapter));
+    }
+
+    // CHAPTER_LIST unions several selectors, so drop repeats before sorting.
+    // The same chapter from two groups is two entries, not a repeat.
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    chapters.retain(|c| seen.insert((normalize_href(&c.url), c.scanlator.clone())));
+    for c in &mut chapters {
+        c.id = url::chapter_id(&id, &c.id);
+    }
+    tag_group_ids(&mut chapters);
+
+    // Newest first by number; the sort is stable, so unnumbered chapters keep
+    // their DOM order at the end.
+    let number = |c: &Chapter| c.chapter.parse::<f32>().ok();
+    chapters.sort_by(|a, b| match (number(a), number(b)) {
+        (Some(x), Some(y)) => y.partial_cmp(&x).unwrap_or(core::cmp::Ordering::Equal),
+        (Some(_), None) => core::cmp::Ordering::Less,
+        (None, Some(_)) => core::cmp::Ordering::Greater,
+        (None, None) => core::cmp::Ordering::Equal,
+    });
+    // Stored newest first, so the order setting applies to cached lists too
+    if settings::chapter_cache_ttl() > 0.0 {
+        cache::drop_legacy_chapters();
+        memory::put_chapters(&id, chapters.iter().map(chapter_to_cache).collect());
+    }
+    filter_language(&mut chapters);
+    if settings::oldest_first() {
+        chapters.reverse();
+    }
+    debug!("chapters: {} for {id}", chapters.len());
+    Ok(chapters)
+}
+
+// Groups that share one link, told apart only by their label, get the group
+// as an ID fragment ("/manga/x/chapter-5/#team-a"), so each keeps its own
+// read state. id_url drops the fragment again when the chapter is loaded.
+fn tag_group_ids(chapters: &mut [Chapter]) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for c in chapters.iter() {
+        *counts.entry(normalize_href(&c.url)).or_default() += 1;
+    }
+    for c in chapters.iter_mut() {
+        // Entries from the stored list are tagged already
+        if counts[&normalize_href(&c.url)] > 1 && !c.id.contains('#') {
+            c.id = format!("{}#{}", c.id, slugify(&c.scanlator));
+        }
+    }
+}
+
+// Long series hide older chapters behind "Load more"/next links; follow them
+// until they run out. Capped so a looping link can't hang the refresh.
+const MAX_CHAPTER_PAGES: usize = 50;
+
+// True when a page reached `stop`, and the rest of the list is already known
+fn crawl_chapter_pages(dom: &Node, s: &Selectors, series: &str, chapters: &mut Vec<Chapter>, stop: Option<&str>) -> Result<bool> {
+    let mut next = first(dom, s.chapter_next).and_then(|n| attr(&n, "href"));
+    let mut seen: HashSet<String> = chapters.iter().map(|c| c.id.clone()).collect();
+    for _ in 0..MAX_CHAPTER_PAGES {
+        let Some(href) = next.take() else { break };
+        // Keep what we have if a later page fails
+        let Ok(page) = get_dom(&abs(&href)) else { break };
+        let (more, reached) = parse_chapters_until(&page, s, series, stop);
+        // A page that only repeats what we have means the link went in a circle
+        if !reached && more.iter().all(|c| seen.contains(&c.id)) { break; }
+        seen.extend(more.iter().map(|c| c.id.clone()));
+        chapters.extend(more);
+        if reached { return Ok(true); }
+        next = first(&page, s.chapter_next).and_then(|n| attr(&n, "href"));
+    }
+    Ok(false)
+}
+
+// Madara's AJAX chapter list: admin-ajax.php when the page exposes its post
+// id, otherwise the newer per-series {manga_url}/ajax/chapters/ endpoint
+fn get_ajax_chapters(dom: &Node, s: &Selectors, series_url: &str) -> Result<Node> {
+    let post_id = select(dom, s.manga_post_id)
+        .into_iter()
+        .find_map(|n| attr(&n, "data-id").or_else(|| attr(&n, "value")))
+        .filter(|v| !v.is_empty());
+    match post_id {
+        Some(post_id) => post_dom(
+            &format!("{}/wp-admin/admin-ajax.php", settings::base_url()),
+            &format!("action=manga_get_chapters&manga={post_id}"),
+        ),
+        None => post_dom(&format!("{}/ajax/chapters/", series_url.trim_end_matches('/')), ""),
+    }
+}
+
+// Seasons are usually marked only where they start ("S2 Chapter 50" or
+// "Season 2 Chapter 50") or end ("Season 1 Finale"); carry each to the
+// unmarked chapters after it, in chapter order
+fn fill_seasons(chapters: &mut [Chapter], ends: &[bool]) {
+    if chapters.iter().all(|c| c.volume.is_empty()) { return; }
+    let number = |c: &Chapter| c.chapter.parse::<f32>().ok();
+    let mut order: Vec<usize> = (0..chapters.len()).filter(|&i| number(&chapters[i]).is_some()).collect();
+    order.sort_by(|&a, &b| number(&chapters[a]).partial_cmp(&number(&chapters[b])).unwrap_or(core::cmp::Ordering::Equal));
+    let mut current: Option<String> = None;
+    for i in order {
+        let chapter = &mut chapters[i];
+        if chapter.volume.is_empty() {
+            if let Some(v) = &current { chapter.volume = v.clone(); }
+            continue;
+        }
+        current = match chapter.volume.parse::<f32>() {
+            Ok(v) if ends[i] => Some((v + 1.0).to_string()),
+            _ => Some(chapter.volume.clone()),
+        };
+    }
+}
+
+fn parse_chapters(dom: &Node, s: &Selectors, series: &str) -> Vec<Chapter> {
+    parse_chapters_until(dom, s, series, None).0
+}
+
+// Stops at the row linking to `stop` (a chapter path ID), leaving it out;
+// the flag says whether it was found
+fn parse_chapters_until(dom: &Node, s: &Selectors, series: &str, stop: Option<&str>) -> (Vec<Chapter>, bool) {
+    let mut reached = false;
+    let mut chapters: Vec<Chapter> = Vec::new();
+    let mut season_ends: Vec<bool> = Vec::new();
+    let hide_locked = settings::hide_locked();
+    let default_group = site().scanlator;
+    // Per-chapter queries dominate on 800+ chapter lists, so columns the
+    // page doesn't have anywhere are never looked up row by row
+    let has = |sel: &str| first(dom, sel).is_some();
+    let (has_date, has_group, has_lock) = (has(s.chapter_date), has(s.chapter_group), has(s.chapter_lock));
+    let has_name = has(s.chapter_name);
+
+    for a in select(dom, s.chapter_list) {
+        // Madara matches the <li>, with the link and its text on the inner <a>
+        let anchor = if attr(&a, "href").is_some() { None } else { first(&a, "a") };
+        let link = attr(anchor.as_ref().unwrap_or(&a), "href");
+
+        // MangaStream's link wraps the number and the date; only the former
+        // names the chapter
+        let name_node = if has_name { first(&a, s.chapter_name) } else { None };
+        let name = text(name_node.as_ref().or(anchor.as_ref()).unwrap_or(&a));
+        if let Some(href) = link {
+            if stop.is_some_and(|id| url::path_id(&href) == id) {
+                reached = true;
+                break;
+            }
+            let lang = parse::chapter_lang(&name);
+            // date (best-effort)
+            let date_str = if has_date { first(&a, s.chapter_date).map(|n| text(&n)) } else { None };
+            let date_updated = date_str.as_deref().and_then(parse::parse_upload_date);
+            let (volume, number) = parse::chapter_numbers(&name, &href);
+            let ends = parse::ends_season(&name);
+            // Their only page is a banner; listing them inflates unread counts
+            if is_upcoming(&name) { continue; }
+            // Keep the raw text when there's no number to fall back on. With
+            // one, a decorative leftover ("NEW", an emoji) is dropped too, and
+            // Aidoku names the chapter by the number, from the URL if need be.
+            let mut title = match number {
+                Some(_) => Some(parse::clean_chapter_title(&name, series))
+                    .filter(|t| !parse::is_decorative_title(t))
+                    .unwrap_or_default(),
+                None => name,
+            };
+
+            // Early-access chapters 404 or render empty until they unlock
+            if is_locked(&a, s, has_lock) {
+                if hide_locked { continue; }
+                title = if title.is_empty() { "\u{1F512}".into() } else { format!("\u{1F512} {title}") };
+            }
+            let group = if has_group { first(&a, s.chapter_group) } else { None };
+            let scanlator = group
+                .map(|n| text(&n))
+                .filter(|g| !g.is_empty())
+                .unwrap_or_else(|| default_group.into());
+
+            let id = url::path_id(&href);
+            chapters.push(Chapter {
+                url: url::id_url(&id),
+                id,
+                title,
+                volume: volume.map(|v| v.to_string()).unwrap_or_default(),
+                chapter: number.map(|c| c.to_string()).unwrap_or_default(),
+                date_updated,
+                scanlator,
+                lang: String::from(lang),
+            });
+            season_ends.push(ends);
+        }
+    }
+
+    fill_seasons(&mut chapters, &season_ends);
+    (chapters, reached)
+}
+
+// Page image URLs in reading order, before the mirror/quality rewrites
+fn page_urls(dom: &Node) -> Vec<String> {
+    let s = theme::selectors(dom);
+    let mut urls: Vec<String> = Vec::new();
+
+    let ads = ad_block_images(dom);
+    let extra_hosts = &remote::get().ad_hosts;
+    for img in select(dom, s.page_image) {
+        if is_ad_image(&img, extra_hosts) { continue; }
+        if let Some(u) = image_url(&img).filter(|u| !ads.contains(u)) { urls.push(u); }
+    }
+
+    // Only placeholders in the DOM: the real URLs are in <noscript>
+    if urls.is_empty() {
+        debug!("pages: no <img> pages, trying <noscript>");
+        urls = reader::noscript_pages(dom, s);
+    }
+    // JS-driven readers ship no <img> tags; read the script payload instead
+    if urls.is_empty() {
+        debug!("pages: trying the ts_reader payload");
+        urls = reader::ts_reader_pages(dom);
+    }
+    if urls.is_empty() {
+        debug!("pages: trying chapter_preloaded_images");
+        urls = reader::preloaded_pages(dom);
+    }
+    // Script payloads carry links as written, relative ones included
+    urls.into_iter().map(|u| abs(&u)).collect()
+}
+
+// The "skip pages" settings: a fixed count off each end, then pages at
+// either end whose file names look like credits or banners. A chapter is
+// never trimmed down to nothing.
+fn skip_credit_pages(urls: &mut Vec<String>) {
+    let (head, tail) = (settings::skip_first_pages(), settings::skip_last_pages());
+    if head + tail < urls.len() {
+        urls.truncate(urls.len() - tail);
+        urls.drain(..head);
+    }
+    if !settings::skip_credit_pages() { return; }
+    let credit = |u: &String| {
+        let u = u.to_lowercase();
+        CREDIT_URLS.iter().any(|p| u.contains(p))
+    };
+    while urls.len() > 1 && urls.first().is_some_and(credit) { urls.remove(0); }
+    while urls.len() > 1 && urls.last().is_some_and(credit) { urls.pop(); }
+}
+
+// Other sub-pages of a chapter that's split across several HTML pages, in
+// order. Only links under the chapter's own path count, so a pager that also
+// points at the next chapter isn't followed there.
+const MAX_SUBPAGES: usize = 60;
+
+fn chapter_subpages(dom: &Node, link: &str) -> Vec<String> {
+    let own = normalize_href(link);
+    let mut subpages: Vec<String> = Vec::new();
+    for node in select(dom, theme::selectors(dom).chapter_subpages) {
+        let Some(href) = attr(&node, "data-redirect").or_else(|| attr(&node, "href")) else { continue };
+        let href = url::resolve(&href, link);
+        let path = normalize_href(&href);
+        // Pagers list the first page too: same path, no page=N
+        let first_page = path == own && !href.contains("page=");
+        let under = path == own || path.starts_with(&format!("{own}/"));
+        if first_page || !under || subpages.contains(&href) { continue; }
+        subpages.push(href);
+        if subpages.len() == MAX_SUBPAGES { break; }
+    }
+    subpages
+}
+
+pub fn get_page_list(site: &'static Site, id: String) -> Result<Vec<Page>> {
+    enter(site);
+    if announcements::is_announcement(&id) { return announcements::pages(&id); }
+    let link = url::list_style(&url::id_url(&id));
+    // A taken-down chapter is read from its archived copy if there is one;
+    // `archived` is then the snapshot's timestamp
+    let (mut dom, archived) = match http::get_dom_unless_gone(&link)? {
+        Some(dom) => (dom, None),
+        None => {
+            let (dom, timestamp) = wayback::chapter(&url::id_url(&id)).ok_or(error::SourceError::Status(404))?;
+            (dom, Some(timestamp))
+        }
+    };
+    let mut urls = page_urls(&dom);
+    // Members-only chapters have no images for guests
+    if urls.is_empty() && archived.is_none() && account::log_in() {
+        dom = get_dom(&link)?;
+        urls = page_urls(&dom);
+    }
+    // Still paginated in list mode: the rest of the images are on sub-pages.
+    // An archived copy has only the page that was crawled.
+    let subpages = if archived.is_some() { Vec::new() } else { chapter_subpages(&dom, &link) };
+    if !subpages.is_empty() { debug!("pages: crawling {} sub-pages", subpages.len()); }
+    for sub in subpages {
+        match get_dom(&sub) {
+            Ok(page) => urls.extend(page_urls(&page)),
+            Err(_) => debug!("pages: sub-page {sub} failed"),
+        }
+    }
+    debug!("pages: {} for {id}", urls.len());
+    if let [only] = urls.as_slice() {
+        let only = only.to_lowercase();
+        if COMING_SOON.iter().any(|m| only.contains(m)) {
+            return Err(error::SourceError::Unsupported(String::from("This chapter hasn't been released yet")).into());
+        }
+    }
+    // Novel entries: the chapter is prose, returned as a single text page
+    if urls.is_empty() {
+        if let Some(text) = reader::novel_text(&dom, theme::selectors(&dom)) {
+            return Ok(vec![Page { index: 0, url: String::new(), base64: String::new(), text }]);
+        }
+        strict_miss("page_image")?;
+    }
+
+    // User-picked image mirror: same path, different host
+    let server = settings::image_server();
+    if !server.is_empty() && archived.is_none() {
+        for u in urls.iter_mut().filter(|u| u.starts_with("http")) {
+            *u = parse::replace_host(u, &server);
+        }
+    }
+
+    // Quality preference: strip the CDN's resize params, or ask for a smaller copy
+    let quality = settings::image_quality();
+    for u in urls.iter_mut().filter(|u| u.starts_with("http")) {
+        match quality.as_str() {
+            "original" => *u = parse::full_size_image(u),
+            "resized" => {
+                let full = parse::full_size_image(u);
+                let sep = if full.contains('?') { '&' } else { '?' };
+                *u = format!("{full}{sep}w={RESIZED_WIDTH}");
+            }
+            _ => {}
+        }
+    }
+
+    // Duplicate nodes (data-src + src copies) repeat pages
+    let mut seen: HashSet<String> = HashSet::new();
+    urls.retain(|u| seen.insert(u.clone()));
+    // Images of a removed chapter are usually gone from the CDN too
+    if let Some(timestamp) = &archived {
+        for u in urls.iter_mut().filter(|u| u.starts_with("http")) {
+            *u = wayback::image_url(timestamp, u);
+        }
+    }
+
+    // DOM order sometimes disagrees with the numbered file names. Only trust
+    // the names when every page has a distinct number, otherwise keep DOM order.
+    let numbers: Vec<Option<u32>> = urls.iter().map(|u| parse::filename_number(u)).collect();
+    let distinct: HashSet<u32> = numbers.iter().flatten().copied().collect();
+    if distinct.len() == urls.len() {
+        urls.sort_by_key(|u| parse::filename_number(u));
+    }
+    skip_credit_pages(&mut urls);
+
+    // Scrambled chapters: each page is fetched, its tiles put back in order
+    // and the result handed over inline. One that can't be (WebP, too large,
+    // a failed fetch) is left as a URL and shows jumbled.
+    let tiles = if archived.is_none() { reader::tile_layout(&dom) } else { None };
+    if tiles.is_some() { debug!("pages: scrambled tiles, reassembling {} pages", urls.len()); }
+
+    // Hotlink-protected hosts: fetch each page here, with cookies and the
+    // chapter as Referer. A page that still fails is left as a URL.
+    let inline = settings::inline_images();
+    Ok(urls
+        .into_iter()
+        .enumerate()
+        .map(|(index, url)| {
+            // Inline data URIs can't be loaded as URLs; hand Aidoku the payload
+            let base64 = url
+                .strip_prefix("data:image/")
+                .and_then(|d| d.split_once(";base64,"))
+                .map(|(_, data)| data.to_string())
+                .or_else(|| {
+                    let layout = tiles.as_ref().filter(|l| url.starts_with("http") && l.covers(&url))?;
+                    let data = http::get_image(&url, &link).ok()?;
+                    tiles::unscramble(&data, &url, layout).map(|jpeg| parse::base64_encode(&jpeg))
+                })
+                .or_else(|| {
+                    // Archive requests don't get the site's cookies
+                    if !inline || archived.is_some() || !url.starts_with("http") { return None; }
+                    http::get_image(&url, &link).ok().map(|data| parse::base64_encode(&data))
+                });
+            match base64 {
+                Some(base64) => Page { index: index as i32, url: String::new(), base64, text: String::new() },
+                None => Page { index: index as i32, url, base64: String::new(), text: String::new() },
+            }
+        })
+        .collect())
+}
+
+pub fn get_search_results(site: &'static Site, filters: Vec<Filter>, page: i32) -> Result<MangaPageResult> {
+    enter(site);
+    search(SearchParams::from_filters(filters, page))
+}
+
+pub fn initialize(site: &'static Site) {
+    register(site);
+    remote::refresh();
+    apply_rate_limit();
+}
+
+// Space requests out through Aidoku's own limiter, N per second
+fn apply_rate_limit() {
+    net::set_rate_limit(settings::rate_limit());
+    net::set_rate_limit_period(1);
+}
+
+// Settings changes take effect immediately: request headers and the base URL
+// are read per request, so only the rate limit and cached state need redoing.
+pub fn handle_notification(site: &'static Site, notification: String) {
+    register(site);
+    match notification.as_str() {
+        settings::SITE_CHANGED => {
+            apply_rate_limit();
+            reset_caches();
+            cache::clear();
+        }
+        settings::CACHE_CHANGED => {
+            http::forget_series();
+            cache::clear();
+        }
+        _ => {}
+    }
+}
+
+// Everything cached in memory that depends on the site/request config
+fn reset_caches() {
+    *AZ_CURSOR.lock().unwrap_or_else(|e| e.into_inner()) = AzCursor { page: 0, letter: 0, letter_page: 0, more: false };
+    CATALOG.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    *REST_UNAVAILABLE.lock().unwrap_or_else(|e| e.into_inner()) = false;
+    *LIVE_SEARCH_UNAVAILABLE.lock().unwrap_or_else(|e| e.into_inner()) = false;
+    http::forget_series();
+    http::reset_layout();
+    theme::forget();
+    genres::clear();
+    account::reset();
+}
+
+// The image CDN 403s hotlinked requests without the site as Referer
+pub fn modify_image_request(site: &'static Site, request: Request) {
+    register(site);
+    let mut request = request.header("Referer", &format!("{}/", settings::base_url()));
+    for (key, value) in http::request_headers() {
+        request = request.header(key, &value);
+    }
+}
+
+// Series ID for a chapter page: its breadcrumb or back link, else the
+// /manga/{slug}/chapter-N/ URL structure Madara uses
+fn parent_series(dom: &Node, chapter_id: &str) -> Option<String> {
+    let linked = select(dom, theme::selectors(dom).chapter_parent)
+        .into_iter()
+        .find_map(|a| attr(&a, "href"))
+        .map(|href| url::manga_id(&href))
+        .filter(|p| p != chapter_id && p != "/");
+    linked.or_else(|| {
+        let mut parts = chapter_id.trim_matches('/').split('/');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("manga"), Some(slug), Some(_)) => Some(url::manga_id(&format!("/manga/{slug}/"))),
+            _ => None,
+        }
+    })
+}
+
+// Hint pointing at a listing by the name this source shows it under
+fn open_listing(listing: &str) -> String {
+    i18n::local("Open \"{listing}\" in {site}'s listings").replace("{listing}", listing).replace("{site}", site().name)
+}
+
+pub fn handle_url(site: &'static Site, url: String) -> Result<DeepLink> {
+    enter(site);
+    let id = url::manga_id(&url);
+    // Deep links can only carry a series or chapter; point everything else
+    // at the listing or search that shows the same thing
+    let route = url::route(&url);
+    let hint = match &route {
+        url::Route::Series | url::Route::Chapter => None,
+        url::Route::Genre(slug) => Some(open_listing(&format!("Genre: {}", slug_title(slug)))),
+        url::Route::Listing(name) => Some(open_listing(i18n::local(name))),
+        url::Route::Search(query) => {
+            Some(i18n::local("Search {site} for \"{query}\"").replace("{site}", site.name).replace("{query}", query))
+        }
+    };
+    if let Some(hint) = hint {
+        return Err(error::SourceError::Unsupported(hint).into());
+    }
+    if matches!(route, url::Route::Chapter) {
+        let dom = get_dom(&url::id_url(&id))?;
+        let manga = match parent_series(&dom, &id) {
+            Some(series) => Some(get_manga_details(site, series)?),
+            None => None,
+        };
+        let (volume, number) = parse::chapter_numbers("", &url);
+        let chapter = Chapter {
+            url: url::id_url(&id),
+            id,
+            title: String::new(),
+            volume: volume.map(|v| v.to_string()).unwrap_or_default(),
+            chapter: number.map(|c| c.to_string()).unwrap_or_default(),
+            date_updated: None,
+            scanlator: site.scanlator.into(),
+            lang: site.lang.into(),
+        };
+        return Ok(DeepLink { manga, chapter: Some(chapter) });
+    }
+    Ok(DeepLink { manga: Some(get_manga_details(site, id)?), chapter: None })
+}
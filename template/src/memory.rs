@@ -0,0 +1,110 @@
+// In-memory LRU of response bodies for the session, in front of the
+// persistent cache. Paging back and forth through listings hits this instead
+// of deserializing the same defaults entry and copying it out again.
+// Parsed documents are host handles that don't outlive the call that made
+// them, so the body is what's kept; bounded by entry count and total size,
+// since everything here lives in the WASM module's own memory.
+// Parsed chapter lists for library refreshes are kept here too, under their
+// own bound.
+
+use aidoku::std::{String, Vec};
+use std::sync::Mutex;
+
+use crate::host::current_date;
+
+const MAX_ENTRIES: usize = 24;
+const MAX_BYTES: usize = 4 * 1024 * 1024;
+
+struct Entry {
+    url: String,
+    time: f64,
+    body: Vec<u8>,
+}
+
+// Least recently used first
+static ENTRIES: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+// The body stored for `url` when it's younger than `ttl` seconds
+pub fn get(url: &str, ttl: f64) -> Option<Vec<u8>> {
+    let mut entries = ENTRIES.lock().unwrap_or_else(|e| e.into_inner());
+    let i = entries.iter().position(|e| e.url == url)?;
+    if current_date() - entries[i].time >= ttl {
+        entries.remove(i);
+        return None;
+    }
+    let entry = entries.remove(i);
+    let body = entry.body.clone();
+    entries.push(entry);
+    Some(body)
+}
+
+// `time` is when the body was fetched, so a copy from the persistent cache
+// doesn't outlive the TTL it started under
+pub fn put(url: &str, time: f64, body: &[u8]) {
+    // One oversized page shouldn't push everything else out
+    if body.len() > MAX_BYTES / 4 { return; }
+    let mut entries = ENTRIES.lock().unwrap_or_else(|e| e.into_inner());
+    entries.retain(|e| e.url != url);
+    entries.push(Entry { url: url.into(), time, body: body.to_vec() });
+    let mut total: usize = entries.iter().map(|e| e.body.len()).sum();
+    while entries.len() > MAX_ENTRIES || total > MAX_BYTES {
+        total -= entries.remove(0).body.len();
+    }
+}
+
+pub fn clear() {
+    ENTRIES.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    LISTS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+// ---- Chapter lists ----
+// One parsed list per series, so a library refresh inside the TTL doesn't
+// refetch and reparse every series, and one past it only parses what's new.
+// Bounded by the chapters held in total, which covers a few hundred series
+// of ordinary length; past it the least recently stored lists go.
+const MAX_LIST_CHAPTERS: usize = 20_000;
+
+#[derive(Clone)]
+pub struct CachedChapter {
+    pub id: String,
+    pub title: String,
+    pub volume: String,
+    pub chapter: String,
+    pub date_updated: Option<f64>,
+    pub scanlator: String,
+    pub lang: String,
+}
+
+struct List {
+    series: String,
+    time: f64,
+    chapters: Vec<CachedChapter>,
+}
+
+// Least recently stored first
+static LISTS: Mutex<Vec<List>> = Mutex::new(Vec::new());
+
+// The stored list when it's younger than `ttl` seconds
+pub fn chapters(series: &str, ttl: f64) -> Option<Vec<CachedChapter>> {
+    if ttl <= 0.0 { return None; }
+    let lists = LISTS.lock().unwrap_or_else(|e| e.into_inner());
+    let list = lists.iter().find(|l| l.series == series)?;
+    (current_date() - list.time < ttl).then(|| list.chapters.clone())
+}
+
+// The stored list at any age, for refreshes that only parse what's new
+pub fn stored_chapters(series: &str) -> Option<Vec<CachedChapter>> {
+    let lists = LISTS.lock().unwrap_or_else(|e| e.into_inner());
+    lists.iter().find(|l| l.series == series).map(|l| l.chapters.clone())
+}
+
+pub fn put_chapters(series: &str, chapters: Vec<CachedChapter>) {
+    if chapters.len() > MAX_LIST_CHAPTERS / 4 { return; }
+    let mut lists = LISTS.lock().unwrap_or_else(|e| e.into_inner());
+    lists.retain(|l| l.series != series);
+    lists.push(List { series: series.into(), time: current_date(), chapters });
+    let mut total: usize = lists.iter().map(|l| l.chapters.len()).sum();
+    while total > MAX_LIST_CHAPTERS {
+        total -= lists.remove(0).chapters.len();
+    }
+}
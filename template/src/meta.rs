@@ -0,0 +1,143 @@
+// Structured-data fallbacks for series pages: JSON-LD and og:/twitter: meta.
+// Theme tweaks break CSS selectors far more often than these.
+
+use aidoku::std::{String, Vec};
+use serde_json::Value;
+
+use crate::{attr, host::Node, select};
+
+// JSON-LD types that describe a series
+const SERIES_TYPES: &[&str] = &["Book", "ComicSeries", "CreativeWorkSeries", "ComicStory"];
+
+pub struct SeriesMeta {
+    pub title: Option<String>,
+    pub cover: Option<String>,
+    pub description: Option<String>,
+}
+
+// First meta tag (by property or name) with non-empty content
+fn meta_content(dom: &Node, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|k| {
+        select(dom, &format!("meta[property='{k}'], meta[name='{k}']"))
+            .into_iter()
+            .find_map(|m| attr(&m, "content"))
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+    })
+}
+
+fn is_series(v: &Value) -> bool {
+    match &v["@type"] {
+        Value::String(t) => SERIES_TYPES.contains(&t.as_str()),
+        Value::Array(ts) => ts.iter().any(|t| t.as_str().is_some_and(|t| SERIES_TYPES.contains(&t))),
+        _ => false,
+    }
+}
+
+// Top-level objects, arrays and @graph entries are all searched
+fn find_series(v: &Value) -> Option<&Value> {
+    match v {
+        Value::Array(items) => items.iter().find_map(find_series),
+        Value::Object(_) if is_series(v) => Some(v),
+        Value::Object(_) => v.get("@graph").and_then(find_series),
+        _ => None,
+    }
+}
+
+fn json_ld_series(dom: &Node) -> Option<Value> {
+    // Script bodies are data, not text, to the parser
+    select(dom, "script[type='application/ld+json']")
+        .into_iter()
+        .filter_map(|s| serde_json::from_str::<Value>(&s.data().read()).ok())
+        .find_map(|v| find_series(&v).cloned())
+}
+
+fn string_field(v: &Value, key: &str) -> Option<String> {
+    let s = match &v[key] {
+        Value::String(s) => s.clone(),
+        // "image": { "url": ... } or a list of them
+        Value::Object(o) => o.get("url")?.as_str()?.to_string(),
+        Value::Array(a) => a.first().and_then(|i| i.as_str().map(String::from).or_else(|| string_field(i, "url")))?,
+        _ => return None,
+    };
+    Some(s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+// Where the page says it lives: rel=canonical, else og:url
+pub fn canonical_url(dom: &Node) -> Option<String> {
+    select(dom, "link[rel='canonical']")
+        .into_iter()
+        .find_map(|l| attr(&l, "href"))
+        .or_else(|| meta_content(dom, &["og:url"]))
+}
+
+// JSON-LD first, then OpenGraph, then Twitter cards
+pub fn series_meta(dom: &Node) -> SeriesMeta {
+    let ld = json_ld_series(dom);
+    let ld_field = |key: &str| ld.as_ref().and_then(|v| string_field(v, key));
+    SeriesMeta {
+        title: ld_field("name").or_else(|| meta_content(dom, &["og:title", "twitter:title"])),
+        cover: ld_field("image").or_else(|| meta_content(dom, &["og:image", "twitter:image"])),
+        description: ld_field("description").or_else(|| meta_content(dom, &["og:description", "twitter:description", "description"])),
+    }
+}
+
+// ---- Trackers ----
+// A tracker entry the series page links to. Kept as data, not just text, so
+// tracker integration can pick the IDs up later.
+pub struct Tracker {
+    pub name: &'static str,
+    pub id: String,
+}
+
+// (name, host, path before the ID)
+const TRACKERS: &[(&str, &str, &str)] = &[
+    ("MAL", "myanimelist.net", "/manga/"),
+    ("AniList", "anilist.co", "/manga/"),
+    ("MangaUpdates", "mangaupdates.com", "/series/"),
+];
+
+// "https://www.mangaupdates.com/series/abc12/title" -> "abc12"; also the
+// old "/series.html?id=123" form
+fn tracker_id(href: &str, host: &str, prefix: &str) -> Option<String> {
+    let rest = href.split_once("://").map_or(href, |(_, r)| r);
+    let (h, path) = rest.find('/').map_or((rest, ""), |i| rest.split_at(i));
+    if h != host && !h.ends_with(&format!(".{host}")) { return None; }
+    let id = match path.strip_prefix(prefix) {
+        Some(tail) => tail.split(['/', '?', '#']).next().unwrap_or_default(),
+        None => path.strip_prefix("/series.html?").and_then(|q| q.split('&').find_map(|kv| kv.strip_prefix("id=")))?,
+    };
+    Some(id.to_string()).filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+// One entry per tracker, the first link to it wins
+pub fn trackers(dom: &Node) -> Vec<Tracker> {
+    let links = select(dom, "a[href*='myanimelist.net'], a[href*='anilist.co'], a[href*='mangaupdates.com']");
+    let mut found: Vec<Tracker> = Vec::new();
+    for href in links.iter().filter_map(|a| attr(a, "href")) {
+        for (name, host, prefix) in TRACKERS {
+            if found.iter().any(|t| t.name == *name) { continue; }
+            if let Some(id) = tracker_id(&href, host, prefix) { found.push(Tracker { name, id }); }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracker_ids() {
+        assert_eq!(tracker_id("https://myanimelist.net/manga/121496/Solo_Leveling", "myanimelist.net", "/manga/").as_deref(), Some("121496"));
+        assert_eq!(tracker_id("https://anilist.co/manga/105398/", "anilist.co", "/manga/").as_deref(), Some("105398"));
+        assert_eq!(tracker_id("https://www.mangaupdates.com/series/abc12/title", "mangaupdates.com", "/series/").as_deref(), Some("abc12"));
+        assert_eq!(tracker_id("https://www.mangaupdates.com/series.html?id=123&x=1", "mangaupdates.com", "/series/").as_deref(), Some("123"));
+        // Other hosts, look-alike hosts and empty or odd ids are rejected
+        assert_eq!(tracker_id("https://anilist.co/manga/105398/", "myanimelist.net", "/manga/"), None);
+        assert_eq!(tracker_id("https://notanilist.co/manga/1/", "anilist.co", "/manga/"), None);
+        assert_eq!(tracker_id("https://anilist.co/manga/", "anilist.co", "/manga/"), None);
+        assert_eq!(tracker_id("https://anilist.co/user/someone/", "anilist.co", "/manga/"), None);
+        assert_eq!(tracker_id("https://anilist.co/manga/1%20x/", "anilist.co", "/manga/"), None);
+    }
+}
@@ -0,0 +1,1062 @@
+// Native stand-ins for the Aidoku imports re-exported by host.rs, so the
+// suite runs under `cargo test` on the build machine: an HTML tree with the
+// part of jsoup's selector syntax the template and selectors.json use, an
+// in-memory defaults store, and a clock that stands still unless a test
+// moves it. The shapes follow aidoku's (StringRef::read, ValueRef::array,
+// as_node, ...), so the modules above can't tell which one they got.
+//
+// Behaviour follows jsoup, which SwiftSoup ports: select() matches the node
+// it's called on as well as its descendants, text() is whitespace-normalised
+// with a space between blocks, and attribute and class matches ignore case.
+// A selector outside the supported syntax panics rather than matching
+// nothing, so a test can't pass on a selector the stand-in never ran.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+// ---- Values ----
+
+pub enum ValueRef {
+    Null,
+    Bool(bool),
+    String(String),
+    Node(Node),
+    Array(Vec<ValueRef>),
+}
+
+// Consuming, like aidoku's
+#[allow(clippy::wrong_self_convention)]
+impl ValueRef {
+    pub fn as_string(self) -> Result<StringRef, ()> {
+        match self {
+            ValueRef::String(s) => Ok(StringRef(ValueRef::String(s))),
+            _ => Err(()),
+        }
+    }
+
+    pub fn as_bool(self) -> Result<bool, ()> {
+        match self {
+            ValueRef::Bool(b) => Ok(b),
+            _ => Err(()),
+        }
+    }
+
+    pub fn as_node(self) -> Result<Node, ()> {
+        match self {
+            ValueRef::Node(n) => Ok(n),
+            _ => Err(()),
+        }
+    }
+
+    pub fn array(self) -> std::vec::IntoIter<ValueRef> {
+        match self {
+            ValueRef::Array(items) => items.into_iter(),
+            _ => Vec::new().into_iter(),
+        }
+    }
+}
+
+pub struct StringRef(pub ValueRef);
+
+impl StringRef {
+    pub fn read(&self) -> String {
+        match &self.0 {
+            ValueRef::String(s) => s.clone(),
+            _ => String::new(),
+        }
+    }
+}
+
+impl From<&str> for StringRef {
+    fn from(s: &str) -> Self {
+        StringRef(ValueRef::String(s.into()))
+    }
+}
+
+impl From<String> for StringRef {
+    fn from(s: String) -> Self {
+        StringRef(ValueRef::String(s))
+    }
+}
+
+// ---- Defaults ----
+
+enum Stored {
+    String(String),
+    Bool(bool),
+}
+
+static DEFAULTS: Mutex<Vec<(String, Stored)>> = Mutex::new(Vec::new());
+
+pub fn defaults_get(key: &str) -> Result<ValueRef, ()> {
+    let defaults = DEFAULTS.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(match defaults.iter().find(|(k, _)| k == key) {
+        Some((_, Stored::String(s))) => ValueRef::String(s.clone()),
+        Some((_, Stored::Bool(b))) => ValueRef::Bool(*b),
+        None => ValueRef::Null,
+    })
+}
+
+pub fn defaults_set(key: &str, value: ValueRef) {
+    let value = match value {
+        ValueRef::String(s) => Stored::String(s),
+        ValueRef::Bool(b) => Stored::Bool(b),
+        _ => panic!("native defaults hold strings and bools only ({key})"),
+    };
+    let mut defaults = DEFAULTS.lock().unwrap_or_else(|e| e.into_inner());
+    defaults.retain(|(k, _)| k != key);
+    defaults.push((key.into(), value));
+}
+
+// A switch in settings.json; text and select settings are strings
+pub fn set_switch(key: &str, on: bool) {
+    defaults_set(key, ValueRef::Bool(on));
+}
+
+// ---- Clock ----
+
+// 2024-06-01 00:00 UTC
+pub const START: f64 = 1_717_200_000.0;
+
+static NOW: Mutex<f64> = Mutex::new(START);
+
+pub fn current_date() -> f64 {
+    *NOW.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn advance(seconds: f64) {
+    *NOW.lock().unwrap_or_else(|e| e.into_inner()) += seconds;
+}
+
+// Empty defaults and the clock back at START
+pub fn reset() {
+    DEFAULTS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    *NOW.lock().unwrap_or_else(|e| e.into_inner()) = START;
+}
+
+// ---- Tree ----
+
+enum Kind {
+    Root,
+    Element { name: String, attrs: Vec<(String, String)> },
+    Text(String),
+    // Script and style bodies, which text() leaves out
+    Data(String),
+    Comment(String),
+}
+
+struct Item {
+    kind: Kind,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+struct Doc {
+    items: Vec<Item>,
+}
+
+#[derive(Clone)]
+pub struct Node {
+    doc: Rc<Doc>,
+    id: usize,
+}
+
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+const RAW_TEXT: &[&str] = &["script", "style"];
+const RCDATA: &[&str] = &["textarea", "title"];
+const BLOCK: &[&str] = &[
+    "html", "head", "body", "frameset", "script", "noscript", "style", "meta", "link", "title", "frame", "noframes",
+    "section", "nav", "aside", "hgroup", "header", "footer", "p", "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "pre",
+    "div", "blockquote", "hr", "address", "figure", "figcaption", "form", "fieldset", "ins", "del", "dl", "dt", "dd",
+    "li", "table", "caption", "thead", "tfoot", "tbody", "colgroup", "col", "tr", "th", "td", "video", "audio",
+    "canvas", "details", "menu", "plaintext", "template", "article", "main", "svg", "math", "center", "dir", "applet",
+    "marquee", "listing",
+];
+// Start tags that close an open <p>
+const CLOSES_P: &[&str] = &[
+    "address", "article", "aside", "blockquote", "details", "div", "dl", "fieldset", "figcaption", "figure", "footer",
+    "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hgroup", "hr", "main", "menu", "nav", "ol", "p", "pre",
+    "section", "table", "ul",
+];
+
+struct Builder {
+    items: Vec<Item>,
+    open: Vec<usize>,
+}
+
+impl Builder {
+    fn add(&mut self, kind: Kind) -> usize {
+        let id = self.items.len();
+        let parent = *self.open.last().unwrap();
+        self.items.push(Item { kind, parent: Some(parent), children: Vec::new() });
+        self.items[parent].children.push(id);
+        id
+    }
+
+    fn text(&mut self, text: String) {
+        if text.is_empty() { return; }
+        // Adjacent text, e.g. around a stray '<', stays one node
+        let parent = *self.open.last().unwrap();
+        if let Some(&last) = self.items[parent].children.last() {
+            if let Kind::Text(t) = &mut self.items[last].kind {
+                t.push_str(&text);
+                return;
+            }
+        }
+        self.add(Kind::Text(text));
+    }
+
+    fn open_name(&self, i: usize) -> &str {
+        match &self.items[self.open[i]].kind {
+            Kind::Element { name, .. } => name,
+            _ => "",
+        }
+    }
+
+    // Pops up to and including the innermost open `name`, if any is open
+    // below the nearest of `boundary`
+    fn close(&mut self, name: &str, boundary: &[&str]) {
+        for i in (1..self.open.len()).rev() {
+            let open = self.open_name(i);
+            if open == name {
+                self.open.truncate(i);
+                return;
+            }
+            if boundary.contains(&open) { return; }
+        }
+    }
+
+    fn start(&mut self, name: String, attrs: Vec<(String, String)>) -> usize {
+        if CLOSES_P.contains(&name.as_str()) { self.close("p", &["button", "table", "td", "th"]); }
+        match name.as_str() {
+            "li" => self.close("li", &["ul", "ol"]),
+            "dt" | "dd" => {
+                self.close("dt", &["dl"]);
+                self.close("dd", &["dl"]);
+            }
+            "option" => self.close("option", &["select", "datalist"]),
+            "tr" => {
+                self.close("td", &["table", "tr"]);
+                self.close("th", &["table", "tr"]);
+                self.close("tr", &["table", "tbody", "thead", "tfoot"]);
+            }
+            "td" | "th" => {
+                self.close("td", &["table", "tr"]);
+                self.close("th", &["table", "tr"]);
+            }
+            _ => {}
+        }
+        let void = VOID.contains(&name.as_str());
+        let id = self.add(Kind::Element { name, attrs });
+        if !void { self.open.push(id); }
+        id
+    }
+}
+
+fn is_space(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0c')
+}
+
+fn parse(html: &str) -> Doc {
+    let mut b = Builder { items: vec![Item { kind: Kind::Root, parent: None, children: Vec::new() }], open: vec![0] };
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            b.text(decode(rest));
+            break;
+        };
+        b.text(decode(&rest[..lt]));
+        rest = &rest[lt..];
+        if let Some(body) = rest.strip_prefix("<!--") {
+            let end = body.find("-->").unwrap_or(body.len());
+            b.add(Kind::Comment(body[..end].into()));
+            rest = body.get(end + 3..).unwrap_or("");
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(after) = rest.strip_prefix("</") {
+            if let Some(after) = after.strip_prefix('>') {
+                rest = after;
+                continue;
+            }
+            if !after.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                b.text("</".into());
+                rest = after;
+                continue;
+            }
+            let end = after.find('>').unwrap_or(after.len());
+            let name = after[..end].split(|c: char| is_space(c) || c == '/').next().unwrap_or("").to_ascii_lowercase();
+            b.close(&name, &[]);
+            rest = after.get(end + 1..).unwrap_or("");
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let (name, attrs, self_closing, after) = start_tag(&rest[1..]);
+            rest = after;
+            let raw = RAW_TEXT.contains(&name.as_str());
+            let rcdata = RCDATA.contains(&name.as_str());
+            let id = b.start(name.clone(), attrs);
+            // Self-closing is honoured on any tag, as jsoup does
+            if self_closing {
+                b.open.retain(|&o| o != id);
+            } else if raw || rcdata {
+                let close = format!("</{name}");
+                let end = find_ignore_case(rest, &close).unwrap_or(rest.len());
+                let body = &rest[..end];
+                if !body.is_empty() {
+                    b.add(if raw { Kind::Data(body.into()) } else { Kind::Text(decode(body)) });
+                }
+                b.open.retain(|&o| o != id);
+                rest = &rest[end..];
+                rest = rest.find('>').map_or("", |e| &rest[e + 1..]);
+            }
+        } else {
+            // A '<' that doesn't open a tag is text
+            b.text("<".into());
+            rest = &rest[1..];
+        }
+    }
+    Doc { items: b.items }
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    let (h, n) = (haystack.as_bytes(), needle.as_bytes());
+    (0..h.len().saturating_sub(n.len() - 1)).find(|&i| h[i..i + n.len()].eq_ignore_ascii_case(n))
+}
+
+// Name, attributes and self-closing flag of the tag `s` starts with (just
+// past its '<'), and the input after its '>'
+fn start_tag(s: &str) -> (String, Vec<(String, String)>, bool, &str) {
+    let name_end = s.find(|c: char| is_space(c) || c == '/' || c == '>').unwrap_or(s.len());
+    let name = s[..name_end].to_ascii_lowercase();
+    let mut rest = &s[name_end..];
+    let mut attrs: Vec<(String, String)> = Vec::new();
+    let mut self_closing = false;
+    loop {
+        rest = rest.trim_start_matches(is_space);
+        if rest.is_empty() { break; }
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('/') {
+            self_closing = after.starts_with('>');
+            rest = after;
+            continue;
+        }
+        let key_end = rest.find(|c: char| is_space(c) || c == '=' || c == '>' || c == '/').unwrap_or(rest.len()).max(1);
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start_matches(is_space);
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            rest = after.trim_start_matches(is_space);
+            if let Some(q) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') {
+                let body = &rest[1..];
+                let end = body.find(q).unwrap_or(body.len());
+                value = decode(&body[..end]);
+                rest = body.get(end + 1..).unwrap_or("");
+            } else {
+                let end = rest.find(|c: char| is_space(c) || c == '>').unwrap_or(rest.len());
+                value = decode(&rest[..end]);
+                rest = &rest[end..];
+            }
+        }
+        if !attrs.iter().any(|(k, _)| *k == key) { attrs.push((key, value)); }
+    }
+    (name, attrs, self_closing, rest)
+}
+
+const ENTITIES: &[(&str, char)] = &[
+    ("amp", '&'), ("lt", '<'), ("gt", '>'), ("quot", '"'), ("apos", '\''), ("nbsp", '\u{a0}'), ("hellip", '\u{2026}'),
+    ("mdash", '\u{2014}'), ("ndash", '\u{2013}'), ("lsquo", '\u{2018}'), ("rsquo", '\u{2019}'), ("ldquo", '\u{201c}'),
+    ("rdquo", '\u{201d}'), ("laquo", '\u{ab}'), ("raquo", '\u{bb}'), ("middot", '\u{b7}'), ("bull", '\u{2022}'),
+    ("copy", '\u{a9}'), ("reg", '\u{ae}'), ("trade", '\u{2122}'), ("times", '\u{d7}'), ("star", '\u{2606}'),
+    ("eacute", '\u{e9}'), ("egrave", '\u{e8}'), ("aacute", '\u{e1}'), ("iacute", '\u{ed}'), ("oacute", '\u{f3}'),
+    ("uacute", '\u{fa}'), ("ntilde", '\u{f1}'), ("ccedil", '\u{e7}'), ("uuml", '\u{fc}'), ("ouml", '\u{f6}'),
+];
+
+// Character references in text and attribute values; unknown ones stay as
+// written
+fn decode(s: &str) -> String {
+    if !s.contains('&') { return s.into(); }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let end = rest[1..].find(|c: char| !c.is_ascii_alphanumeric() && c != '#').map_or(rest.len(), |e| e + 1);
+        let name = &rest[1..end];
+        let c = if let Some(num) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+            u32::from_str_radix(num, 16).ok().and_then(char::from_u32)
+        } else if let Some(num) = name.strip_prefix('#') {
+            num.parse().ok().and_then(char::from_u32)
+        } else {
+            ENTITIES.iter().find(|(n, _)| *n == name).map(|(_, c)| *c)
+        };
+        match c {
+            Some(c) => {
+                out.push(if c == '\0' { '\u{fffd}' } else { c });
+                rest = &rest[end..];
+                if let Some(after) = rest.strip_prefix(';') { rest = after; }
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// ---- Serialising ----
+
+fn escape(s: &str, attr: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '\u{a0}' => out.push_str("&nbsp;"),
+            '<' if !attr => out.push_str("&lt;"),
+            '>' if !attr => out.push_str("&gt;"),
+            '"' if attr => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl Doc {
+    fn name(&self, id: usize) -> Option<&str> {
+        match &self.items[id].kind {
+            Kind::Element { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    fn attr(&self, id: usize, key: &str) -> Option<&str> {
+        match &self.items[id].kind {
+            Kind::Element { attrs, .. } => attrs.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v.as_str()),
+            _ => None,
+        }
+    }
+
+    fn is_block(&self, id: usize) -> bool {
+        self.name(id).is_some_and(|n| BLOCK.contains(&n))
+    }
+
+    fn outer_html(&self, id: usize, out: &mut String) {
+        match &self.items[id].kind {
+            Kind::Root => self.inner_html(id, out),
+            Kind::Text(t) => out.push_str(&escape(t, false)),
+            Kind::Data(d) => out.push_str(d),
+            Kind::Comment(c) => {
+                out.push_str("<!--");
+                out.push_str(c);
+                out.push_str("-->");
+            }
+            Kind::Element { name, attrs } => {
+                out.push('<');
+                out.push_str(name);
+                for (k, v) in attrs {
+                    out.push(' ');
+                    out.push_str(k);
+                    out.push_str("=\"");
+                    out.push_str(&escape(v, true));
+                    out.push('"');
+                }
+                out.push('>');
+                if VOID.contains(&name.as_str()) { return; }
+                self.inner_html(id, out);
+                out.push_str("</");
+                out.push_str(name);
+                out.push('>');
+            }
+        }
+    }
+
+    fn inner_html(&self, id: usize, out: &mut String) {
+        for &child in &self.items[id].children {
+            self.outer_html(child, out);
+        }
+    }
+
+    // jsoup's Element.text()
+    fn text(&self, id: usize, own: bool) -> String {
+        let mut out = String::new();
+        self.text_into(id, own, &mut out);
+        out.trim_matches(|c: char| c <= ' ').into()
+    }
+
+    fn text_into(&self, id: usize, own: bool, out: &mut String) {
+        let children = &self.items[id].children;
+        for (i, &child) in children.iter().enumerate() {
+            match &self.items[child].kind {
+                Kind::Text(t) => {
+                    if self.preserves_space(id) {
+                        out.push_str(t);
+                    } else {
+                        append_normalised(out, t);
+                    }
+                }
+                Kind::Element { name, .. } if !own => {
+                    if !out.is_empty() && (self.is_block(child) || name == "br") && !out.ends_with(' ') { out.push(' '); }
+                    self.text_into(child, false, out);
+                    let next = children.get(i + 1).copied();
+                    let inline_next = next.is_some_and(|n| !self.is_block(n) && !matches!(self.items[n].kind, Kind::Comment(_)));
+                    if self.is_block(child) && inline_next && !out.ends_with(' ') { out.push(' '); }
+                }
+                Kind::Element { name, .. } if name == "br" && !out.ends_with(' ') => out.push(' '),
+                _ => {}
+            }
+        }
+    }
+
+    fn preserves_space(&self, mut id: usize) -> bool {
+        for _ in 0..6 {
+            if self.name(id).is_some_and(|n| matches!(n, "pre" | "textarea" | "title" | "plaintext")) { return true; }
+            match self.items[id].parent {
+                Some(p) => id = p,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    // Script and style bodies and comments under `id`, as jsoup's data()
+    fn data(&self, id: usize, out: &mut String) {
+        for &child in &self.items[id].children {
+            match &self.items[child].kind {
+                Kind::Data(d) | Kind::Comment(d) => out.push_str(d),
+                Kind::Element { .. } => self.data(child, out),
+                _ => {}
+            }
+        }
+    }
+
+    fn elements(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.items[id].children.iter().copied().filter(|&c| self.name(c).is_some())
+    }
+
+    fn parent_element(&self, id: usize) -> Option<usize> {
+        self.items[id].parent.filter(|&p| self.name(p).is_some())
+    }
+
+    // Element siblings of `id`, itself included, in order
+    fn siblings(&self, id: usize) -> Vec<usize> {
+        match self.items[id].parent {
+            Some(p) => self.elements(p).collect(),
+            None => vec![id],
+        }
+    }
+
+    // `id` and every element under it, in document order
+    fn descendants(&self, id: usize, out: &mut Vec<usize>) {
+        if self.name(id).is_some() { out.push(id); }
+        for &child in &self.items[id].children {
+            self.descendants(child, out);
+        }
+    }
+}
+
+fn append_normalised(out: &mut String, text: &str) {
+    let mut last_space = out.is_empty() || out.ends_with(' ');
+    let strip_leading = last_space;
+    let mut reached = false;
+    for c in text.chars() {
+        if is_space(c) || c == '\u{a0}' {
+            if (strip_leading && !reached) || last_space { continue; }
+            out.push(' ');
+            last_space = true;
+        } else if c != '\u{200b}' && c != '\u{ad}' {
+            out.push(c);
+            last_space = false;
+            reached = true;
+        }
+    }
+}
+
+// ---- Selectors ----
+
+#[derive(Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+    Adjacent,
+    Sibling,
+}
+
+enum Test {
+    Tag(String),
+    Id(String),
+    Class(String),
+    Attr(String),
+    AttrPrefix(String),
+    AttrOp(String, char, String),
+    NthChild { a: i32, b: i32, last: bool, of_type: bool },
+    Contains(String),
+    ContainsOwn(String),
+    Has(Group),
+    Not(Group),
+    Index(std::cmp::Ordering, usize),
+    Empty,
+}
+
+struct Complex {
+    // Set when the selector opens with a combinator ("> img"), which
+    // relates its first compound to the node select() was called on
+    lead: Option<Combinator>,
+    // Compounds left to right, each with the combinator before it
+    parts: Vec<(Combinator, Vec<Test>)>,
+}
+
+struct Group(Vec<Complex>);
+
+struct Parser<'a> {
+    query: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn fail(&self) -> ! {
+        panic!("native selector: can't parse {:?} at {:?}", self.query, self.rest)
+    }
+
+    fn skip_space(&mut self) -> bool {
+        let before = self.rest.len();
+        self.rest = self.rest.trim_start_matches(is_space);
+        self.rest.len() != before
+    }
+
+    fn eat(&mut self, prefix: char) -> bool {
+        match self.rest.strip_prefix(prefix) {
+            Some(after) => {
+                self.rest = after;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn ident(&mut self) -> String {
+        let end = self.rest.find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_')).unwrap_or(self.rest.len());
+        if end == 0 { self.fail(); }
+        let ident = self.rest[..end].into();
+        self.rest = &self.rest[end..];
+        ident
+    }
+
+    // A pseudo-class argument up to the matching ')', quotes stripped
+    fn argument(&mut self) -> String {
+        let mut depth = 0;
+        let mut quote = None;
+        for (i, c) in self.rest.char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '(') => depth += 1,
+                (None, ')') if depth == 0 => {
+                    let arg = self.rest[..i].trim();
+                    self.rest = &self.rest[i + 1..];
+                    let unquoted = arg.strip_prefix(['"', '\'']).and_then(|a| a.strip_suffix(['"', '\'']));
+                    return unquoted.unwrap_or(arg).into();
+                }
+                (None, ')') => depth -= 1,
+                _ => {}
+            }
+        }
+        self.fail()
+    }
+
+    fn group(&mut self, nested: bool) -> Group {
+        let mut complexes = Vec::new();
+        loop {
+            complexes.push(self.complex());
+            self.skip_space();
+            if self.eat(',') { continue; }
+            if self.rest.is_empty() || nested && self.rest.starts_with(')') { break; }
+            self.fail();
+        }
+        Group(complexes)
+    }
+
+    fn combinator(&mut self) -> Option<Combinator> {
+        let spaced = self.skip_space();
+        let c = match self.rest.chars().next() {
+            Some('>') => Combinator::Child,
+            Some('+') => Combinator::Adjacent,
+            Some('~') => Combinator::Sibling,
+            Some(',' | ')') | None => return None,
+            _ if spaced => return Some(Combinator::Descendant),
+            _ => return None,
+        };
+        self.rest = &self.rest[1..];
+        self.skip_space();
+        Some(c)
+    }
+
+    fn complex(&mut self) -> Complex {
+        self.skip_space();
+        let lead = self.combinator();
+        let mut parts = vec![(Combinator::Descendant, self.compound())];
+        while let Some(c) = self.combinator() {
+            parts.push((c, self.compound()));
+        }
+        Complex { lead, parts }
+    }
+
+    fn compound(&mut self) -> Vec<Test> {
+        let mut tests = Vec::new();
+        let any = self.eat('*');
+        if !any && self.rest.starts_with(|c: char| c.is_alphabetic()) {
+            tests.push(Test::Tag(self.ident().to_ascii_lowercase()));
+        }
+        loop {
+            if self.eat('#') {
+                tests.push(Test::Id(self.ident()));
+            } else if self.eat('.') {
+                tests.push(Test::Class(self.ident().to_lowercase()));
+            } else if self.eat('[') {
+                tests.push(self.attribute());
+            } else if self.eat(':') {
+                tests.push(self.pseudo());
+            } else {
+                break;
+            }
+        }
+        if tests.is_empty() && !any { self.fail(); }
+        tests
+    }
+
+    fn attribute(&mut self) -> Test {
+        let end = self.rest.find(']').unwrap_or_else(|| self.fail());
+        let inner = &self.rest[..end];
+        self.rest = &self.rest[end + 1..];
+        let Some(op_at) = inner.find(['=', '~', '^', '$', '*', '!']).filter(|&i| i > 0) else {
+            let key = inner.trim().to_ascii_lowercase();
+            return match key.strip_prefix('^') {
+                Some(prefix) => Test::AttrPrefix(prefix.into()),
+                None => Test::Attr(key),
+            };
+        };
+        let key = inner[..op_at].trim().to_ascii_lowercase();
+        let op_text = &inner[op_at..];
+        let (op, value) = match op_text.strip_prefix('=') {
+            Some(v) => ('=', v),
+            None => match op_text[1..].strip_prefix('=') {
+                Some(v) => (op_text.as_bytes()[0] as char, v),
+                None => self.fail(),
+            },
+        };
+        if op == '~' || op == '!' { self.fail(); }
+        let value = value.trim();
+        let value = value.strip_prefix(['"', '\'']).and_then(|v| v.strip_suffix(['"', '\''])).unwrap_or(value);
+        Test::AttrOp(key, op, value.trim().to_lowercase())
+    }
+
+    fn pseudo(&mut self) -> Test {
+        let name = self.ident().to_ascii_lowercase();
+        let nth = |last, of_type| Test::NthChild { a: 0, b: 1, last, of_type };
+        match name.as_str() {
+            "first-child" => nth(false, false),
+            "last-child" => nth(true, false),
+            "first-of-type" => nth(false, true),
+            "last-of-type" => nth(true, true),
+            "empty" => Test::Empty,
+            _ => {
+                if !self.eat('(') { self.fail(); }
+                match name.as_str() {
+                    "nth-child" | "nth-last-child" | "nth-of-type" | "nth-last-of-type" => {
+                        let (a, b) = self.nth();
+                        Test::NthChild { a, b, last: name.contains("last"), of_type: name.ends_with("of-type") }
+                    }
+                    "contains" => Test::Contains(self.argument().to_lowercase()),
+                    "containsown" => Test::ContainsOwn(self.argument().to_lowercase()),
+                    "has" | "not" => {
+                        let inner = self.group(true);
+                        if !self.eat(')') { self.fail(); }
+                        if name == "has" { Test::Has(inner) } else { Test::Not(inner) }
+                    }
+                    "eq" | "lt" | "gt" => {
+                        let n = self.argument().parse().unwrap_or_else(|_| self.fail());
+                        let ord = match name.as_str() {
+                            "eq" => std::cmp::Ordering::Equal,
+                            "lt" => std::cmp::Ordering::Less,
+                            _ => std::cmp::Ordering::Greater,
+                        };
+                        Test::Index(ord, n)
+                    }
+                    _ => self.fail(),
+                }
+            }
+        }
+    }
+
+    // "an+b", "odd", "even" or a plain index
+    fn nth(&mut self) -> (i32, i32) {
+        let arg: String = self.argument().chars().filter(|c| !is_space(*c)).collect::<String>().to_lowercase();
+        match arg.as_str() {
+            "odd" => return (2, 1),
+            "even" => return (2, 0),
+            _ => {}
+        }
+        let Some(n_at) = arg.find('n') else {
+            return (0, arg.parse().unwrap_or_else(|_| self.fail()));
+        };
+        let a = match &arg[..n_at] {
+            "" | "+" => 1,
+            "-" => -1,
+            a => a.parse().unwrap_or_else(|_| self.fail()),
+        };
+        let b = &arg[n_at + 1..];
+        let b = if b.is_empty() { 0 } else { b.trim_start_matches('+').parse().unwrap_or_else(|_| self.fail()) };
+        (a, b)
+    }
+}
+
+fn parse_selector(query: &str) -> Group {
+    let mut parser = Parser { query, rest: query };
+    parser.group(false)
+}
+
+impl Doc {
+    fn matches_group(&self, group: &Group, root: usize, id: usize) -> bool {
+        group.0.iter().any(|c| self.matches_complex(c, root, id, c.parts.len() - 1))
+    }
+
+    // Whether compounds ..=`last` of `c` match with `id` as the last one
+    fn matches_complex(&self, c: &Complex, root: usize, id: usize, last: usize) -> bool {
+        let (combinator, tests) = &c.parts[last];
+        if !tests.iter().all(|t| self.matches_test(t, root, id)) { return false; }
+        if last == 0 {
+            return match c.lead {
+                None => true,
+                Some(lead) => self.related(lead, root, id, &mut |a| a == root),
+            };
+        }
+        self.related(*combinator, root, id, &mut |a| self.matches_complex(c, root, a, last - 1))
+    }
+
+    // Whether some node standing in `combinator`'s relation to `id` passes
+    // `f`; ancestors are looked for up to `root`, as jsoup does
+    fn related(&self, combinator: Combinator, root: usize, id: usize, f: &mut dyn FnMut(usize) -> bool) -> bool {
+        if id == root { return false; }
+        match combinator {
+            Combinator::Descendant => {
+                let mut at = self.items[id].parent;
+                while let Some(p) = at {
+                    if f(p) { return true; }
+                    if p == root { break; }
+                    at = self.items[p].parent;
+                }
+                false
+            }
+            Combinator::Child => self.items[id].parent.is_some_and(f),
+            Combinator::Adjacent | Combinator::Sibling => {
+                let siblings = self.siblings(id);
+                let i = siblings.iter().position(|&s| s == id).unwrap();
+                if combinator == Combinator::Adjacent {
+                    i > 0 && f(siblings[i - 1])
+                } else {
+                    siblings[..i].iter().any(|&s| f(s))
+                }
+            }
+        }
+    }
+
+    fn matches_test(&self, test: &Test, root: usize, id: usize) -> bool {
+        let Some(name) = self.name(id) else { return false };
+        match test {
+            Test::Tag(tag) => name == tag,
+            Test::Id(want) => self.attr(id, "id") == Some(want.as_str()),
+            Test::Class(want) => self.attr(id, "class").is_some_and(|c| c.split(is_space).any(|c| c.to_lowercase() == *want)),
+            Test::Attr(key) => self.attr(id, key).is_some(),
+            Test::AttrPrefix(prefix) => match &self.items[id].kind {
+                Kind::Element { attrs, .. } => attrs.iter().any(|(k, _)| k.starts_with(prefix.as_str())),
+                _ => false,
+            },
+            Test::AttrOp(key, op, want) => self.attr(id, key).is_some_and(|v| {
+                let v = v.trim().to_lowercase();
+                match op {
+                    '=' => v == *want,
+                    '^' => v.starts_with(want.as_str()),
+                    '$' => v.ends_with(want.as_str()),
+                    _ => v.contains(want.as_str()),
+                }
+            }),
+            Test::NthChild { a, b, last, of_type } => {
+                let mut siblings = self.siblings(id);
+                if *of_type { siblings.retain(|&s| self.name(s) == Some(name)); }
+                if *last { siblings.reverse(); }
+                let pos = siblings.iter().position(|&s| s == id).unwrap() as i32 + 1;
+                match *a {
+                    0 => pos == *b,
+                    a => (pos - b) % a == 0 && (pos - b) / a >= 0,
+                }
+            }
+            Test::Contains(want) => self.text(id, false).to_lowercase().contains(want.as_str()),
+            Test::ContainsOwn(want) => self.text(id, true).to_lowercase().contains(want.as_str()),
+            Test::Has(inner) => {
+                let mut under = Vec::new();
+                self.descendants(id, &mut under);
+                under.into_iter().any(|d| d != id && self.matches_group(inner, id, d))
+            }
+            Test::Not(inner) => !self.matches_group(inner, root, id),
+            Test::Index(ord, n) => self.siblings(id).iter().position(|&s| s == id).unwrap().cmp(n) == *ord,
+            Test::Empty => self.items[id].children.iter().all(|&c| match &self.items[c].kind {
+                Kind::Text(t) => t.is_empty(),
+                Kind::Comment(_) => true,
+                _ => false,
+            }),
+        }
+    }
+}
+
+// ---- Node ----
+
+// select() calls made on this thread, each one a host round trip in the
+// app; the benchmarks count them per chapter row
+thread_local! {
+    static QUERIES: Cell<usize> = const { Cell::new(0) };
+}
+
+pub fn queries() -> usize {
+    QUERIES.with(Cell::get)
+}
+
+impl Node {
+    pub fn new(data: &[u8]) -> Result<Node, ()> {
+        Ok(Node { doc: Rc::new(parse(&String::from_utf8_lossy(data))), id: 0 })
+    }
+
+    // Matches of `query` in document order, this node included
+    pub fn select(&self, query: &str) -> ValueRef {
+        QUERIES.with(|q| q.set(q.get() + 1));
+        let group = parse_selector(query);
+        let mut candidates = Vec::new();
+        self.doc.descendants(self.id, &mut candidates);
+        let matches = candidates
+            .into_iter()
+            .filter(|&id| self.doc.matches_group(&group, self.id, id))
+            .map(|id| ValueRef::Node(Node { doc: self.doc.clone(), id }))
+            .collect();
+        ValueRef::Array(matches)
+    }
+
+    pub fn text(&self) -> StringRef {
+        StringRef::from(self.doc.text(self.id, false))
+    }
+
+    pub fn own_text(&self) -> StringRef {
+        StringRef::from(self.doc.text(self.id, true))
+    }
+
+    pub fn attr(&self, key: &str) -> StringRef {
+        StringRef::from(self.doc.attr(self.id, key).unwrap_or(""))
+    }
+
+    pub fn html(&self) -> StringRef {
+        let mut out = String::new();
+        self.doc.inner_html(self.id, &mut out);
+        StringRef::from(out)
+    }
+
+    pub fn outer_html(&self) -> StringRef {
+        let mut out = String::new();
+        self.doc.outer_html(self.id, &mut out);
+        StringRef::from(out)
+    }
+
+    pub fn data(&self) -> StringRef {
+        let mut out = String::new();
+        self.doc.data(self.id, &mut out);
+        StringRef::from(out)
+    }
+
+    pub fn parent(&self) -> Option<Node> {
+        self.doc.parent_element(self.id).map(|id| Node { doc: self.doc.clone(), id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(html: &str) -> Node {
+        Node::new(html.as_bytes()).unwrap()
+    }
+
+    fn texts(node: &Node, query: &str) -> Vec<String> {
+        node.select(query).array().filter_map(|v| v.as_node().ok()).map(|n| n.text().read()).collect()
+    }
+
+    #[test]
+    fn tree_and_text() {
+        let d = doc("<div><p>One &amp; <b>two</b><p>three<br>four</div> <li>a<li>b");
+        assert_eq!(texts(&d, "p"), ["One & two", "three four"]);
+        assert_eq!(texts(&d, "li"), ["a", "b"]);
+        assert_eq!(d.text().read(), "One & two three four a b");
+        let d = doc("<ul>\n  <li>\n    <a href=x>Chapter 1</a>\n    <span>Jan 1</span>\n  </li>\n</ul>");
+        assert_eq!(texts(&d, "li"), ["Chapter 1 Jan 1"]);
+        assert_eq!(doc("<div>a</div>b<div>c</div>").text().read(), "a b c");
+    }
+
+    #[test]
+    fn raw_text_and_attributes() {
+        let d = doc("<script>if (a < b) { x = '</div>'; }</script><img data-src='/a.jpg?x=1&amp;y=2' SRC=b.png alt>");
+        let script = d.select("script").array().next().unwrap().as_node().unwrap();
+        assert_eq!(script.data().read(), "if (a < b) { x = '</div>'; }");
+        assert_eq!(script.text().read(), "");
+        let img = d.select("img").array().next().unwrap().as_node().unwrap();
+        assert_eq!(img.attr("data-src").read(), "/a.jpg?x=1&y=2");
+        assert_eq!(img.attr("src").read(), "b.png");
+        assert_eq!(img.attr("missing").read(), "");
+        assert_eq!(doc("<p class=\"a\">x &lt; y</p>").html().read(), "<p class=\"a\">x &lt; y</p>");
+    }
+
+    #[test]
+    fn selectors() {
+        let d = doc(
+            "<div id=main class='Box wide'><ul><li class=a><a href='/manga/x/'>X</a></li><li class=b><a href='/genres/y'>Y</a></li></ul>\
+             <div class=imptdt>Status <i>Ongoing</i></div><div class=imptdt>Type <i>Manhwa</i></div></div>",
+        );
+        assert_eq!(texts(&d, "#main li a"), ["X", "Y"]);
+        assert_eq!(texts(&d, "div.box.wide > ul > li.b"), ["Y"]);
+        assert_eq!(texts(&d, "a[href*='/manga/'], a[href*='/genres/']"), ["X", "Y"]);
+        assert_eq!(texts(&d, "li:nth-child(2) a"), ["Y"]);
+        assert_eq!(texts(&d, "li:first-child a, li:last-child a"), ["X", "Y"]);
+        assert_eq!(texts(&d, ".imptdt:contains(status) i"), ["Ongoing"]);
+        assert_eq!(texts(&d, "li:has(a[href^='/genres'])"), ["Y"]);
+        assert_eq!(texts(&d, "li:not(.a)"), ["Y"]);
+        assert_eq!(texts(&d, "li + li"), ["Y"]);
+        assert!(texts(&d, "span, .missing").is_empty());
+        // The node select() is called on can match, and a leading
+        // combinator is relative to it
+        let li = d.select("li.a").array().next().unwrap().as_node().unwrap();
+        assert_eq!(texts(&li, "li"), ["X"]);
+        assert_eq!(texts(&li, "> a, > * > a"), ["X"]);
+        assert!(texts(&li, "ul a").is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "native selector")]
+    fn unsupported_selectors_panic() {
+        doc("<p>").select("p:matches(x)");
+    }
+
+    #[test]
+    fn defaults_and_clock() {
+        let _held = crate::client::mock();
+        reset();
+        assert!(defaults_get("k").and_then(|v| v.as_string()).is_err());
+        defaults_set("k", StringRef::from("v").0);
+        set_switch("s", true);
+        assert_eq!(defaults_get("k").and_then(|v| v.as_string()).map(|v| v.read()), Ok(String::from("v")));
+        assert_eq!(defaults_get("s").and_then(|v| v.as_bool()), Ok(true));
+        advance(60.0);
+        assert_eq!(current_date(), START + 60.0);
+        reset();
+        assert_eq!(current_date(), START);
+    }
+}
@@ -0,0 +1,632 @@
+// Text parsing helpers for chapter and series metadata.
+
+use aidoku::std::{String, Vec};
+
+use crate::{host::current_date, settings, site};
+
+// Number right after one of `keys`, e.g. "Vol. 2 Ch. 15" with ["ch"] -> 15
+fn number_after(s: &str, keys: &[&str]) -> Option<f32> {
+    for key in keys {
+        let mut rest = s;
+        while let Some(i) = rest.find(key) {
+            // keyword has to start a word so "epoch 3" doesn't read as "ch 3"
+            let boundary = !rest[..i].chars().last().is_some_and(char::is_alphanumeric);
+            let after = &rest[i + key.len()..];
+            let digits = after.trim_start_matches([' ', '.', ':', '-', '_', '#']);
+            let num: String = digits.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+            let num = num.trim_end_matches('.');
+            if boundary && !num.is_empty() {
+                if let Ok(n) = num.parse() { return Some(n); }
+            }
+            rest = after;
+        }
+    }
+    None
+}
+
+// Slug words a chapter number follows, longest first so "chapter" isn't read as "ch"
+const URL_KEYS: &[&str] = &["chapter", "chap", "ch", "episode", "ep"];
+
+// "/series-chapter-10-5/" -> 10.5, also "ch-10", "episode10"; a trailing
+// numeric part is the decimal
+fn chapter_from_url(url: &str) -> Option<f32> {
+    let slug = url.split(['?', '#']).next()?.trim_end_matches('/').rsplit('/').next()?.to_lowercase();
+    let parts: Vec<&str> = slug.split(['-', '_']).collect();
+    let numeric = |p: &str| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit());
+    // The last keyword part, with its number after it or glued on ("chapter10")
+    let (i, glued) = parts.iter().enumerate().rev().find_map(|(i, p)| {
+        let rest = URL_KEYS.iter().find_map(|k| p.strip_prefix(k))?;
+        (rest.is_empty() || numeric(rest)).then_some((i, rest))
+    })?;
+    let (whole, next) = if glued.is_empty() { (*parts.get(i + 1)?, i + 2) } else { (glued, i + 1) };
+    if !numeric(whole) { return None; }
+    match parts.get(next) {
+        Some(f) if next + 1 == parts.len() && numeric(f) => format!("{whole}.{f}").parse().ok(),
+        _ => whole.parse().ok(),
+    }
+}
+
+// "S2 Chapter 50" -> 2; only a whole "s<digits>" word counts
+fn short_season(s: &str) -> Option<f32> {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter_map(|w| w.strip_prefix('s'))
+        .find(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|n| n.parse().ok())
+}
+
+// (volume, chapter) from the link text, falling back to the URL slug.
+// Manhwa seasons count as volumes, so Aidoku groups chapters by them.
+pub fn chapter_numbers(title: &str, url: &str) -> (Option<f32>, Option<f32>) {
+    let t = title.to_lowercase();
+    let volume = number_after(&t, &["volume", "vol", "season"]).or_else(|| short_season(&t));
+    let chapter = number_after(&t, &["chapter", "chap", "ch", "episode", "ep"]).or_else(|| chapter_from_url(url));
+    (volume, chapter)
+}
+
+// Badges themes put inside the chapter link, next to or instead of its name
+const BADGES: &[&str] = &["new", "hot", "up", "free", "baru"];
+
+// Link text that doesn't describe the chapter: empty, emoji or symbols only,
+// or nothing but badges ("NEW", "\u{1F525}", "\u{2728} HOT")
+pub fn is_decorative_title(title: &str) -> bool {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .all(|w| BADGES.iter().any(|b| w.eq_ignore_ascii_case(b)))
+}
+
+// "Season 1 Finale", "[Season 2 End]": the marker closes its season
+pub fn ends_season(title: &str) -> bool {
+    let t = title.to_lowercase();
+    let mut words = t.split(|c: char| !c.is_alphanumeric());
+    t.contains("season") && words.any(|w| matches!(w, "end" | "finale" | "final"))
+}
+
+// What sits between the series name, the chapter number and the subtitle
+const TITLE_SEPARATORS: &[char] = &[' ', '-', ':', '|', '\u{2013}', '\u{2014}'];
+
+// "Solo Leveling Chapter 10 - The Return" -> "The Return". Aidoku shows the
+// number on its own, so only the subtitle is worth keeping.
+pub fn clean_chapter_title(title: &str, series: &str) -> String {
+    let mut t = title.trim();
+    if let Some(head) = t.get(..series.len()) {
+        if !series.is_empty() && head.eq_ignore_ascii_case(series) { t = &t[series.len()..]; }
+    }
+    t = t.trim_start_matches(TITLE_SEPARATORS);
+    for key in ["chapter", "chap", "ch.", "episode", "ep."] {
+        if t.get(..key.len()).is_some_and(|h| h.eq_ignore_ascii_case(key)) {
+            t = t[key.len()..]
+                .trim_start_matches([' ', '.', ':', '#'])
+                .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+            break;
+        }
+    }
+    t.trim_start_matches(TITLE_SEPARATORS).trim().to_string()
+}
+
+// Language markers seen at the end of chapter titles
+const LANG_MARKERS: &[(&str, &str)] = &[
+    // For sites whose own releases aren't in English
+    ("english", "en"), ("[en]", "en"), ("(en)", "en"),
+    ("indonesian", "id"), ("indo", "id"), ("[id]", "id"), ("(id)", "id"),
+    ("spanish", "es"), ("espa\u{f1}ol", "es"), ("[es]", "es"), ("(es)", "es"),
+    ("portuguese", "pt-br"), ("[pt]", "pt-br"), ("(pt)", "pt-br"),
+    ("french", "fr"), ("[fr]", "fr"), ("(fr)", "fr"),
+    ("japanese raw", "ja"), ("jp raw", "ja"), ("chinese raw", "zh"), ("cn raw", "zh"),
+    // The site is mostly manhwa, so a bare RAW is a Korean raw
+    ("raw", "ko"),
+];
+
+// Language of a chapter from its title suffix, the site's unless marked otherwise.
+// A word marker only counts as a whole trailing word, optionally closed by a
+// bracket, so "Draw" and "Withdraw" stay untagged.
+pub fn chapter_lang(title: &str) -> &'static str {
+    let t = title.trim().to_lowercase();
+    let closed = t.strip_suffix([')', ']']).unwrap_or(&t);
+    LANG_MARKERS
+        .iter()
+        .find(|(marker, _)| {
+            [t.as_str(), closed].iter().any(|s| {
+                s.strip_suffix(marker).is_some_and(|head| {
+                    marker.starts_with(['[', '('])
+                        || !head.chars().next_back().is_some_and(char::is_alphanumeric)
+                })
+            })
+        })
+        .map(|(_, lang)| *lang)
+        .unwrap_or(site().lang)
+}
+
+// Month names per locale; the theme sometimes renders dates in Indonesian
+const MONTHS: &[[&str; 12]] = &[
+    [
+        "january", "february", "march", "april", "may", "june",
+        "july", "august", "september", "october", "november", "december",
+    ],
+    [
+        "januari", "februari", "maret", "april", "mei", "juni",
+        "juli", "agustus", "september", "oktober", "november", "desember",
+    ],
+];
+
+// "jan", "sept", "january", "agustus" -> 1-based month
+fn month_from_name(s: &str) -> Option<u8> {
+    if s.len() < 3 { return None; }
+    MONTHS
+        .iter()
+        .find_map(|names| names.iter().position(|m| m.starts_with(s)))
+        .map(|i| i as u8 + 1)
+}
+
+// Calendar math by hand (Hinnant's civil-day algorithms); a date crate is a
+// lot of WASM for two conversions
+fn is_leap(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+fn epoch(year: i32, month: u8, day: u8) -> Option<f64> {
+    let year = i64::from(if year < 100 { year + 2000 } else { year });
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) { return None; }
+    Some(days_from_civil(year, month.into(), day.into()) as f64 * 86400.0)
+}
+
+// "03/01/2024" (day first, like the site), "2024-01-03"
+fn parse_numeric_date(s: &str) -> Option<f64> {
+    let parts: Vec<&str> = s.split(['/', '-', '.']).collect();
+    let [a, b, c] = parts[..] else { return None };
+    let (a_len, a, b, c) = (a.len(), a.parse::<i32>().ok()?, b.parse::<i32>().ok()?, c.parse::<i32>().ok()?);
+    if a_len == 4 { return epoch(a, u8::try_from(b).ok()?, u8::try_from(c).ok()?); }
+    // Only read it month-first when day-first is impossible
+    let (day, month) = if b > 12 { (b, a) } else { (a, b) };
+    epoch(c, u8::try_from(month).ok()?, u8::try_from(day).ok()?)
+}
+
+// "5 mins ago", "2 days ago", "an hour ago", "yesterday" -> now minus that.
+// Indonesian too: "5 menit yang lalu", "sejam lalu", "kemarin".
+fn parse_relative_date(s: &str) -> Option<f64> {
+    if matches!(s, "just now" | "now" | "baru saja" | "sekarang") { return Some(current_date()); }
+    if matches!(s, "yesterday" | "kemarin") { return Some(current_date() - 86400.0); }
+    if matches!(s, "today" | "hari ini") { return Some(current_date()); }
+
+    let s = s.strip_suffix("ago").or_else(|| s.strip_suffix("yang lalu")).or_else(|| s.strip_suffix("lalu"))?.trim();
+    // "sejam", "sehari": se- is "one"
+    let s = match s.strip_prefix("se") {
+        Some(unit) if ["detik", "menit", "jam", "hari", "minggu", "bulan", "tahun"].contains(&unit) => format!("1 {unit}"),
+        _ => s.to_string(),
+    };
+    let mut words = s.split_whitespace();
+    let amount = match words.next()? {
+        "a" | "an" | "one" | "satu" => 1.0,
+        // "inf"/"nan" parse as floats too
+        n => n.parse::<f64>().ok().filter(|n| n.is_finite())?,
+    };
+    let unit = words.next()?;
+    let seconds = if unit.starts_with("sec") || unit == "detik" { 1.0 }
+        else if unit.starts_with("min") && unit != "minggu" || unit == "menit" { 60.0 }
+        else if unit.starts_with("hour") || unit.starts_with("hr") || unit == "jam" { 3600.0 }
+        else if unit.starts_with("day") || unit == "hari" { 86400.0 }
+        else if unit.starts_with("week") || unit == "minggu" { 7.0 * 86400.0 }
+        else if unit.starts_with("month") || unit == "bulan" { 30.0 * 86400.0 }
+        else if unit.starts_with("year") || unit == "tahun" { 365.0 * 86400.0 }
+        else { return None };
+    Some(current_date() - amount * seconds)
+}
+
+// Upload dates: "January 3, 2024", "3 Jan 2024", "03/01/2024", "2 hours ago"
+pub fn parse_date(s: &str) -> Option<f64> {
+    let s = s.trim().to_lowercase();
+    if let Some(d) = parse_relative_date(&s) { return Some(d); }
+    if let Some(d) = parse_numeric_date(&s) { return Some(d); }
+
+    let mut month = None;
+    let mut nums: Vec<i32> = Vec::new();
+    for w in s.split(|c: char| c == ',' || c.is_whitespace()).filter(|w| !w.is_empty()) {
+        let w = w.trim_end_matches('.');
+        if let Some(m) = month_from_name(w) { month = Some(m); }
+        else if let Ok(n) = w.trim_end_matches(|c: char| c.is_alphabetic()).parse::<i32>() { nums.push(n); }
+    }
+    let (day, year) = match nums[..] {
+        [a, b] if a > 31 => (b, a),
+        [a, b] => (a, b),
+        _ => return None,
+    };
+    epoch(year, month?, u8::try_from(day).ok()?)
+}
+
+// Upload dates as UTC epochs. An absolute date is a calendar day in the
+// site's timezone, so it starts `utc_offset` hours before that UTC midnight;
+// relative dates already count back from now.
+pub fn parse_upload_date(s: &str) -> Option<f64> {
+    if let Some(d) = parse_relative_date(&s.trim().to_lowercase()) { return Some(d); }
+    parse_date(s).map(|d| d - settings::utc_offset() * 3600.0)
+}
+
+// Epoch seconds -> "2024-01-03"
+pub fn format_date(epoch: f64) -> String {
+    if !epoch.is_finite() { return String::new(); }
+    let (year, month, day) = civil_from_days((epoch as i64).div_euclid(86400));
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// "&amp;", "&#8217;", "&#x2019;" -> the character; unknown entities are kept
+pub fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let Some(end) = rest.find(';').filter(|e| *e <= 10) else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "hellip" => Some('\u{2026}'),
+            "ndash" => Some('\u{2013}'),
+            "mdash" => Some('\u{2014}'),
+            "lsquo" => Some('\u{2018}'),
+            "rsquo" => Some('\u{2019}'),
+            "ldquo" => Some('\u{201C}'),
+            "rdquo" => Some('\u{201D}'),
+            e => e.strip_prefix('#').and_then(|n| match n.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => n.parse().ok(),
+            }).and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => { out.push(c); rest = &rest[end + 1..]; }
+            None => { out.push('&'); rest = &rest[1..]; }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Series/page title: decoded entities, single spaces, no " – Site Name" suffix
+pub fn clean_title(title: &str) -> String {
+    let title = decode_entities(title).split_whitespace().collect::<Vec<_>>().join(" ");
+    // ASCII lowering keeps byte offsets valid for slicing `title`
+    let lower = title.to_ascii_lowercase();
+    for name in site().title_suffixes {
+        if let Some(head) = lower.strip_suffix(name) {
+            let head = head.trim_end();
+            if let Some(sep) = head.chars().last().filter(|c| TITLE_SEPARATORS.contains(c)) {
+                return title[..head.len() - sep.len_utf8()].trim_end().to_string();
+            }
+        }
+    }
+    title
+}
+
+// Theme boilerplate that ends up in summaries; sites add their own
+const DESCRIPTION_BOILERPLATE: &[&str] = &["show more", "show less"];
+
+// Summary HTML -> plain text: <br>/<p> become line breaks, tags are dropped,
+// entities decoded, whitespace collapsed and boilerplate lines removed.
+pub fn clean_description(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = rest.find('<') {
+        text.push_str(&rest[..i]);
+        let Some(end) = rest[i..].find('>') else { rest = ""; break };
+        let tag = rest[i + 1..i + end].trim_start_matches('/').to_lowercase();
+        let name = tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or_default();
+        match name {
+            "br" => text.push('\n'),
+            "p" | "div" => text.push_str("\n\n"),
+            _ => {}
+        }
+        rest = &rest[i + end + 1..];
+    }
+    text.push_str(rest);
+
+    let text = decode_entities(&text);
+    let mut paragraphs: Vec<String> = Vec::new();
+    for para in text.split("\n\n") {
+        let lines: Vec<String> = para
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|l| !l.is_empty())
+            .filter(|l| {
+                let lower = l.to_lowercase();
+                !DESCRIPTION_BOILERPLATE.iter().chain(site().boilerplate).any(|b| lower.starts_with(b))
+            })
+            .collect();
+        if !lines.is_empty() { paragraphs.push(lines.join("\n")); }
+    }
+    paragraphs.join("\n\n")
+}
+
+// Largest candidate of a srcset ("a.jpg 175w, b.jpg 350w" -> b.jpg)
+pub fn best_srcset(srcset: &str) -> Option<String> {
+    srcset
+        .split(',')
+        .filter_map(|c| {
+            let mut parts = c.split_whitespace();
+            let url = parts.next()?;
+            let size = parts
+                .next()
+                .and_then(|d| d.trim_end_matches(['w', 'x']).parse::<f32>().ok())
+                .unwrap_or(0.0);
+            Some((url, size))
+        })
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(core::cmp::Ordering::Equal))
+        .map(|(url, _)| url.to_string())
+}
+
+// Undo CDN/WordPress resizing: drop ?resize=/w=/h=/fit= and "-193x278" before
+// the extension, so covers load at full size.
+pub fn full_size_image(url: &str) -> String {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .filter(|p| !["resize", "w", "h", "fit", "quality", "strip", "ssl"].contains(&p.split('=').next().unwrap_or_default()))
+        .collect();
+
+    let mut path = path.to_string();
+    if let Some(dot) = path.rfind('.').filter(|d| !path[*d..].contains('/')) {
+        let (stem, ext) = path.split_at(dot);
+        if let Some(dash) = stem.rfind('-') {
+            let size = &stem[dash + 1..];
+            let is_size = size
+                .split_once('x')
+                .is_some_and(|(w, h)| !w.is_empty() && !h.is_empty() && w.chars().chain(h.chars()).all(|c| c.is_ascii_digit()));
+            if is_size { path = format!("{}{ext}", &stem[..dash]); }
+        }
+    }
+    if kept.is_empty() { path } else { format!("{path}?{}", kept.join("&")) }
+}
+
+// Last run of digits in an image's file name: ".../chapter-3/012.webp" -> 12
+pub fn filename_number(url: &str) -> Option<u32> {
+    let name = url.split(['?', '#']).next()?.rsplit('/').next()?;
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end].trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    stem[start..end].parse().ok()
+}
+
+// Same URL on another host: "https://a.com/x/1.jpg", "b.com" -> "https://b.com/x/1.jpg"
+pub fn replace_host(url: &str, host: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else { return url.to_string() };
+    let path = rest.find('/').map_or("", |i| &rest[i..]);
+    format!("{scheme}://{host}{path}")
+}
+
+// Standard padded base64, for images handed to Aidoku inline
+pub fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (u32::from(chunk[0]) << 16)
+            | (u32::from(chunk.get(1).copied().unwrap_or(0)) << 8)
+            | u32::from(chunk.get(2).copied().unwrap_or(0));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// Comparable form of a title: lowercase words of letters/digits only, so
+// "Solo Leveling: Ragnarok" and "solo leveling ragnarok" match
+pub fn normalize_title(title: &str) -> String {
+    decode_entities(title)
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Character bigrams of a normalized title, spaces dropped
+fn bigrams(s: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = s.chars().filter(|c| *c != ' ').collect();
+    let mut pairs: Vec<(char, char)> = chars.windows(2).map(|w| (w[0], w[1])).collect();
+    pairs.sort_unstable();
+    pairs
+}
+
+// 0..=1 closeness of a title to a query. Exact matches first, then titles
+// containing every query word in any order, then bigram overlap (Dice).
+pub fn title_similarity(query: &str, title: &str) -> f32 {
+    let (q, t) = (normalize_title(query), normalize_title(title));
+    if q.is_empty() || t.is_empty() { return 0.0; }
+    if q == t { return 1.0; }
+
+    let (a, b) = (bigrams(&q), bigrams(&t));
+    let mut shared = 0;
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            core::cmp::Ordering::Less => i += 1,
+            core::cmp::Ordering::Greater => j += 1,
+            core::cmp::Ordering::Equal => { shared += 1; i += 1; j += 1; }
+        }
+    }
+    let dice = if a.len() + b.len() == 0 { 0.0 } else { 2.0 * shared as f32 / (a.len() + b.len()) as f32 };
+
+    let words: Vec<&str> = t.split(' ').collect();
+    if q.split(' ').all(|w| words.contains(&w)) {
+        // Shorter titles holding all the words are likelier the intended one
+        0.8 + 0.15 * dice
+    } else {
+        0.75 * dice
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: f64 = 86400.0;
+
+    #[test]
+    fn absolute_dates() {
+        let jan3 = 19725.0 * DAY;
+        assert_eq!(parse_date("January 3, 2024"), Some(jan3));
+        assert_eq!(parse_date("3 Jan 2024"), Some(jan3));
+        assert_eq!(parse_date("Jan 3rd, 2024"), Some(jan3));
+        assert_eq!(parse_date("03/01/2024"), Some(jan3));
+        assert_eq!(parse_date("2024-01-03"), Some(jan3));
+        assert_eq!(parse_date("3 Januari 2024"), Some(jan3));
+        assert_eq!(parse_date("17 Agustus 2024"), Some(19952.0 * DAY));
+        // Month-first only when day-first can't be
+        assert_eq!(parse_date("01/13/2024"), Some(19735.0 * DAY));
+        assert_eq!(parse_date("29 Feb 2024"), Some(19782.0 * DAY));
+        assert_eq!(parse_date("29 Feb 2023"), None);
+        assert_eq!(parse_date("31/04/2024"), None);
+        assert_eq!(parse_date("soon"), None);
+        assert_eq!(format_date(19725.0 * DAY + 3600.0), "2024-01-03");
+    }
+
+    #[test]
+    fn chapter_numbers_from_urls() {
+        assert_eq!(chapter_from_url("https://a.com/solo-leveling-chapter-10/"), Some(10.0));
+        assert_eq!(chapter_from_url("https://a.com/solo-leveling-chapter-10-5/"), Some(10.5));
+        assert_eq!(chapter_from_url("https://a.com/series-ch-7?x=1#top"), Some(7.0));
+        assert_eq!(chapter_from_url("https://a.com/series-episode12/"), Some(12.0));
+        // Digits in the series name don't count
+        assert_eq!(chapter_from_url("https://a.com/2-kids-chapter-3/"), Some(3.0));
+        assert_eq!(chapter_from_url("https://a.com/series-chapter-3-part-2/"), Some(3.0));
+        assert_eq!(chapter_from_url("https://a.com/epoch-prologue/"), None);
+        assert_eq!(chapter_numbers("Season 2 Chapter 15", ""), (Some(2.0), Some(15.0)));
+        assert_eq!(chapter_numbers("S2 Ep. 4", ""), (Some(2.0), Some(4.0)));
+    }
+
+    #[test]
+    fn chapter_titles() {
+        assert_eq!(clean_chapter_title("Solo Leveling Chapter 10 - The Return", "Solo Leveling"), "The Return");
+        assert_eq!(clean_chapter_title("Ch. 3.5: Side Story", ""), "Side Story");
+        assert_eq!(clean_chapter_title("Chapter 12", ""), "");
+        assert!(is_decorative_title("\u{2728} HOT"));
+        assert!(!is_decorative_title("New Beginnings"));
+        assert!(ends_season("[Season 2 End]"));
+        assert!(!ends_season("The End of the World"));
+        assert_eq!(chapter_lang("Chapter 5 [RAW]"), "ko");
+        assert_eq!(chapter_lang("Chapter 5 (Indonesian)"), "id");
+        assert_eq!(chapter_lang("Chapter 5"), site().lang);
+        assert_eq!(chapter_lang("Chapter 12 - Draw"), site().lang);
+        assert_eq!(chapter_lang("Chapter 3: The Last Straw"), site().lang);
+        assert_eq!(chapter_lang("Chapter 8 Withdraw"), site().lang);
+        assert_eq!(chapter_lang("Chapter 4 Kindo"), site().lang);
+        assert_eq!(chapter_lang("Chapter 5 - Raw"), "ko");
+        assert_eq!(chapter_lang("Chapter 5 (Indo)"), "id");
+        assert_eq!(chapter_lang("Chapter 5 [Japanese RAW]"), "ja");
+    }
+
+    #[test]
+    fn srcsets_and_resized_images() {
+        assert_eq!(best_srcset("a.jpg 175w, b.jpg 350w, c.jpg 300w").as_deref(), Some("b.jpg"));
+        assert_eq!(best_srcset("a.jpg 1x, b.jpg 2x").as_deref(), Some("b.jpg"));
+        assert_eq!(best_srcset(""), None);
+        assert_eq!(full_size_image("https://a.com/cover-193x278.jpg"), "https://a.com/cover.jpg");
+        assert_eq!(full_size_image("https://a.com/cover.jpg?resize=193,278&v=2"), "https://a.com/cover.jpg?v=2");
+        assert_eq!(full_size_image("https://a.com/2024-10x/cover-x.jpg"), "https://a.com/2024-10x/cover-x.jpg");
+        assert_eq!(filename_number("https://a.com/chapter-3/012.webp"), Some(12));
+        assert_eq!(filename_number("https://a.com/chapter-3/page_7-hd.jpg?v=1"), Some(7));
+        assert_eq!(filename_number("https://a.com/chapter-3/cover.jpg"), None);
+        assert_eq!(replace_host("https://a.com/x/1.jpg", "b.com"), "https://b.com/x/1.jpg");
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(&[0xff, 0xfe, 0xfd, 0x00]), "//79AA==");
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(decode_entities("Tom &amp; Jerry"), "Tom & Jerry");
+        assert_eq!(decode_entities("It&#8217;s &#x2019;"), "It\u{2019}s \u{2019}");
+        assert_eq!(decode_entities("&hellip;&nbsp;&mdash;"), "\u{2026} \u{2014}");
+        // Unknown or unterminated entities are left alone
+        assert_eq!(decode_entities("&bogus; & &amp"), "&bogus; & &amp");
+        assert_eq!(decode_entities("&#xD800;"), "&#xD800;");
+    }
+
+    fn everything(s: &str) {
+        let series: String = s.chars().take(5).collect();
+        let _ = parse_date(s);
+        let _ = parse_upload_date(s);
+        let _ = chapter_from_url(s);
+        let _ = chapter_numbers(s, s);
+        let _ = clean_chapter_title(s, &series);
+        let _ = (is_decorative_title(s), ends_season(s), chapter_lang(s));
+        let _ = (decode_entities(s), clean_title(s), clean_description(s), normalize_title(s));
+        let _ = (best_srcset(s), full_size_image(s), filename_number(s), replace_host(s, "b.com"));
+        let _ = title_similarity(s, &series);
+    }
+
+    // What the site serves can be anything; none of it may trap the module
+    #[test]
+    fn garbage_never_panics() {
+        for seed in 0..3000 {
+            everything(&crate::tests::garbage(seed, seed as usize % 48));
+            everything(&String::from_utf8_lossy(&crate::tests::noise(seed, 32)));
+        }
+    }
+
+    #[test]
+    fn truncated_input_never_panics() {
+        let inputs = [
+            "January 3, 2024",
+            "5 menit yang lalu",
+            "https://a.com/x-chapter-10-5/cover-193x278.jpg?resize=193,278&v=2#top",
+            "a.jpg 175w, b.jpg 350w",
+            "<p>Tom &amp; Jerry &#x2019;\u{1F525}</p><br/>Show more",
+            "Solo Leveling \u{2013} Chapter 10.5: The Return [RAW]",
+        ];
+        for input in inputs {
+            crate::tests::prefixes(input).for_each(everything);
+        }
+    }
+}
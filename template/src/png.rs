@@ -0,0 +1,177 @@
+// PNG decoding for scrambled pages (see tiles.rs): every color type and bit
+// depth, but not interlaced images. Alpha is flattened onto white, which is
+// what the reader shows behind a transparent page. Chunk CRCs aren't checked.
+
+use aidoku::std::Vec;
+
+use crate::{
+    inflate,
+    tiles::{Image, MAX_PIXELS},
+};
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let (ia, ib, ic) = (i16::from(a), i16::from(b), i16::from(c));
+    let p = ia + ib - ic;
+    let (pa, pb, pc) = ((p - ia).abs(), (p - ib).abs(), (p - ic).abs());
+    if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+// Undoes one scanline's filter in place, against the line above it
+fn unfilter(filter: u8, line: &mut [u8], above: &[u8], bpp: usize) -> Option<()> {
+    for i in 0..line.len() {
+        let a = if i >= bpp { line[i - bpp] } else { 0 };
+        let (b, c) = (above[i], if i >= bpp { above[i - bpp] } else { 0 });
+        let predicted = match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+            4 => paeth(a, b, c),
+            _ => return None,
+        };
+        line[i] = line[i].wrapping_add(predicted);
+    }
+    Some(())
+}
+
+// Sample `i` of a scanline as 8 bits
+fn sample(line: &[u8], i: usize, depth: u8, scale: bool) -> u8 {
+    match depth {
+        8 => line[i],
+        16 => line[2 * i],
+        _ => {
+            let bit = i * usize::from(depth);
+            let v = (line[bit / 8] >> (8 - usize::from(depth) - bit % 8)) & ((1 << depth) - 1);
+            // Palette indices stay indices; gray levels stretch to 0..255
+            if scale { (u16::from(v) * 255 / ((1 << depth) - 1)) as u8 } else { v }
+        }
+    }
+}
+
+fn over_white(v: u8, alpha: u8) -> u8 {
+    ((u16::from(v) * u16::from(alpha) + 255 * u16::from(255 - alpha)) / 255) as u8
+}
+
+pub fn decode(data: &[u8]) -> Option<Image> {
+    let body = data.strip_prefix(b"\x89PNG\r\n\x1a\n")?;
+    let (mut header, mut palette, mut alphas): (Option<&[u8]>, &[u8], &[u8]) = (None, &[], &[]);
+    let mut compressed = Vec::new();
+    let mut pos = 0;
+    loop {
+        let len = u32::from_be_bytes(body.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let kind = body.get(pos + 4..pos + 8)?;
+        let chunk = body.get(pos + 8..(pos + 8).checked_add(len)?)?;
+        pos += 12 + len;
+        match kind {
+            b"IHDR" => header = Some(chunk),
+            b"PLTE" => palette = chunk,
+            b"tRNS" => alphas = chunk,
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+    let &[w0, w1, w2, w3, h0, h1, h2, h3, depth, color, 0, 0, 0] = header? else { return None };
+    let (width, height) = (u32::from_be_bytes([w0, w1, w2, w3]) as usize, u32::from_be_bytes([h0, h1, h2, h3]) as usize);
+    if width == 0 || height == 0 || width.checked_mul(height)? > MAX_PIXELS { return None; }
+    let samples = match (color, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) | (3, 1 | 2 | 4 | 8) => 1,
+        (4, 8 | 16) => 2,
+        (2, 8 | 16) => 3,
+        (6, 8 | 16) => 4,
+        _ => return None,
+    };
+    if color == 3 && palette.len() < 3 { return None; }
+    let bits = samples * usize::from(depth);
+    let (stride, bpp) = ((width * bits).div_ceil(8), bits.div_ceil(8));
+    let raw = inflate::zlib(&compressed)?;
+    if raw.len() < height * (stride + 1) { return None; }
+
+    let channels = if color == 0 || color == 4 { 1 } else { 3 };
+    let mut pixels = Vec::with_capacity(width * height * channels);
+    let mut above = vec![0u8; stride];
+    for row in raw.chunks(stride + 1).take(height) {
+        let mut line = row[1..].to_vec();
+        unfilter(row[0], &mut line, &above, bpp)?;
+        for x in 0..width {
+            let s = |k: usize| sample(&line, x * samples + k, depth, color != 3);
+            match color {
+                0 => pixels.push(s(0)),
+                4 => pixels.push(over_white(s(0), s(1))),
+                2 => pixels.extend_from_slice(&[s(0), s(1), s(2)]),
+                6 => {
+                    let alpha = s(3);
+                    pixels.extend_from_slice(&[0, 1, 2].map(|k| over_white(s(k), alpha)));
+                }
+                _ => {
+                    let i = usize::from(s(0));
+                    let rgb = palette.get(3 * i..3 * i + 3).unwrap_or(&[0, 0, 0]);
+                    let alpha = alphas.get(i).copied().unwrap_or(255);
+                    pixels.extend(rgb.iter().map(|&v| over_white(v, alpha)));
+                }
+            }
+        }
+        above = line;
+    }
+    Some(Image { width, height, channels, pixels })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The pattern the fixtures were written from: 45x29, red across, green
+    // down, blue diagonally
+    const W: usize = 45;
+    const H: usize = 29;
+
+    fn rgb(x: usize, y: usize) -> [u8; 3] {
+        [(x * 255 / (W - 1)) as u8, (y * 255 / (H - 1)) as u8, ((x + y) * 255 / (W + H - 2)) as u8]
+    }
+
+    fn gray(x: usize, y: usize) -> u8 {
+        let [r, g, _] = rgb(x, y);
+        r / 2 + g / 2
+    }
+
+    fn pixel(image: &Image, x: usize, y: usize) -> &[u8] {
+        let i = (y * image.width + x) * image.channels;
+        &image.pixels[i..i + image.channels]
+    }
+
+    #[test]
+    fn color_types() {
+        let image = decode(include_bytes!("../tests/fixtures/tiles_rgb.png")).unwrap();
+        assert_eq!((image.width, image.height, image.channels), (W, H, 3));
+        assert!((0..H).all(|y| (0..W).all(|x| pixel(&image, x, y) == rgb(x, y))));
+
+        // Transparent first row, over white
+        let image = decode(include_bytes!("../tests/fixtures/tiles_rgba.png")).unwrap();
+        assert!((0..W).all(|x| pixel(&image, x, 0) == [255; 3]));
+        assert!((1..H).all(|y| (0..W).all(|x| pixel(&image, x, y) == rgb(x, y))));
+
+        let image = decode(include_bytes!("../tests/fixtures/tiles_gray16.png")).unwrap();
+        assert_eq!(image.channels, 1);
+        assert!((0..H).all(|y| (0..W).all(|x| pixel(&image, x, y) == [gray(x, y)])));
+
+        // 4-bit palette of 16 grays, entry 0 transparent
+        let image = decode(include_bytes!("../tests/fixtures/tiles_palette.png")).unwrap();
+        assert!((0..H).all(|y| (0..W).all(|x| {
+            let level = gray(x, y) >> 4;
+            pixel(&image, x, y)[0] == if level == 0 { 255 } else { level * 17 }
+        })));
+    }
+
+    #[test]
+    fn garbage_never_panics() {
+        let data = include_bytes!("../tests/fixtures/tiles_rgb.png");
+        for len in (0..data.len()).step_by(7) {
+            decode(&data[..len]);
+        }
+        let mut flipped = data.to_vec();
+        for i in (8..flipped.len()).step_by(13) {
+            flipped[i] ^= 0x5a;
+            decode(&flipped);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Page URLs from JS-driven readers, for chapters whose <img> tags are
+// injected by script and missing from the served HTML, and the prose of
+// novel chapters, which have no pages at all.
+
+use aidoku::std::{String, Vec};
+use serde_json::Value;
+
+use crate::{first, host::Node, http::parse_html, image_url, select, text, theme::Selectors, tiles::Layout};
+
+// The JSON object/array literal starting at the first '{' or '[' after
+// `marker`, matched by bracket depth (strings and escapes respected).
+fn json_after<'a>(script: &'a str, marker: &str) -> Option<&'a str> {
+    let start = script.find(marker)? + marker.len();
+    let open = start + script[start..].find(['{', '['])?;
+    let mut depth = 0;
+    let mut in_str = false;
+    let mut escaped = false;
+    for (i, c) in script[open..].char_indices() {
+        if in_str {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_str = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_str = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 { return Some(&script[open..open + i + 1]); }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Script bodies are data, not text, to the parser
+fn scripts(dom: &Node) -> Vec<String> {
+    select(dom, "script").into_iter().map(|s| s.data().read()).collect()
+}
+
+// MangaStream themes: ts_reader.run({"sources":[{"images":[...]}], ...})
+pub fn ts_reader_pages(dom: &Node) -> Vec<String> {
+    for script in scripts(dom) {
+        let Some(json) = json_after(&script, "ts_reader.run(") else { continue };
+        let Ok(v) = serde_json::from_str::<Value>(json) else { continue };
+        let images: Vec<String> = v["sources"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find_map(|s| s["images"].as_array().filter(|i| !i.is_empty()))
+            .into_iter()
+            .flatten()
+            .filter_map(|i| i.as_str().map(|u| u.trim().to_string()))
+            .filter(|u| !u.is_empty())
+            .collect();
+        if !images.is_empty() { return images; }
+    }
+    Vec::new()
+}
+
+// Madara: var chapter_preloaded_images = ["...", ...] (or chapter_data when
+// it isn't encrypted). Arrays, {"1": "..."} maps and {"src": ...} entries.
+pub fn preloaded_pages(dom: &Node) -> Vec<String> {
+    fn image_url(v: &Value) -> Option<String> {
+        let u = v.as_str().or_else(|| v["src"].as_str()).or_else(|| v["url"].as_str())?;
+        Some(u.trim().replace("\\/", "/")).filter(|u| !u.is_empty())
+    }
+
+    for script in scripts(dom) {
+        for marker in ["chapter_preloaded_images", "chapter_data"] {
+            let Some(json) = json_after(&script, marker) else { continue };
+            let Ok(v) = serde_json::from_str::<Value>(json) else { continue };
+            let images: Vec<String> = match &v {
+                Value::Array(items) => items.iter().filter_map(image_url).collect(),
+                // serde_json sorts keys as strings ("10" < "2"), so order numerically
+                Value::Object(map) => {
+                    let mut entries: Vec<(u32, String)> = map
+                        .iter()
+                        .filter_map(|(k, v)| Some((k.parse().unwrap_or(u32::MAX), image_url(v)?)))
+                        .collect();
+                    entries.sort_by_key(|(k, _)| *k);
+                    entries.into_iter().map(|(_, u)| u).collect()
+                }
+                _ => Vec::new(),
+            };
+            if !images.is_empty() { return images; }
+        }
+    }
+    Vec::new()
+}
+
+// Scrambled-tile chapters: the key the reader script puts the tiles back
+// with, as `var scramble = {...}` or a "scramble" field of its config
+pub fn tile_layout(dom: &Node) -> Option<Layout> {
+    scripts(dom).iter().find_map(|script| {
+        ["\"scramble\":", "scramble =", "scramble="].iter().find_map(|m| Layout::parse(json_after(script, m)?))
+    })
+}
+
+// Lazy-load plugins leave a 1x1 placeholder in src and the real <img> inside
+// <noscript>, which the parser may keep as raw markup; parse it separately.
+pub fn noscript_pages(dom: &Node, s: &Selectors) -> Vec<String> {
+    let mut images = Vec::new();
+    for block in select(dom, s.page_noscript) {
+        let Ok(inner) = parse_html(block.html().read().as_bytes()) else { continue };
+        for img in select(&inner, "img") {
+            if let Some(src) = image_url(&img) { images.push(src); }
+        }
+    }
+    images
+}
+
+// Shorter than this it's a caption or an error notice, not a chapter
+const MIN_NOVEL_TEXT: usize = 200;
+
+// Paragraphs of the reader container, a blank line apart, for Aidoku's
+// text reader
+pub fn novel_text(dom: &Node, s: &Selectors) -> Option<String> {
+    let body = first(dom, s.novel_text)?;
+    let mut paragraphs: Vec<String> = select(&body, "p").iter().map(text).filter(|p| !p.is_empty()).collect();
+    // Some posts are bare text with <br> breaks instead of paragraphs
+    if paragraphs.is_empty() { paragraphs.push(text(&body)); }
+    let content = paragraphs.join("\n\n");
+    if content.chars().count() < MIN_NOVEL_TEXT { None } else { Some(content) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_after_matches_brackets() {
+        let script = r#"ts_reader.run({"sources":[{"images":["a.jpg","b.jpg"]}],"x":1});"#;
+        assert_eq!(json_after(script, "ts_reader.run("), Some(r#"{"sources":[{"images":["a.jpg","b.jpg"]}],"x":1}"#));
+        // Brackets and escaped quotes inside strings don't count
+        let script = r#"var chapter_preloaded_images = ["a]\"{.jpg", "b.jpg"], next = [1];"#;
+        assert_eq!(json_after(script, "chapter_preloaded_images"), Some(r#"["a]\"{.jpg", "b.jpg"]"#));
+        assert_eq!(json_after("var x = [1, [2, 3]", "x"), None);
+        assert_eq!(json_after("var y = [1]", "x"), None);
+        assert_eq!(json_after("x = 1;", "x"), None);
+    }
+
+    #[test]
+    fn tile_layouts() {
+        let dom = |script: &str| parse_html(format!("<html><script>{script}</script></html>").as_bytes()).unwrap();
+        let layout = tile_layout(&dom(r#"var scramble = {"cols": 2, "rows": 2, "key": "3-2-1-0"};"#)).unwrap();
+        assert!(layout.covers("https://a.com/001.jpg"));
+        let layout = tile_layout(&dom(r#"ts_reader.run({"sources": [], "scramble": {"cols": 2, "rows": 1, "images": {"2.jpg": [1, 0]}}});"#));
+        assert!(layout.is_some_and(|l| l.covers("https://a.com/2.jpg") && !l.covers("https://a.com/1.jpg")));
+        assert!(tile_layout(&dom("function unscramble(tiles) { return tiles; }")).is_none());
+    }
+
+    #[test]
+    fn garbage_never_panics() {
+        for seed in 0..3000 {
+            let script = crate::tests::garbage(seed, seed as usize % 64);
+            for marker in ["", "[", "{", "ts_reader.run(", "\u{1F525}"] {
+                let _ = json_after(&script, marker);
+            }
+        }
+        let script = r#"ts_reader.run({"sources":[{"images":["a\"].jpg","\u{e9}.jpg"]}]});"#;
+        for prefix in crate::tests::prefixes(script) {
+            let _ = json_after(prefix, "ts_reader.run(");
+        }
+    }
+}
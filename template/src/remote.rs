@@ -0,0 +1,107 @@
+// Hot-fix config published by the maintainer: a small JSON file at the
+// site's pinned `remote_config` URL, fetched at startup and kept in defaults,
+// so a domain move or a theme update can be patched without a new .aix.
+//
+//   {
+//     "base_url": "https://new-domain.example",
+//     "selectors": {"chapter_list": "ul.clstyle li a"},
+//     "ad_hosts": ["ads.example"],
+//     "mirrors": ["https://mirror.example"]
+//   }
+//
+// Every key is optional. The user's own settings win over all of them.
+
+use aidoku::std::{net::HttpMethod, String, Vec};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::sync::{Mutex, OnceLock};
+
+use crate::{
+    client::{client, HttpRequest},
+    host::{current_date, defaults_get, defaults_set, StringRef},
+    http, settings, site,
+};
+
+// How long a fetched config is trusted before asking again
+const TTL: f64 = 6.0 * 60.0 * 60.0;
+
+const BODY_KEY: &str = "remote.body";
+const TIME_KEY: &str = "remote.time";
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Remote {
+    pub base_url: String,
+    pub selectors: Map<String, Value>,
+    pub ad_hosts: Vec<String>,
+    pub mirrors: Vec<String>,
+    // `selectors` as JSON, serialized once for theme::overrides
+    #[serde(skip)]
+    pub selectors_json: String,
+}
+
+// Parsed copy of the stored body, leaked so every lookup can borrow it.
+// Only a refresh that brings a new body replaces it, a few times a day at
+// most, so the old copies leaking is a bounded cost.
+static LOADED: Mutex<Option<&'static Remote>> = Mutex::new(None);
+// What get() answers during the current entry-point call: the setting and
+// the stored body are read once per call, not once per URL or selector.
+static CURRENT: Mutex<Option<&'static Remote>> = Mutex::new(None);
+static EMPTY: OnceLock<Remote> = OnceLock::new();
+
+fn read(key: &str) -> String {
+    defaults_get(key).and_then(|v| v.as_string()).map(|v| v.read()).unwrap_or_default()
+}
+
+fn write(key: &str, value: &str) {
+    defaults_set(key, StringRef::from(value).0);
+}
+
+// Refetch when the stored copy is older than TTL. Failures keep the old copy;
+// a GitHub outage shouldn't undo a fix that's already been picked up.
+pub fn refresh() {
+    let Some(url) = site().remote_config else { return };
+    if !settings::remote_config() { return; }
+    let fetched = read(TIME_KEY).parse::<f64>().unwrap_or(0.0);
+    if current_date() - fetched < TTL { return; }
+
+    let user_agent = http::user_agent();
+    let headers = [("User-Agent", user_agent.as_str())];
+    let request = HttpRequest { method: HttpMethod::Get, url, headers: &headers, body: None };
+    let Some(response) = client().send(&request).filter(|r| r.status == 200) else { return };
+    let body = String::from_utf8_lossy(&response.data).to_string();
+    if serde_json::from_str::<Remote>(&body).is_err() { return; }
+
+    write(BODY_KEY, &body);
+    write(TIME_KEY, &current_date().to_string());
+    *LOADED.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    begin_call();
+    settings::forget_base_url();
+}
+
+// Start of an entry-point call: the setting may have changed since the last one
+pub fn begin_call() {
+    *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+fn parse(body: &str) -> Remote {
+    let mut remote: Remote = serde_json::from_str(body).unwrap_or_default();
+    if !remote.selectors.is_empty() {
+        remote.selectors_json = Value::Object(remote.selectors.clone()).to_string();
+    }
+    remote
+}
+
+fn load() -> &'static Remote {
+    if site().remote_config.is_none() || !settings::remote_config() {
+        return EMPTY.get_or_init(Remote::default);
+    }
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    loaded.get_or_insert_with(|| Box::leak(Box::new(parse(&read(BODY_KEY)))))
+}
+
+// Current config; empty when the site has none or the user turned it off
+pub fn get() -> &'static Remote {
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    current.get_or_insert_with(load)
+}
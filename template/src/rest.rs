@@ -0,0 +1,57 @@
+// WordPress REST API (/wp-json/wp/v2/): clean JSON for plain title searches,
+// unaffected by theme changes. Madara's filters have no REST equivalent.
+
+use aidoku::{
+    error::Result,
+    std::{String, Vec},
+};
+use serde_json::Value;
+
+use crate::{error::SourceError, http::get_text_cached, parse::decode_entities, settings, url};
+
+pub const PER_PAGE: usize = 20;
+
+pub struct Post {
+    pub title: String,
+    pub link: String,
+    pub cover: Option<String>,
+    pub categories: Vec<String>,
+}
+
+fn search_url(query: &str, page: i32) -> String {
+    format!(
+        "{}/wp-json/wp/v2/wp-manga?search={}&page={}&per_page={PER_PAGE}&_embed=wp:featuredmedia,wp:term",
+        settings::base_url(),
+        url::encode(query),
+        page.max(1)
+    )
+}
+
+fn post(v: &Value) -> Option<Post> {
+    let title = decode_entities(v["title"]["rendered"].as_str()?.trim());
+    let link = v["link"].as_str()?.to_string();
+    if title.is_empty() || link.is_empty() { return None; }
+    let embedded = &v["_embedded"];
+    let cover = embedded["wp:featuredmedia"][0]["source_url"].as_str().map(String::from);
+    // wp:term is a list of taxonomies, each a list of terms
+    let categories = embedded["wp:term"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|t| t.as_array())
+        .flatten()
+        .filter(|t| t["taxonomy"].as_str().is_some_and(|x| x.contains("genre")))
+        .filter_map(|t| t["name"].as_str().map(decode_entities))
+        .collect();
+    Some(Post { title, link, cover, categories })
+}
+
+// Anything but a JSON array (404, login wall, HTML error page) means the
+// API isn't usable on this site
+pub fn search(query: &str, page: i32) -> Result<Vec<Post>> {
+    let body = get_text_cached(&search_url(query, page))?;
+    let Ok(Value::Array(items)) = serde_json::from_str::<Value>(&body) else {
+        return Err(SourceError::Parse.into());
+    };
+    Ok(items.iter().filter_map(post).collect())
+}
@@ -0,0 +1,272 @@
+// Source preferences declared in res/settings.json, read through Aidoku's
+// defaults. Keys here and in the JSON must match.
+
+use aidoku::std::{String, Vec};
+
+use std::sync::Mutex;
+
+use crate::{
+    host::{defaults_get, defaults_set, StringRef},
+    remote, site,
+};
+
+fn get_bool(key: &str) -> bool {
+    defaults_get(key).and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+fn get_string(key: &str) -> String {
+    defaults_get(key).and_then(|v| v.as_string()).map(|v| v.read()).unwrap_or_default()
+}
+
+// Sent by the Site and Account groups (base URL, UA, cookies, login) via
+// "notification" in the JSON
+pub const SITE_CHANGED: &str = "siteChanged";
+
+// ---- Site ----
+// Override for when the site changes domains; "example.org" and
+// "https://example.org/" both work. Falls back to the remote config's
+// base URL, then the site's own.
+fn configured_base_url() -> String {
+    let mut value = get_string("baseUrl");
+    if value.trim().is_empty() { value.clone_from(&remote::get().base_url); }
+    let value = value.trim().trim_end_matches('/');
+    if value.is_empty() || value.contains(' ') {
+        site().base_url.into()
+    } else if value.starts_with("http://") || value.starts_with("https://") {
+        value.into()
+    } else {
+        format!("https://{value}")
+    }
+}
+
+// Where the configured domain last redirected to. Recorded against the
+// configured value, so editing the setting drops it.
+const REDIRECT_FROM: &str = "redirect.from";
+const REDIRECT_TO: &str = "redirect.to";
+
+// The effective base URL for the current entry-point call. Every abs(),
+// id_url() and image URL asks for it; worked out once per call, it's one
+// pass over defaults and the remote config instead of one per link.
+static BASE_URL: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn forget_base_url() {
+    *BASE_URL.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+// Effective base URL: the configured one, or where it now redirects
+pub fn base_url() -> String {
+    let mut memo = BASE_URL.lock().unwrap_or_else(|e| e.into_inner());
+    memo.get_or_insert_with(|| {
+        let configured = configured_base_url();
+        let to = get_string(REDIRECT_TO);
+        if !to.is_empty() && get_string(REDIRECT_FROM) == configured { to } else { configured }
+    })
+    .clone()
+}
+
+pub fn remember_redirect(to: &str) {
+    defaults_set(REDIRECT_FROM, StringRef::from(configured_base_url().as_str()).0);
+    defaults_set(REDIRECT_TO, StringRef::from(to).0);
+    forget_base_url();
+}
+
+// Failover domains in the order they're tried: the user's list, then the
+// remote config's, then the site's own. Entries are "https://host".
+pub fn mirrors() -> Vec<String> {
+    let mut mirrors: Vec<String> = Vec::new();
+    let user = get_string("mirrors");
+    let listed = user
+        .split(',')
+        .map(String::from)
+        .chain(remote::get().mirrors.iter().cloned())
+        .chain(site().mirrors.iter().map(|m| String::from(*m)));
+    for m in listed {
+        let m = m.trim().trim_end_matches('/');
+        if m.is_empty() || m.contains(' ') { continue; }
+        let m = if m.starts_with("http://") || m.starts_with("https://") { m.into() } else { format!("https://{m}") };
+        if !mirrors.contains(&m) { mirrors.push(m); }
+    }
+    mirrors
+}
+
+// The WAF sometimes blocks the built-in UA; let users swap it out. Empty
+// keeps the header profile's own.
+pub fn user_agent() -> String {
+    get_string("userAgent").trim().into()
+}
+
+// "safari" or "chrome"; see http::Profile
+pub fn header_profile() -> String {
+    get_string("headerProfile")
+}
+
+// Cookie header for Cloudflare "under attack" mode. Accepts either a bare
+// cf_clearance value or a full "name=value; name2=value2" header.
+pub fn cookie() -> String {
+    let value = get_string("cookie");
+    let value = value.trim().trim_start_matches("Cookie:").trim();
+    if value.is_empty() || value.contains('=') { value.into() } else { format!("cf_clearance={value}") }
+}
+
+// Requests per second; the site IP-bans bursts during library refresh
+pub fn rate_limit() -> i32 {
+    get_string("rateLimit").parse().ok().filter(|n| *n > 0).unwrap_or(DEFAULT_RATE_LIMIT)
+}
+
+pub const DEFAULT_RATE_LIMIT: i32 = 2;
+
+// Seconds one request may take, retries and mirror failover included
+pub fn request_timeout() -> f64 {
+    get_string("requestTimeout").parse::<f64>().ok().filter(|n| *n > 0.0).unwrap_or(DEFAULT_REQUEST_TIMEOUT)
+}
+
+pub const DEFAULT_REQUEST_TIMEOUT: f64 = 30.0;
+
+// ---- Account ----
+// wp-login.php credentials, used when a chapter comes back empty
+pub fn username() -> String {
+    get_string("username").trim().into()
+}
+
+pub fn password() -> String {
+    get_string("password")
+}
+
+// Logged-in session cookie copied from a browser ("wordpress_logged_in_...=..."),
+// for accounts that can't use the login form (social sign-in, captcha)
+pub fn session_cookie() -> String {
+    let value = get_string("sessionCookie");
+    value.trim().trim_start_matches("Cookie:").trim().into()
+}
+
+// ---- Cache ----
+// Sent when the cache settings change
+pub const CACHE_CHANGED: &str = "cacheChanged";
+
+// Response cache lifetime in seconds; 0 disables it
+pub fn cache_ttl() -> f64 {
+    get_string("cacheTtl").parse::<f64>().map(|m| m * 60.0).unwrap_or(DEFAULT_CACHE_TTL)
+}
+
+pub const DEFAULT_CACHE_TTL: f64 = 15.0 * 60.0;
+
+// Parsed chapter lists are reused for this many seconds; 0 (the default)
+// always refetches
+pub fn chapter_cache_ttl() -> f64 {
+    get_string("chapterCacheTtl").parse::<f64>().map(|m| m * 60.0).unwrap_or(0.0)
+}
+
+// ---- Content ----
+pub fn hide_nsfw() -> bool {
+    get_bool("hideNsfw")
+}
+
+// Comma-separated title keywords, lowercased; "(Uncensored), Solo Leveling"
+pub fn title_blocklist() -> Vec<String> {
+    get_string("titleBlocklist")
+        .split(',')
+        .map(|k| k.trim().to_lowercase())
+        .filter(|k| !k.is_empty())
+        .collect()
+}
+
+// Comma-separated genre names or slugs to drop from listings and search
+pub fn genre_blocklist() -> Vec<String> {
+    get_string("genreBlocklist")
+        .split(',')
+        .map(|g| String::from(g.trim()))
+        .filter(|g| !g.is_empty())
+        .collect()
+}
+
+// "", "auto", "scroll", "rtl", "ltr", "vertical"
+pub fn viewer() -> String {
+    get_string("viewer")
+}
+
+// Build "Latest" from the RSS feed instead of the themed listing
+pub fn latest_from_feed() -> bool {
+    get_bool("latestFromFeed")
+}
+
+// ---- Chapters ----
+pub fn hide_locked() -> bool {
+    get_bool("hideLocked")
+}
+
+pub fn oldest_first() -> bool {
+    get_bool("oldestFirst")
+}
+
+// Language code from parse::chapter_lang, or "all"
+pub fn chapter_language() -> String {
+    let value = get_string("chapterLanguage");
+    if value.is_empty() { "all".into() } else { value }
+}
+
+// Read removed chapters from the Wayback Machine; see wayback.rs
+pub fn wayback_fallback() -> bool {
+    get_bool("waybackFallback")
+}
+
+// Hours east of UTC the site shows its dates in; empty uses the site's own
+pub fn utc_offset() -> f64 {
+    get_string("serverTimezone").trim().parse().unwrap_or(site().utc_offset)
+}
+
+// ---- Images ----
+// Mirror host to rewrite page URLs to; empty keeps the original host
+pub fn image_server() -> String {
+    get_string("imageServer")
+}
+
+// "", "original", "resized"
+pub fn image_quality() -> String {
+    get_string("imageQuality")
+}
+
+// Pages dropped from the start and end of every chapter
+pub fn skip_first_pages() -> usize {
+    get_string("skipFirstPages").parse().unwrap_or(0)
+}
+
+pub fn skip_last_pages() -> usize {
+    get_string("skipLastPages").parse().unwrap_or(0)
+}
+
+// Also drop edge pages whose file names look like credits (config::CREDIT_URLS)
+pub fn skip_credit_pages() -> bool {
+    get_bool("skipCreditPages")
+}
+
+// Download pages in the source and hand them over as base64
+pub fn inline_images() -> bool {
+    get_bool("inlineImages")
+}
+
+// ---- Advanced ----
+// JSON object of selector overrides, e.g. {"chapter_list": "ul.clstyle li a"};
+// keys are the field names of theme::Selectors
+pub fn selector_overrides() -> String {
+    get_string("selectorOverrides")
+}
+
+// Series IDs as bare slugs instead of site paths; see url::manga_id
+pub fn slug_ids() -> bool {
+    get_bool("slugIds")
+}
+
+// Selector misses as errors instead of empty results; see lib::strict_miss
+pub fn strict_mode() -> bool {
+    get_bool("strictMode")
+}
+
+// Request/parse tracing in Aidoku's log viewer; see the debug! macro
+pub fn debug_logging() -> bool {
+    get_bool("debugLogging")
+}
+
+// Pick up the maintainer's hot-fix config; on unless switched off
+pub fn remote_config() -> bool {
+    defaults_get("remoteConfig").and_then(|v| v.as_bool()).unwrap_or(true)
+}
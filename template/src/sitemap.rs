@@ -0,0 +1,80 @@
+// Series URLs from the XML sitemaps: the one complete list of everything on
+// the site, and independent of the theme's archive pages.
+
+use aidoku::{
+    error::Result,
+    std::{String, Vec},
+};
+
+use crate::{feed::tag, http::get_text_cached, settings};
+
+// WordPress core first, then Yoast / Rank Math
+const INDEXES: &[&str] = &["/wp-sitemap.xml", "/sitemap_index.xml", "/sitemap.xml"];
+
+fn locs(xml: &str) -> Vec<String> {
+    xml.split("<loc")
+        .skip(1)
+        .filter_map(|l| tag(&format!("<loc{l}"), "loc").map(|u| u.trim().replace("&amp;", "&")))
+        .filter(|u| !u.is_empty())
+        .collect()
+}
+
+// Sub-sitemaps holding series posts, not genres, chapters or pages
+fn is_series_sitemap(url: &str) -> bool {
+    let u = url.to_lowercase();
+    u.contains("manga") && !["genre", "tax", "chapter", "author", "user"].iter().any(|x| u.contains(x))
+}
+
+pub fn series_urls() -> Result<Vec<String>> {
+    let base = settings::base_url();
+    let mut last_err = None;
+    for index in INDEXES {
+        let xml = match get_text_cached(&format!("{base}{index}")) {
+            Ok(xml) => xml,
+            Err(e) => { last_err = Some(e); continue; }
+        };
+        // An index lists sitemaps; a plain urlset is already the list
+        if !xml.contains("<sitemapindex") {
+            let urls = locs(&xml);
+            if !urls.is_empty() { return Ok(urls); }
+            continue;
+        }
+        let mut urls = Vec::new();
+        for sitemap in locs(&xml).into_iter().filter(|u| is_series_sitemap(u)) {
+            // One broken part shouldn't lose the rest of the catalog
+            let Ok(part) = get_text_cached(&sitemap) else { continue };
+            urls.extend(locs(&part));
+        }
+        if !urls.is_empty() { return Ok(urls); }
+    }
+    match last_err {
+        Some(e) => Err(e),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locs_in_order() {
+        let xml = "<urlset>\
+            <url><loc>https://a.com/manga/one/</loc></url>\
+            <url><loc> https://a.com/manga/two/?a=1&amp;b=2 </loc></url>\
+            <url><loc></loc></url>\
+            <url><loc><![CDATA[https://a.com/manga/three/]]></loc></url>\
+        </urlset>";
+        assert_eq!(locs(xml), ["https://a.com/manga/one/", "https://a.com/manga/two/?a=1&b=2", "https://a.com/manga/three/"]);
+        assert!(locs("<urlset></urlset>").is_empty());
+    }
+
+    #[test]
+    fn series_sitemaps() {
+        assert!(is_series_sitemap("https://a.com/wp-sitemap-posts-wp-manga-1.xml"));
+        assert!(is_series_sitemap("https://a.com/manga-sitemap.xml"));
+        assert!(!is_series_sitemap("https://a.com/wp-sitemap-taxonomies-wp-manga-genre-1.xml"));
+        assert!(!is_series_sitemap("https://a.com/wp-manga-chapter-sitemap1.xml"));
+        assert!(!is_series_sitemap("https://a.com/post-sitemap.xml"));
+    }
+}
@@ -0,0 +1,583 @@
+// Entry points run against saved pages. The mock client answers the
+// fixture URLs and 404s everything else, so the REST, AJAX and sitemap
+// fallbacks fail the way they do on a site without them. native.rs stands
+// in for the HTML parser, defaults and clock; setup() empties the defaults
+// and puts the clock back at native::START.
+
+use super::*;
+use crate::client::{self, MockClient};
+use std::sync::MutexGuard;
+
+static SITE: Site = Site {
+    name: "Test Scans",
+    lang: "en",
+    base_url: "https://example.test",
+    scanlator: "Test Scans",
+    title_suffixes: &["test scans"],
+    boilerplate: &[],
+    remote_config: None,
+    mirrors: &[],
+    utc_offset: 0.0,
+};
+
+const LISTING: &[u8] = include_bytes!("../tests/fixtures/listing.html");
+const SERIES: &[u8] = include_bytes!("../tests/fixtures/series.html");
+const CHAPTER: &[u8] = include_bytes!("../tests/fixtures/chapter.html");
+
+const DAY: f64 = 86400.0;
+
+// Deterministic junk for the edge-case tests: xorshift64 over a seed
+pub(crate) fn noise(seed: u64, len: usize) -> Vec<u8> {
+    let mut x = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x as u8
+        })
+        .collect()
+}
+
+// Text built from the pieces the parsers split and slice on, multi-byte
+// characters included, so garbage reaches their edge cases
+pub(crate) fn garbage(seed: u64, len: usize) -> String {
+    const PIECES: &[&str] = &[
+        "0", "7", "31", "2024", ".", "-", "_", "/", ":", "?", "#", "&", ";", "=", ",", " ", "\n", "<", ">", "\"", "'",
+        "{", "}", "[", "]", "\\", "&#", "&#x", "amp", "chapter", "ch", "ep", "season", "s", "x", "w", "jan", "ago", "se",
+        "<p>", "</p>", "<br>", "http://", "https://", "//", "\u{e9}", "\u{2013}", "\u{1F525}", "\u{d654}",
+    ];
+    noise(seed, len).iter().map(|b| PIECES[*b as usize % PIECES.len()]).collect()
+}
+
+// Every prefix of `s` that ends on a character boundary
+pub(crate) fn prefixes(s: &str) -> impl Iterator<Item = &str> {
+    (0..=s.len()).filter(|i| s.is_char_boundary(*i)).map(|i| &s[..i])
+}
+
+fn setup() -> (MutexGuard<'static, ()>, &'static MockClient) {
+    let (held, mock) = client::mock();
+    native::reset();
+    register(&SITE);
+    reset_caches();
+    memory::clear();
+    mock.serve("https://example.test/manga/?page=1", LISTING);
+    mock.serve("https://example.test/?s=solo&post_type=wp-manga&page=1", LISTING);
+    mock.serve("https://example.test/manga/solo-leveling/", SERIES);
+    mock.serve("https://example.test/solo-leveling-chapter-2/?style=list", CHAPTER);
+    // Madara-style chapter link, under its series
+    mock.serve("https://example.test/manga/solo-leveling/chapter-2/", CHAPTER);
+    (held, mock)
+}
+
+#[test]
+fn manga_list() {
+    let (_held, mock) = setup();
+    let result = get_manga_list(&SITE, Vec::new(), 1).unwrap();
+    let ids: Vec<&str> = result.manga.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(ids, ["/manga/solo-leveling/", "/manga/omniscient-readers-viewpoint/"]);
+    assert_eq!(result.manga[1].title, "Omniscient Reader\u{2019}s Viewpoint");
+    // Covers at full size
+    assert_eq!(result.manga[0].cover, "https://example.test/wp-content/uploads/solo-leveling.jpg");
+    assert_eq!(result.manga[1].cover, "https://example.test/wp-content/uploads/orv.jpg");
+    assert_eq!(result.manga[0].url, "https://example.test/manga/solo-leveling/");
+    assert!(result.has_more);
+    assert!(mock.requests().iter().any(|u| u == "https://example.test/manga/?page=1"));
+}
+
+#[test]
+fn search_falls_back_to_the_results_page() {
+    let (_held, mock) = setup();
+    let filters = vec![Filter::Title { value: String::from("solo") }];
+    let result = get_search_results(&SITE, filters, 1).unwrap();
+    // Ranked by similarity to the query
+    assert_eq!(result.manga.first().map(|m| m.title.as_str()), Some("Solo Leveling"));
+    assert_eq!(result.manga.len(), 2);
+    assert!(mock.requests().iter().any(|u| u == "https://example.test/?s=solo&post_type=wp-manga&page=1"));
+}
+
+#[test]
+fn manga_details() {
+    let (_held, _mock) = setup();
+    let manga = get_manga_details(&SITE, String::from("/manga/solo-leveling/")).unwrap();
+    assert_eq!(manga.id, "/manga/solo-leveling/");
+    assert_eq!(manga.title, "Solo Leveling");
+    assert_eq!(manga.author, "Chugong");
+    assert_eq!(manga.artist, "DUBU");
+    assert_eq!(manga.description, "E-rank hunter Sung Jinwoo gets a second chance.");
+    assert_eq!(manga.categories, ["Action", "Fantasy"]);
+    assert_eq!(manga.cover, "https://example.test/wp-content/uploads/solo-leveling.jpg");
+    assert!(matches!(manga.status, MangaStatus::Ongoing));
+    assert!(matches!(manga.nsfw, MangaContentRating::Safe));
+    assert!(matches!(manga.viewer, MangaViewer::Scroll));
+}
+
+#[test]
+fn chapter_list() {
+    let (_held, _mock) = setup();
+    let chapters = get_chapter_list(&SITE, String::from("/manga/solo-leveling/")).unwrap();
+    let ids: Vec<&str> = chapters.iter().map(|c| c.id.as_str()).collect();
+    assert_eq!(ids, ["/solo-leveling-chapter-3/", "/solo-leveling-chapter-2/", "/solo-leveling-chapter-1/"]);
+    let numbers: Vec<&str> = chapters.iter().map(|c| c.chapter.as_str()).collect();
+    assert_eq!(numbers, ["3", "2", "1"]);
+    assert_eq!(chapters[0].date_updated, Some(19725.0 * DAY));
+    // The row's link wraps the date too; it stays out of the title
+    assert!(chapters.iter().all(|c| c.title.is_empty()), "{:?}", chapters[0].title);
+    assert_eq!(chapters[0].url, "https://example.test/solo-leveling-chapter-3/");
+    assert_eq!(chapters[0].scanlator, "Test Scans");
+    assert_eq!(chapters[0].lang, "en");
+}
+
+#[test]
+fn page_list() {
+    let (_held, mock) = setup();
+    let pages = get_page_list(&SITE, String::from("/solo-leveling-chapter-2/")).unwrap();
+    let urls: Vec<&str> = pages.iter().map(|p| p.url.as_str()).collect();
+    assert_eq!(urls, [
+        "https://example.test/wp-content/uploads/sl/2/001.jpg",
+        "https://example.test/wp-content/uploads/sl/2/002.jpg",
+        "https://example.test/wp-content/uploads/sl/2/003.jpg",
+    ]);
+    assert_eq!(pages.iter().map(|p| p.index).collect::<Vec<_>>(), [0, 1, 2]);
+    // List style, so a paged chapter comes back whole
+    assert!(mock.requests().iter().any(|u| u == "https://example.test/solo-leveling-chapter-2/?style=list"));
+}
+
+#[test]
+fn scrambled_pages_are_reassembled() {
+    let (_held, mock) = setup();
+    let script = r#"<script>var scramble = {"cols": 3, "rows": 2, "images": {"001.jpg": [4, 0, 5, 1, 3, 2], "002.jpg": "1-0-2-3-4-5"}};</script>"#;
+    let chapter = String::from_utf8_lossy(CHAPTER).replace("</body>", &format!("{script}</body>"));
+    mock.serve("https://example.test/solo-leveling-chapter-2/?style=list", chapter.as_bytes());
+    let served = include_bytes!("../tests/fixtures/tiles_444.jpg");
+    mock.serve("https://example.test/wp-content/uploads/sl/2/001.jpg", served);
+    mock.serve("https://example.test/wp-content/uploads/sl/2/002.jpg", b"RIFF\0\0\0\0WEBPVP8 ");
+    let pages = get_page_list(&SITE, String::from("/solo-leveling-chapter-2/")).unwrap();
+    let layout = tiles::Layout::parse(r#"{"cols": 3, "rows": 2, "key": [4, 0, 5, 1, 3, 2]}"#).unwrap();
+    let expected = parse::base64_encode(&tiles::unscramble(served, "001.jpg", &layout).unwrap());
+    assert!(pages[0].url.is_empty() && pages[0].base64 == expected);
+    // WebP can't be put back, and 003 isn't scrambled: both stay URLs
+    assert_eq!(pages[1].url, "https://example.test/wp-content/uploads/sl/2/002.jpg");
+    assert_eq!(pages[2].url, "https://example.test/wp-content/uploads/sl/2/003.jpg");
+    assert!(!mock.requests().iter().any(|u| u.ends_with("003.jpg")));
+}
+
+#[test]
+fn missing_chapter_is_an_error() {
+    let (_held, _mock) = setup();
+    assert!(get_page_list(&SITE, String::from("/solo-leveling-chapter-99/")).is_err());
+}
+
+#[test]
+fn rate_limits_fail_instead_of_waiting() {
+    let (_held, mock) = setup();
+    mock.serve_status("https://example.test/busy/", 429, b"");
+    assert!(http::get_dom_unless_gone("https://example.test/busy/").is_err());
+    assert_eq!(mock.requests(), ["https://example.test/busy/"]);
+    // A gateway blip is retried straight away, with no time spent waiting
+    mock.serve_status("https://example.test/down/", 503, b"");
+    assert!(http::get_dom_unless_gone("https://example.test/down/").is_err());
+    assert_eq!(mock.requests().iter().filter(|u| *u == "https://example.test/down/").count(), 4);
+    assert_eq!(native::current_date(), native::START);
+}
+
+#[test]
+fn page_and_image_headers() {
+    let (_held, mock) = setup();
+    let names = |url: &str| mock.headers_for(url).into_iter().map(|(k, _)| k).collect::<Vec<_>>();
+    let value = |url: &str, name: &str| {
+        let headers = mock.headers_for(url);
+        headers.iter().filter(|(k, _)| k == name).map(|(_, v)| v.clone()).collect::<Vec<_>>()
+    };
+    http::get_dom("https://example.test/manga/solo-leveling/").unwrap();
+    let page = "https://example.test/manga/solo-leveling/";
+    assert!(names(page).iter().any(|k| k == "Accept-Language"));
+    assert_eq!(value(page, "Referer"), ["https://example.test/"]);
+
+    let image = "https://example.test/wp-content/uploads/sl/2/001.jpg";
+    let _ = http::get_image(image, "https://example.test/solo-leveling-chapter-2/");
+    assert!(!names(image).iter().any(|k| k == "Accept-Language"));
+    assert_eq!(value(image, "Accept").len(), 1);
+    assert_eq!(value(image, "Referer"), ["https://example.test/solo-leveling-chapter-2/"]);
+}
+
+#[test]
+fn chapter_lists_are_kept_in_memory() {
+    let (_held, mock) = setup();
+    let set = |key: &str, value: &str| native::defaults_set(key, native::StringRef::from(value).0);
+    let stored = |key: &str| native::defaults_get(key).unwrap().as_string().map(|s| s.read()).unwrap_or_default();
+    set("chapterCacheTtl", "10");
+    let id = String::from("/manga/solo-leveling/");
+    let ids = |chapters: Vec<Chapter>| chapters.into_iter().map(|c| c.id).collect::<Vec<_>>();
+    let first = ids(get_chapter_list(&SITE, id.clone()).unwrap());
+    let fetched = mock.requests().len();
+    // Past the shared series page, inside the TTL: served without a request
+    native::advance(120.0);
+    assert_eq!(ids(get_chapter_list(&SITE, id.clone()).unwrap()), first);
+    assert_eq!(mock.requests().len(), fetched);
+    assert_eq!(stored(&format!("chapters.{id}")), "");
+    // Past it, the list is parsed again and stored afresh
+    native::advance(600.0);
+    assert!(memory::chapters(&id, 600.0).is_none());
+    assert_eq!(ids(get_chapter_list(&SITE, id.clone()).unwrap()), first);
+    assert!(memory::chapters(&id, 600.0).is_some());
+
+    // Lists an earlier version kept in defaults are dropped
+    set("chapters.index", "[\"/manga/old/\"]");
+    set("chapters./manga/old/", "{\"hash\":1,\"chapters\":[]}");
+    cache::clear();
+    assert_eq!((stored("chapters.index"), stored("chapters./manga/old/")), (String::new(), String::new()));
+    assert!(memory::stored_chapters(&id).is_none());
+}
+
+#[test]
+fn chapter_links() {
+    let (_held, _mock) = setup();
+    let link = handle_url(&SITE, String::from("https://example.test/manga/solo-leveling/chapter-2/")).unwrap();
+    assert_eq!(link.manga.map(|m| m.id).as_deref(), Some("/manga/solo-leveling/"));
+    let chapter = link.chapter.unwrap();
+    assert_eq!((chapter.id.as_str(), chapter.chapter.as_str()), ("/manga/solo-leveling/chapter-2/", "2"));
+}
+
+#[test]
+fn series_links() {
+    let (_held, _mock) = setup();
+    let link = handle_url(&SITE, String::from("https://example.test/manga/solo-leveling/")).unwrap();
+    assert_eq!(link.manga.map(|m| m.title).as_deref(), Some("Solo Leveling"));
+    assert!(link.chapter.is_none());
+}
+
+#[test]
+fn listing_links_point_at_the_listing() {
+    let (_held, mock) = setup();
+    assert!(handle_url(&SITE, String::from("https://example.test/genres/action/")).is_err());
+    // Answered from the URL alone
+    assert!(mock.requests().is_empty());
+}
+
+#[test]
+fn statuses() {
+    assert!(matches!(map_status("OnGoing"), MangaStatus::Ongoing));
+    assert!(matches!(map_status("Completed"), MangaStatus::Completed));
+    assert!(matches!(map_status("Season End"), MangaStatus::Hiatus));
+    assert!(matches!(map_status("on_hold"), MangaStatus::Hiatus));
+    assert!(matches!(map_status("Dropped"), MangaStatus::Cancelled));
+    assert!(matches!(map_status("Coming Soon"), MangaStatus::Unknown));
+    for seed in 0..2000 {
+        let _ = map_status(&garbage(seed, seed as usize % 32));
+    }
+}
+
+// Cut-off and junk pages load as whatever they hold, or fail with an error;
+// they never take the module down. Each body gets its own URL, so no cache
+// serves an earlier one.
+#[test]
+fn truncated_pages_never_panic() {
+    let (_held, mock) = setup();
+    let junk = (0..8).map(|seed| noise(seed, 2048));
+    let bodies: Vec<Vec<u8>> = (0..SERIES.len()).step_by(61).map(|n| SERIES[..n].to_vec()).chain(junk.clone()).collect();
+    for (n, body) in bodies.iter().enumerate() {
+        let id = format!("/manga/cut-{n}/");
+        mock.serve(&format!("https://example.test{id}"), body);
+        let _ = get_manga_details(&SITE, id.clone());
+        let _ = get_chapter_list(&SITE, id);
+    }
+    let bodies: Vec<Vec<u8>> = (0..CHAPTER.len()).step_by(37).map(|n| CHAPTER[..n].to_vec()).chain(junk).collect();
+    for (n, body) in bodies.iter().enumerate() {
+        let id = format!("/cut-{n}-chapter-1/");
+        mock.serve(&format!("https://example.test{id}?style=list"), body);
+        let _ = get_page_list(&SITE, id);
+    }
+}
+
+// ---- Snapshots ----
+// Parsed models as "key: value" lines, compared with tests/snapshots/*.txt.
+// UPDATE_SNAPSHOTS=1 rewrites the files instead; review their diff.
+
+fn line(out: &mut String, key: &str, value: &str) {
+    out.push_str(key);
+    out.push(':');
+    if !value.is_empty() {
+        out.push(' ');
+        out.push_str(&value.replace('\n', "\\n"));
+    }
+    out.push('\n');
+}
+
+fn manga_text(m: &Manga) -> String {
+    let status = match m.status {
+        MangaStatus::Ongoing => "Ongoing",
+        MangaStatus::Completed => "Completed",
+        MangaStatus::Cancelled => "Cancelled",
+        MangaStatus::Hiatus => "Hiatus",
+        _ => "Unknown",
+    };
+    let nsfw = match m.nsfw {
+        MangaContentRating::Nsfw => "Nsfw",
+        MangaContentRating::Suggestive => "Suggestive",
+        _ => "Safe",
+    };
+    let viewer = match m.viewer {
+        MangaViewer::Rtl => "Rtl",
+        MangaViewer::Ltr => "Ltr",
+        MangaViewer::Vertical => "Vertical",
+        MangaViewer::Scroll => "Scroll",
+        _ => "Default",
+    };
+    let mut out = String::new();
+    for (key, value) in [
+        ("id", m.id.as_str()),
+        ("title", &m.title),
+        ("author", &m.author),
+        ("artist", &m.artist),
+        ("description", &m.description),
+        ("url", &m.url),
+        ("cover", &m.cover),
+        ("categories", &m.categories.join(", ")),
+        ("status", status),
+        ("nsfw", nsfw),
+        ("viewer", viewer),
+    ] {
+        line(&mut out, key, value);
+    }
+    out
+}
+
+fn chapter_text(c: &Chapter) -> String {
+    let date = c.date_updated.map_or_else(|| String::from("-"), parse::format_date);
+    let mut out = String::new();
+    for (key, value) in [
+        ("id", c.id.as_str()),
+        ("title", &c.title),
+        ("volume", &c.volume),
+        ("chapter", &c.chapter),
+        ("date", &date),
+        ("scanlator", &c.scanlator),
+        ("lang", &c.lang),
+        ("url", &c.url),
+    ] {
+        line(&mut out, key, value);
+    }
+    out
+}
+
+fn page_text(p: &Page) -> String {
+    let mut out = String::new();
+    line(&mut out, "index", &p.index.to_string());
+    line(&mut out, "url", &p.url);
+    line(&mut out, "base64", &format!("{} bytes", p.base64.len()));
+    line(&mut out, "text", &p.text);
+    out
+}
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = format!("{}/tests/snapshots/{name}.txt", env!("CARGO_MANIFEST_DIR"));
+    if std::env::var("UPDATE_SNAPSHOTS").is_ok_and(|v| v == "1") {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(expected == actual, "{name} snapshot differs; rerun with UPDATE_SNAPSHOTS=1 to rewrite {path}\n--- expected\n{expected}\n--- actual\n{actual}");
+}
+
+const MADARA_SERIES: &[u8] = include_bytes!("../tests/fixtures/madara_series.html");
+const MADARA_ID: &str = "/manga/omniscient-readers-viewpoint/";
+
+#[test]
+fn manga_list_snapshot() {
+    let (_held, _mock) = setup();
+    let result = get_manga_list(&SITE, Vec::new(), 1).unwrap();
+    let cards: Vec<String> = result.manga.iter().map(manga_text).collect();
+    assert_snapshot("manga_list", &format!("has_more: {}\n\n{}", result.has_more, cards.join("\n")));
+}
+
+#[test]
+fn madara_details_snapshot() {
+    let (_held, mock) = setup();
+    mock.serve(&format!("https://example.test{MADARA_ID}"), MADARA_SERIES);
+    let manga = get_manga_details(&SITE, String::from(MADARA_ID)).unwrap();
+    assert_snapshot("madara_details", &manga_text(&manga));
+}
+
+#[test]
+fn madara_chapters_snapshot() {
+    let (_held, mock) = setup();
+    mock.serve(&format!("https://example.test{MADARA_ID}"), MADARA_SERIES);
+    let chapters = get_chapter_list(&SITE, String::from(MADARA_ID)).unwrap();
+    let rows: Vec<String> = chapters.iter().map(chapter_text).collect();
+    assert_snapshot("madara_chapters", &rows.join("\n"));
+}
+
+#[test]
+fn mangastream_details_snapshot() {
+    let (_held, _mock) = setup();
+    let manga = get_manga_details(&SITE, String::from("/manga/solo-leveling/")).unwrap();
+    assert_snapshot("mangastream_details", &manga_text(&manga));
+}
+
+#[test]
+fn mangastream_chapters_snapshot() {
+    let (_held, _mock) = setup();
+    let chapters = get_chapter_list(&SITE, String::from("/manga/solo-leveling/")).unwrap();
+    let rows: Vec<String> = chapters.iter().map(chapter_text).collect();
+    assert_snapshot("mangastream_chapters", &rows.join("\n"));
+}
+
+#[test]
+fn pages_snapshot() {
+    let (_held, _mock) = setup();
+    let pages = get_page_list(&SITE, String::from("/solo-leveling-chapter-2/")).unwrap();
+    let rows: Vec<String> = pages.iter().map(page_text).collect();
+    assert_snapshot("pages", &rows.join("\n"));
+}
+
+// ---- Benchmarks ----
+// Long lists on generated pages. The query counts run with the suite, since
+// select() calls are what a long list costs in the app, each one a host
+// round trip; the timings are ignored, run them with
+// `cargo test --release -- --ignored --nocapture`. Their bounds only catch a
+// return of per-row scans, not normal noise.
+
+const LONG_LIST_ROWS: usize = 1000;
+
+// Madara rows `first..=last`, newest first, with a release date on each when
+// `dates` is set and a "next" link to `next` when there is one
+fn chapter_rows(first: usize, last: usize, dates: bool, next: Option<&str>) -> Vec<u8> {
+    let mut html = String::from("<html><body><div class=\"c-page-content\">");
+    html.push_str("<div class=\"post-title\"><h1>Long Series</h1></div>");
+    html.push_str("<div class=\"listing-chapters_wrap\"><ul class=\"main version-chap\">");
+    for n in (first..=last).rev() {
+        html.push_str(&format!(
+            "<li class=\"wp-manga-chapter\"><a href=\"https://example.test/manga/long-series/chapter-{n}/\">Chapter {n}</a>"
+        ));
+        if dates {
+            html.push_str(&format!("<span class=\"chapter-release-date\"><i>March {}, 2024</i></span>", n % 28 + 1));
+        }
+        html.push_str("</li>");
+    }
+    html.push_str("</ul>");
+    if let Some(next) = next {
+        html.push_str(&format!("<div class=\"chapter-pagination\"><a class=\"next\" href=\"{next}\">Next</a></div>"));
+    }
+    html.push_str("</div></div></body></html>");
+    html.into_bytes()
+}
+
+fn long_chapter_list() -> Vec<u8> {
+    chapter_rows(1, LONG_LIST_ROWS, true, None)
+}
+
+fn timed<T>(name: &str, runs: u32, mut f: impl FnMut() -> T) -> std::time::Duration {
+    let start = std::time::Instant::now();
+    for _ in 0..runs {
+        std::hint::black_box(f());
+    }
+    let each = start.elapsed() / runs;
+    println!("{name}: {each:?} per run");
+    each
+}
+
+// A column the page has nowhere costs nothing per row: one query per row
+// for the link, one more when the rows carry dates
+#[test]
+fn chapter_rows_cost_a_query_per_column() {
+    let (_held, _mock) = setup();
+    for (dates, per_row) in [(false, 1), (true, 2)] {
+        let dom = http::parse_html(&chapter_rows(1, LONG_LIST_ROWS, dates, None)).unwrap();
+        let s = theme::selectors(&dom);
+        let before = native::queries();
+        let chapters = parse_chapters(&dom, s, "Long Series");
+        let queries = native::queries() - before;
+        println!("parse_chapters: {queries} queries for {LONG_LIST_ROWS} rows, dates: {dates}");
+        assert_eq!(chapters.len(), LONG_LIST_ROWS);
+        assert!(queries <= per_row * LONG_LIST_ROWS + 8, "{queries} queries");
+    }
+}
+
+// The theme is detected once; later pages of the site don't scan for markers
+#[test]
+fn theme_is_detected_once() {
+    let (_held, _mock) = setup();
+    let dom = http::parse_html(&chapter_rows(1, 3, true, None)).unwrap();
+    let before = native::queries();
+    assert_eq!(theme::theme(&dom), Theme::Madara);
+    assert_eq!(native::queries() - before, 2);
+    let fragment = http::parse_html(b"<ul><li class=\"wp-manga-chapter\"><a href=\"/x/\">1</a></li></ul>").unwrap();
+    let before = native::queries();
+    assert_eq!(theme::theme(&fragment), Theme::Madara);
+    let _ = theme::selectors(&dom);
+    assert_eq!(native::queries() - before, 0);
+}
+
+#[test]
+#[ignore]
+fn bench_parse_long_chapter_list() {
+    let (_held, _mock) = setup();
+    let dom = http::parse_html(&long_chapter_list()).unwrap();
+    let s = theme::selectors(&dom);
+    let each = timed("parse_chapters_until", 10, || {
+        parse_chapters_until(&dom, s, "Long Series", None).0.len()
+    });
+    assert_eq!(parse_chapters_until(&dom, s, "Long Series", None).0.len(), LONG_LIST_ROWS);
+    assert!(each.as_secs_f64() < 2.0);
+}
+
+#[test]
+#[ignore]
+fn bench_get_long_chapter_list() {
+    let (_held, mock) = setup();
+    let body = long_chapter_list();
+    let ids: Vec<String> = (0..10).map(|n| format!("/manga/long-series-{n}/")).collect();
+    for id in &ids {
+        mock.serve(&format!("https://example.test{id}"), &body);
+    }
+    // A new ID per run, so neither the page nor the chapter cache answers
+    let mut ids = ids.into_iter();
+    let each = timed("get_chapter_list", 10, || {
+        get_chapter_list(&SITE, ids.next().unwrap()).unwrap().len()
+    });
+    assert!(each.as_secs_f64() < 3.0);
+}
+
+// 2000 chapters over 20 "next" pages, which crawl_chapter_pages checks
+// against everything seen so far
+#[test]
+#[ignore]
+fn bench_crawl_chapter_pages() {
+    const PAGES: usize = 20;
+    const PER_PAGE: usize = 100;
+    let (_held, mock) = setup();
+    for run in 0..5 {
+        let page_url = |p: usize| format!("/manga/crawl-{run}/chapters/{p}/");
+        for p in 0..PAGES {
+            let last = (PAGES - p) * PER_PAGE;
+            let next = (p + 1 < PAGES).then(|| page_url(p + 1));
+            let body = chapter_rows(last - PER_PAGE + 1, last, true, next.as_deref());
+            let url = if p == 0 { format!("/manga/crawl-{run}/") } else { page_url(p) };
+            mock.serve(&format!("https://example.test{url}"), &body);
+        }
+    }
+    let mut run = 0;
+    let each = timed("get_chapter_list over 20 pages", 5, || {
+        run += 1;
+        get_chapter_list(&SITE, format!("/manga/crawl-{}/", run - 1)).unwrap().len()
+    });
+    let chapters = get_chapter_list(&SITE, String::from("/manga/crawl-0/")).unwrap();
+    assert_eq!(chapters.len(), PAGES * PER_PAGE);
+    assert!(each.as_secs_f64() < 5.0);
+}
+
+#[test]
+#[ignore]
+fn bench_page_urls() {
+    let (_held, _mock) = setup();
+    let mut html = String::from("<html><body><div id=\"readerarea\">");
+    for n in 1..=300 {
+        html.push_str(&format!("<img src=\"https://example.test/wp-content/uploads/long/{n:03}.jpg\">"));
+    }
+    html.push_str("</div></body></html>");
+    let dom = http::parse_html(html.as_bytes()).unwrap();
+    let each = timed("page_urls", 10, || page_urls(&dom).len());
+    assert_eq!(page_urls(&dom).len(), 300);
+    assert!(each.as_secs_f64() < 1.0);
+}
@@ -0,0 +1,177 @@
+// Madara and MangaStream lay pages out differently enough that one union of
+// both themes' selectors picks up the wrong nodes. The site is matched to a
+// theme from its pages, and parsing uses that theme's selectors only. The
+// selectors themselves live in selectors.json, compiled in by build.rs.
+
+use aidoku::std::{String, Vec};
+use serde_json::Value;
+use std::sync::Mutex;
+
+use crate::{
+    config::{MADARA, MADARA_MARKERS, MANGASTREAM, MANGASTREAM_MARKERS, MIXED},
+    first,
+    host::Node,
+    remote, settings,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Theme {
+    Madara,
+    MangaStream,
+    // Neither (or both) matched: fragments, error pages, a customized theme
+    Unknown,
+}
+
+#[derive(Clone, Copy)]
+pub struct Selectors {
+    pub list_item: &'static str,
+    pub title: &'static str,
+    pub cover: &'static str,
+    pub list_genres: &'static str,
+    // Card score; Madara rates out of 5, MangaStream out of 10
+    pub card_rating: &'static str,
+    pub href: &'static str,
+    pub details_title: &'static str,
+    pub manga_meta: &'static str,
+    // Label/value rows of the details table
+    pub info_row: &'static str,
+    pub info_label: &'static str,
+    pub info_value: &'static str,
+    pub summary: &'static str,
+    pub adult_badge: &'static str,
+    pub details_adult_badge: &'static str,
+    pub genres: &'static str,
+    pub status: &'static str,
+    pub manga_post_id: &'static str,
+    pub chapter_list: &'static str,
+    // Name inside a chapter row whose link also wraps the date; rows without
+    // it are named by the link text
+    pub chapter_name: &'static str,
+    pub chapter_next: &'static str,
+    pub chapter_date: &'static str,
+    pub chapter_group: &'static str,
+    pub chapter_lock: &'static str,
+    // Link from a chapter page back to its series
+    pub chapter_parent: &'static str,
+    pub page_image: &'static str,
+    pub page_noscript: &'static str,
+    pub pagination_next: &'static str,
+    pub page_numbers: &'static str,
+    // Homepage "Popular Today" slider and the sidebar popular tabs
+    pub popular_today: &'static str,
+    pub popular_weekly: &'static str,
+    pub popular_monthly: &'static str,
+    pub popular_all_time: &'static str,
+    // Links into the genre archive, from menus and sidebars
+    pub genre_link: &'static str,
+    // Rows of the logged-in user's bookmark page
+    pub bookmark_item: &'static str,
+    // "Related Series" / "You may also like" cards on a series page
+    pub related_item: &'static str,
+    pub related_title: &'static str,
+    // Average score and bookmark count on a series page
+    pub details_rating: &'static str,
+    pub details_followers: &'static str,
+    // Prose body of a novel chapter, for entries without page images
+    pub novel_text: &'static str,
+    // Homepage "Latest Update" cards and the newest chapter link on each
+    pub latest_item: &'static str,
+    pub latest_chapter: &'static str,
+    // Upload time of the newest chapter on a "Latest Update" card
+    pub latest_date: &'static str,
+    // Links to the other sub-pages of a chapter split across several pages
+    pub chapter_subpages: &'static str,
+    // Series page thumbnail, for when og:image and structured data have none
+    pub details_cover: &'static str,
+}
+
+impl Selectors {
+    pub fn field(&self, name: &str) -> Option<&'static str> {
+        let mut copy = *self;
+        copy.field_mut(name).map(|f| *f)
+    }
+}
+
+fn has_any(dom: &Node, markers: &str) -> bool {
+    first(dom, markers).is_some()
+}
+
+pub fn detect(dom: &Node) -> Theme {
+    match (has_any(dom, MADARA_MARKERS), has_any(dom, MANGASTREAM_MARKERS)) {
+        (true, false) => Theme::Madara,
+        (false, true) => Theme::MangaStream,
+        _ => Theme::Unknown,
+    }
+}
+
+fn defaults(theme: Theme) -> &'static Selectors {
+    match theme {
+        Theme::Madara => &MADARA,
+        Theme::MangaStream => &MANGASTREAM,
+        Theme::Unknown => &MIXED,
+    }
+}
+
+// Remote hot-fix overrides with the user's setting on top of them. Only
+// both at once needs a merge; either alone is used as it is.
+fn overrides() -> String {
+    let user = settings::selector_overrides();
+    let remote = remote::get();
+    if remote.selectors.is_empty() { return user; }
+    if user.trim().is_empty() { return remote.selectors_json.clone(); }
+    let mut all = remote.selectors.clone();
+    if let Ok(Value::Object(mine)) = serde_json::from_str::<Value>(&user) { all.extend(mine); }
+    Value::Object(all).to_string()
+}
+
+// Theme defaults with the overrides applied, rebuilt when the
+// setting text changes. Overridden sets are leaked to stay 'static; that
+// happens once per edit of the setting, not per request.
+static MERGED: Mutex<Vec<(Theme, String, &'static Selectors)>> = Mutex::new(Vec::new());
+
+fn merged(theme: Theme, raw: &str) -> &'static Selectors {
+    let mut cache = MERGED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, _, s)) = cache.iter().find(|(t, r, _)| *t == theme && r == raw) {
+        return s;
+    }
+    // A typo in the JSON shouldn't break the source; ignore what doesn't parse
+    let Ok(Value::Object(overrides)) = serde_json::from_str::<Value>(raw) else { return defaults(theme) };
+    let base = defaults(theme);
+    let mut s = *base;
+    for (name, value) in &overrides {
+        let (Some(field), Some(value)) = (s.field_mut(name), value.as_str().map(str::trim)) else { continue };
+        if !value.is_empty() { *field = String::from(value).leak(); }
+    }
+    let s: &'static Selectors = Box::leak(Box::new(s));
+    cache.retain(|(t, _, _)| *t != theme);
+    cache.push((theme, raw.into(), s));
+    s
+}
+
+// Detection is two queries over the whole document, and a site is one theme
+// on every page. Once a page is clearly one of them, that answer stands for
+// the base URL; pages that match neither, like AJAX fragments, then get it too.
+static DETECTED: Mutex<Option<(String, Theme)>> = Mutex::new(None);
+
+// The site's theme, detected from `dom` until a page has settled it
+pub fn theme(dom: &Node) -> Theme {
+    let base = settings::base_url();
+    let mut detected = DETECTED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, theme)) = detected.as_ref().filter(|(b, _)| *b == base) {
+        return *theme;
+    }
+    let theme = detect(dom);
+    debug!("theme: {theme:?}");
+    if theme != Theme::Unknown { *detected = Some((base, theme)); }
+    theme
+}
+
+pub fn forget() {
+    *DETECTED.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+pub fn selectors(dom: &Node) -> &'static Selectors {
+    let theme = theme(dom);
+    let raw = overrides();
+    if raw.trim().is_empty() { defaults(theme) } else { merged(theme, &raw) }
+}
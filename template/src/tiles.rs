@@ -0,0 +1,180 @@
+// Scrambled-tile pages. Some protected chapters serve each image cut into a
+// grid with the tiles shuffled, and the reader's script puts them back with a
+// key from the page:
+//
+//     var scramble = {"cols": 4, "rows": 4, "key": [5, 0, 11, ...]};
+//
+// key[i] is the served tile that belongs at position i, row-major. Tiles are
+// width / cols by height / rows; the leftover strips on the right and bottom
+// edges aren't shuffled. A key can be a "5-0-11-..." string too, and
+// "images" can map file names to keys of their own. Here the page is
+// decoded, reassembled and re-encoded as JPEG for Page.base64.
+
+use aidoku::std::{String, Vec};
+use serde_json::Value;
+
+use crate::{jpeg, png};
+
+// Past this, decoding would take more memory than a page is worth; the page
+// is left as a URL instead
+pub const MAX_PIXELS: usize = 12 * 1024 * 1024;
+// Re-encoded pages, close enough to the served ones not to show artifacts
+const QUALITY: u8 = 90;
+
+// Decoded image: 1 (gray) or 3 (RGB) bytes per pixel, rows top to bottom
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub pixels: Vec<u8>,
+}
+
+pub struct Layout {
+    cols: usize,
+    rows: usize,
+    key: Option<Vec<usize>>,
+    // Per-file keys, by file name
+    images: Vec<(String, Vec<usize>)>,
+}
+
+// [5, 0, 11] or "5-0-11" / "5,0,11"
+fn key(value: &Value) -> Option<Vec<usize>> {
+    match value {
+        Value::Array(items) => items.iter().map(|v| v.as_u64().map(|n| n as usize)).collect(),
+        Value::String(s) => s.split(['-', ',']).map(|n| n.trim().parse().ok()).collect(),
+        _ => None,
+    }
+}
+
+// Last path segment, without query or fragment
+fn file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+impl Layout {
+    pub fn parse(json: &str) -> Option<Layout> {
+        let Ok(Value::Object(o)) = serde_json::from_str::<Value>(json) else { return None };
+        let dimension = |name: &str| o.get(name)?.as_u64().filter(|n| (1..=64).contains(n)).map(|n| n as usize);
+        let (cols, rows) = (dimension("cols")?, dimension("rows")?);
+        let images = match o.get("images") {
+            Some(Value::Object(map)) => map.iter().filter_map(|(name, v)| Some((name.clone(), key(v)?))).collect(),
+            _ => Vec::new(),
+        };
+        let key = o.get("key").and_then(key);
+        if key.is_none() && images.is_empty() { return None; }
+        Some(Layout { cols, rows, key, images })
+    }
+
+    // The key for one page URL: its own by file name, else the shared one
+    fn key_for(&self, url: &str) -> Option<&[usize]> {
+        let name = file_name(url);
+        self.images
+            .iter()
+            .find(|(n, _)| file_name(n).eq_ignore_ascii_case(name))
+            .map(|(_, k)| k.as_slice())
+            .or(self.key.as_deref())
+    }
+
+    // Whether `url` is served scrambled at all
+    pub fn covers(&self, url: &str) -> bool {
+        self.key_for(url).is_some()
+    }
+}
+
+// Tiles moved back to where the key says. None when the key isn't a
+// permutation of the grid or the tiles would be empty.
+pub fn reassemble(image: &Image, cols: usize, rows: usize, key: &[usize]) -> Option<Image> {
+    let n = cols * rows;
+    if key.len() != n { return None; }
+    let mut seen = vec![false; n];
+    for &k in key {
+        if k >= n || seen[k] { return None; }
+        seen[k] = true;
+    }
+    let (tw, th) = (image.width / cols, image.height / rows);
+    if tw == 0 || th == 0 { return None; }
+    let stride = image.width * image.channels;
+    let span = tw * image.channels;
+    // Starting from a copy leaves the edge strips where they were
+    let mut out = image.pixels.clone();
+    for (to, &from) in key.iter().enumerate() {
+        let (dx, dy) = (to % cols * tw, to / cols * th);
+        let (sx, sy) = (from % cols * tw, from / cols * th);
+        for y in 0..th {
+            let src = (sy + y) * stride + sx * image.channels;
+            let dst = (dy + y) * stride + dx * image.channels;
+            out[dst..dst + span].copy_from_slice(&image.pixels[src..src + span]);
+        }
+    }
+    Some(Image { width: image.width, height: image.height, channels: image.channels, pixels: out })
+}
+
+// JPEG or PNG bytes, undecoded formats (WebP, GIF) and oversized pages give None
+fn decode(data: &[u8]) -> Option<Image> {
+    match data {
+        [0xff, 0xd8, ..] => jpeg::decode(data),
+        [0x89, b'P', b'N', b'G', ..] => png::decode(data),
+        _ => None,
+    }
+}
+
+// A served page put back together, as JPEG bytes
+pub fn unscramble(data: &[u8], url: &str, layout: &Layout) -> Option<Vec<u8>> {
+    let key = layout.key_for(url)?;
+    let image = decode(data)?;
+    let image = reassemble(&image, layout.cols, layout.rows, key)?;
+    jpeg::encode(&image, QUALITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 4x2 grid of 2x2 tiles plus a 1px strip on the right, each tile one
+    // gray level so positions are easy to check
+    fn grid() -> Image {
+        let (width, height) = (9, 4);
+        let pixels = (0..height).flat_map(|y| (0..width).map(move |x| if x == 8 { 255 } else { (y / 2 * 4 + x / 2) as u8 * 10 })).collect();
+        Image { width, height, channels: 1, pixels }
+    }
+
+    fn tile_at(image: &Image, i: usize) -> u8 {
+        image.pixels[(i / 4 * 2) * image.width + i % 4 * 2]
+    }
+
+    #[test]
+    fn layouts() {
+        let layout = Layout::parse(r#"{"cols": 2, "rows": 2, "key": "3-2-1-0"}"#).unwrap();
+        assert_eq!(layout.key_for("https://a.com/001.jpg"), Some([3, 2, 1, 0].as_slice()));
+        let layout = Layout::parse(r#"{"cols": 2, "rows": 1, "images": {"002.jpg": [1, 0]}}"#).unwrap();
+        assert_eq!(layout.key_for("https://a.com/ch/002.jpg?v=2"), Some([1, 0].as_slice()));
+        assert!(!layout.covers("https://a.com/ch/001.jpg"));
+        assert!(Layout::parse(r#"{"cols": 2, "rows": 2}"#).is_none());
+        assert!(Layout::parse(r#"{"cols": 0, "rows": 2, "key": []}"#).is_none());
+        assert!(Layout::parse("[1, 2]").is_none());
+    }
+
+    #[test]
+    fn tiles_go_back_in_place() {
+        let image = grid();
+        let key = [5, 0, 7, 2, 1, 6, 3, 4];
+        let out = reassemble(&image, 4, 2, &key).unwrap();
+        for (to, &from) in key.iter().enumerate() {
+            assert_eq!(tile_at(&out, to), tile_at(&image, from), "tile {to}");
+        }
+        // The strip past the last column stays put
+        assert!((0..4).all(|y| out.pixels[y * 9 + 8] == 255));
+        // Not a permutation, wrong length, tiles narrower than a pixel
+        assert!(reassemble(&image, 4, 2, &[0, 0, 1, 2, 3, 4, 5, 6]).is_none());
+        assert!(reassemble(&image, 4, 2, &[0, 1, 2]).is_none());
+        assert!(reassemble(&image, 16, 1, &(0..16).collect::<Vec<_>>()).is_none());
+    }
+
+    #[test]
+    fn unsupported_formats_are_left_alone() {
+        let layout = Layout::parse(r#"{"cols": 2, "rows": 2, "key": [3, 2, 1, 0]}"#).unwrap();
+        assert!(unscramble(b"RIFF\0\0\0\0WEBPVP8 ", "https://a.com/1.webp", &layout).is_none());
+        assert!(unscramble(b"", "https://a.com/1.jpg", &layout).is_none());
+    }
+}
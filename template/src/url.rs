@@ -0,0 +1,459 @@
+// Search/listing URL building.
+// Everything that ends up in a request query string goes through SearchParams,
+// so every filter combination produces the same canonical URL.
+
+use aidoku::{
+    std::{String, Vec},
+    Filter,
+};
+
+use crate::{filters, genres, i18n, settings};
+
+#[derive(Default)]
+pub struct SearchParams {
+    pub query: String,
+    pub page: i32,
+    pub sort: Option<&'static str>,
+    // "asc" / "desc"; None leaves each sort in the site's own direction
+    pub order: Option<&'static str>,
+    pub genres: Vec<String>,
+    // Madara can't exclude genres; these are applied client-side after parsing
+    pub excluded: Vec<String>,
+    pub status: &'static str,
+    pub kind: &'static str,
+    pub year: String,
+    pub adult: &'static str,
+    // On a 5-star scale; 0 when unset. Applied client-side like `excluded`
+    pub min_rating: f32,
+    pub author: String,
+    pub artist: String,
+}
+
+// Query-string value encoding (application/x-www-form-urlencoded): UTF-8
+// bytes outside the unreserved set become %XX and spaces become "+", which
+// PHP decodes back to a space. Apostrophes, "&", "#", "+" itself, CJK and
+// emoji all arrive intact; runs of whitespace collapse to one space first.
+pub fn encode(value: &str) -> String {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut out = String::with_capacity(value.len() * 3);
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            b' ' => out.push('+'),
+            _ => {
+                out.push('%');
+                out.push(HEX[(b >> 4) as usize] as char);
+                out.push(HEX[(b & 0xF) as usize] as char);
+            }
+        }
+    }
+    out
+}
+
+impl SearchParams {
+    pub fn from_filters(filters: Vec<Filter>, page: i32) -> Self {
+        let mut params = SearchParams { page, ..Default::default() };
+        for f in filters {
+            match f {
+                Filter::Title { value } => { params.query = value; }
+                // Genre filters carry the site's genre slug as their id, sent
+                // as genre[]. value: 0 = ignored, 1 = included, 2 = excluded. Included
+                // genres go to the site, so they use its current slug; excluded
+                // ones are matched against slugified card genres instead.
+                Filter::Genre(genre) => match genre.value {
+                    1 => params.genres.push(genres::resolve(&genre.name).unwrap_or(genre.id)),
+                    2 => params.excluded.push(genre.id),
+                    _ => {}
+                },
+                Filter::Select { name, value } if i18n::canonical(&name) == filters::STATUS.name => { params.status = filters::STATUS.param(value); }
+                Filter::Select { name, value } if i18n::canonical(&name) == filters::TYPE.name => { params.kind = filters::TYPE.param(value); }
+                Filter::Select { name, value } if i18n::canonical(&name) == filters::ADULT.name => { params.adult = filters::ADULT.param(value); }
+                Filter::Select { name, value } if i18n::canonical(&name) == filters::MIN_RATING.name => {
+                    params.min_rating = filters::MIN_RATING.param(value).parse().unwrap_or(0.0);
+                }
+                Filter::Sort { index, ascending } => {
+                    params.sort = filters::SORT.choices.get(index as usize).map(|c| c.param);
+                    params.order = params.sort.map(|_| if ascending { "asc" } else { "desc" });
+                }
+                Filter::Text { name, value } if i18n::canonical(&name) == filters::YEAR => { params.year = value.trim().into(); }
+                Filter::Author { value } => { params.author = value.trim().into(); }
+                Filter::Text { name, value } if i18n::canonical(&name) == filters::ARTIST => { params.artist = value.trim().into(); }
+                _ => {}
+            }
+        }
+        params
+    }
+
+    // Any filter besides sort and direction
+    fn has_filters(&self) -> bool {
+        !(self.genres.is_empty()
+            && self.excluded.is_empty()
+            && self.status.is_empty()
+            && self.kind.is_empty()
+            && self.year.is_empty()
+            && self.adult.is_empty()
+            && self.author.is_empty()
+            && self.artist.is_empty()
+            && self.min_rating <= 0.0)
+    }
+
+    // Plain browsing: no query and no filters, so the first page always has cards
+    pub fn is_browse(&self) -> bool {
+        self.query.is_empty() && !self.has_filters()
+    }
+
+    // Just a title query; everything else needs Madara's search page
+    pub fn is_plain_query(&self) -> bool {
+        !self.query.is_empty() && self.sort.is_none() && !self.has_filters()
+    }
+
+    // WP search: /?s=term&post_type=wp-manga plus Madara advanced-search params.
+    // Browsing with no query and no filters goes to the /manga/ archive
+    // instead; the site sometimes rejects an empty ?s= outright.
+    pub fn url(&self) -> String {
+        let page = if self.page < 1 { 1 } else { self.page };
+        if self.query.is_empty() && !self.has_filters() {
+            let mut url = format!("{}/manga/?page={page}", settings::base_url());
+            if let Some(o) = self.sort {
+                url.push_str(&format!("&m_orderby={o}"));
+            }
+            if let Some(o) = self.order {
+                url.push_str(&format!("&order={o}"));
+            }
+            return url;
+        }
+        let mut url = format!(
+            "{}/?s={}&post_type=wp-manga&page={}",
+            settings::base_url(),
+            encode(&self.query),
+            page
+        );
+        // genre[]=slug, repeated; op=1 asks for series having all of them
+        for g in &self.genres {
+            url.push_str(&format!("&genre%5B%5D={}", encode(g)));
+        }
+        if self.genres.len() > 1 {
+            url.push_str("&op=1");
+        }
+        if let Some(o) = self.sort {
+            url.push_str(&format!("&m_orderby={o}"));
+        }
+        if let Some(o) = self.order {
+            url.push_str(&format!("&order={o}"));
+        }
+        if !self.status.is_empty() {
+            url.push_str(&format!("&status%5B%5D={}", self.status));
+        }
+        if !self.kind.is_empty() {
+            url.push_str(&format!("&type={}", self.kind));
+        }
+        // release=YYYY
+        if self.year.len() == 4 && self.year.chars().all(|c| c.is_ascii_digit()) {
+            url.push_str(&format!("&release={}", self.year));
+        }
+        if !self.author.is_empty() {
+            url.push_str(&format!("&author={}", encode(&self.author)));
+        }
+        if !self.artist.is_empty() {
+            url.push_str(&format!("&artist={}", encode(&self.artist)));
+        }
+        // adult=0 hides 18+ entries, adult=1 shows only them
+        if !self.adult.is_empty() {
+            url.push_str(&format!("&adult={}", self.adult));
+        }
+        url
+    }
+}
+
+// A-Z directory page for one letter ("." is the site's key for symbols/#)
+pub fn az_url(letter: &str, page: i32) -> String {
+    format!("{}/a-z-list/?show={}&page={}", settings::base_url(), encode(letter), if page < 1 { 1 } else { page })
+}
+
+// Homepage, whose latest-updates block pages through /page/N/
+pub fn home_url(page: i32) -> String {
+    let base = settings::base_url();
+    if page <= 1 { format!("{base}/") } else { format!("{base}/page/{page}/") }
+}
+
+// Paginated WordPress archive, e.g. /project/page/2/
+pub fn archive_url(path: &str, page: i32) -> String {
+    let path = path.trim_matches('/');
+    let base = settings::base_url();
+    if page <= 1 { format!("{base}/{path}/") } else { format!("{base}/{path}/page/{page}/") }
+}
+
+// Chapter page in Madara's list reading style. Chapters set to "paged" mode
+// render one image per request; ?style=list returns them all at once.
+// MangaStream readers ignore the parameter.
+pub fn list_style(link: &str) -> String {
+    if link.contains("style=") { return link.into(); }
+    let sep = if link.contains('?') { '&' } else { '?' };
+    format!("{link}{sep}style=list")
+}
+
+// Absolute URL for a link found on `page`. Handles full URLs (and data:
+// URIs), protocol-relative "//cdn/x", root-relative "/x", query-only "?p=2",
+// and paths relative to the page's directory, "../" included. The link's own
+// query string and fragment are kept as they are.
+pub fn resolve(href: &str, page: &str) -> String {
+    let href = href.trim();
+    let lower = href.to_ascii_lowercase();
+    if lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("data:") {
+        return href.into();
+    }
+    let (scheme, rest) = page.split_once("://").unwrap_or(("https", page));
+    if let Some(net) = href.strip_prefix("//") {
+        return format!("{scheme}://{net}");
+    }
+    let host_end = rest.find('/').unwrap_or(rest.len());
+    let origin = format!("{scheme}://{}", &rest[..host_end]);
+    if href.starts_with('/') {
+        return format!("{origin}{href}");
+    }
+    let page_path = rest[host_end..].split(['?', '#']).next().unwrap_or_default();
+    let path = if page_path.is_empty() { "/" } else { page_path };
+    if href.is_empty() || href.starts_with(['?', '#']) {
+        return format!("{origin}{path}{href}");
+    }
+
+    let split = href.find(['?', '#']).unwrap_or(href.len());
+    let (relative, tail) = href.split_at(split);
+    let dir = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+    let mut segments: Vec<&str> = dir.split('/').filter(|s| !s.is_empty()).collect();
+    let parts: Vec<&str> = relative.split('/').collect();
+    for (i, part) in parts.iter().enumerate() {
+        match *part {
+            "." => {}
+            ".." => { segments.pop(); }
+            "" if i + 1 < parts.len() => {}
+            segment => segments.push(segment),
+        }
+    }
+    // "x/", "." and ".." name directories; keep their trailing slash
+    let dir_like = relative.ends_with('/') || matches!(parts.last(), Some(&".") | Some(&".."));
+    let mut out = format!("{origin}/{}", segments.join("/"));
+    if dir_like && !out.ends_with('/') { out.push('/'); }
+    out.push_str(tail);
+    out
+}
+
+// Manga and chapter IDs are site paths ("/manga/some-series/"), not absolute
+// URLs, so library entries and history survive the site's domain changes.
+pub fn path_id(href: &str) -> String {
+    let href = href.trim().split('#').next().unwrap_or_default();
+    // "//host/path" is as absolute as "https://host/path"
+    let host_at = href.find("://").map(|i| i + 3).or_else(|| href.starts_with("//").then_some(2));
+    let path = match host_at {
+        Some(i) => href[i..].find('/').map_or("/", |p| &href[i + p..]),
+        None => href,
+    };
+    if path.starts_with('/') { path.into() } else { format!("/{path}") }
+}
+
+// Full URL for an ID on the current base. Library entries from before the
+// switch still hold absolute URLs (possibly on an old domain); those are
+// reduced to their path first, so both kinds load.
+pub fn id_url(id: &str) -> String {
+    // Slug-only IDs, as other Madara sources (and the slugIds setting) use
+    if !id.is_empty() && !id.contains('/') {
+        return format!("{}/manga/{}/", settings::base_url(), id.trim());
+    }
+    format!("{}{}", settings::base_url(), path_id(id))
+}
+
+// "/manga/{slug}/" -> slug
+fn series_slug(path: &str) -> Option<&str> {
+    let mut parts = path.trim_matches('/').split('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("manga"), Some(slug), None) if !slug.is_empty() => Some(slug),
+        _ => None,
+    }
+}
+
+// Manga ID for a series link: its path, or with the slugIds setting on just
+// the slug, as other Madara sources do, so Aidoku's migration can match
+// entries across them. Links outside /manga/{slug}/ keep their path.
+pub fn manga_id(href: &str) -> String {
+    let path = path_id(href);
+    match series_slug(&path) {
+        Some(slug) if settings::slug_ids() => slug.into(),
+        _ => path,
+    }
+}
+
+// "https://host" of an old absolute-URL ID
+fn legacy_origin(id: &str) -> Option<&str> {
+    let start = id.find("://")? + 3;
+    Some(id[start..].find('/').map_or(id, |p| &id[..start + p]))
+}
+
+// Chapter ID in the same format as its series' ID. Aidoku keys read history
+// by chapter ID, so series saved with an absolute ID keep getting absolute
+// chapter IDs and their progress stays attached.
+pub fn chapter_id(series_id: &str, href: &str) -> String {
+    let path = path_id(href);
+    match legacy_origin(series_id) {
+        Some(origin) => format!("{origin}{path}"),
+        None => path,
+    }
+}
+
+// WordPress RSS feed of series, newest first
+pub fn feed_url(page: i32) -> String {
+    format!("{}/feed/?post_type=wp-manga&paged={}", settings::base_url(), if page < 1 { 1 } else { page })
+}
+
+// Site news: the plain posts feed, without the series post type
+pub fn news_feed_url() -> String {
+    format!("{}/feed/", settings::base_url())
+}
+
+// What a site URL points at, for handle_url
+pub enum Route {
+    Series,
+    Chapter,
+    // Genre archive, by slug
+    Genre(String),
+    // Search results, by decoded query
+    Search(String),
+    // One of the listings declared in aidoku.json
+    Listing(&'static str),
+}
+
+// "%27" and "+" back to text; malformed escapes are kept as-is
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => match s.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                Some(b) => { out.push(b); i += 2; }
+                None => out.push(b'%'),
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into()
+}
+
+fn query_param<'a>(url: &'a str, name: &str) -> Option<&'a str> {
+    let query = url.split('#').next()?.split_once('?')?.1;
+    query.split('&').find_map(|p| p.strip_prefix(name)?.strip_prefix('='))
+}
+
+pub fn route(url: &str) -> Route {
+    let path = path_id(url);
+    let path = path.split('?').next().unwrap_or_default().trim_end_matches('/');
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    if let Some(q) = query_param(url, "s") {
+        return Route::Search(percent_decode(q));
+    }
+    match segments.as_slice() {
+        ["manga-genre", slug, ..] | ["genres", slug, ..] => return Route::Genre(String::from(*slug)),
+        ["project", ..] => return Route::Listing("Projects"),
+        ["a-z-list", ..] => return Route::Listing("A-Z"),
+        // The homepage and bare archive, optionally sorted
+        [] | ["manga"] | ["manga", "page", _] => {
+            return Route::Listing(match query_param(url, "m_orderby") {
+                Some("trending") | Some("views") => "Popular",
+                Some("new-manga") => "New Series",
+                _ => "Latest",
+            });
+        }
+        _ => {}
+    }
+    if url.contains("/chapter/") || url.contains("/ch/") || url.contains("/chapter-") {
+        Route::Chapter
+    } else {
+        Route::Series
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_query_values() {
+        assert_eq!(encode("solo leveling"), "solo+leveling");
+        assert_eq!(encode("  solo \t leveling\n"), "solo+leveling");
+        assert_eq!(encode("Omniscient Reader's Viewpoint"), "Omniscient+Reader%27s+Viewpoint");
+        assert_eq!(encode("Tom & Jerry #1"), "Tom+%26+Jerry+%231");
+        assert_eq!(encode("1+1=2 50%"), "1%2B1%3D2+50%25");
+        assert_eq!(encode("\u{d654}\u{c0b0}\u{ad6c}"), "%ED%99%94%EC%82%B0%EA%B5%AC");
+        assert_eq!(encode("\u{1F525}"), "%F0%9F%94%A5");
+        assert_eq!(encode("a-b_c.d~e"), "a-b_c.d~e");
+    }
+
+    // What PHP decodes from an encoded value is the (space-collapsed) original
+    #[test]
+    fn encode_round_trips() {
+        for value in ["Reader's", "Tom & Jerry", "1+1=2 50%", "\u{d654}\u{c0b0}\u{ad6c} \u{1F525}", "a/b?c#d"] {
+            assert_eq!(percent_decode(&encode(value)), value);
+        }
+        assert_eq!(percent_decode("a%20b+c"), "a b c");
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+
+    #[test]
+    fn resolve_links() {
+        let page = "https://a.com/x/y/z.html?p=1";
+        assert_eq!(resolve("https://b.com/a", page), "https://b.com/a");
+        assert_eq!(resolve(" data:image/png;base64,AA ", page), "data:image/png;base64,AA");
+        assert_eq!(resolve("//cdn.a.com/1.jpg", page), "https://cdn.a.com/1.jpg");
+        assert_eq!(resolve("/manga/", page), "https://a.com/manga/");
+        assert_eq!(resolve("?p=2", page), "https://a.com/x/y/z.html?p=2");
+        assert_eq!(resolve("#top", "https://a.com"), "https://a.com/#top");
+        assert_eq!(resolve("1.jpg?v=2", page), "https://a.com/x/y/1.jpg?v=2");
+        assert_eq!(resolve("../b/1.jpg", page), "https://a.com/x/b/1.jpg");
+        assert_eq!(resolve("../../../1.jpg", page), "https://a.com/1.jpg");
+        assert_eq!(resolve("./", page), "https://a.com/x/y/");
+        assert_eq!(resolve("..", page), "https://a.com/x/");
+        assert_eq!(resolve("/x", "a.com/p"), "https://a.com/x");
+    }
+
+    #[test]
+    fn path_ids() {
+        assert_eq!(path_id("https://a.com/manga/x/#comments"), "/manga/x/");
+        assert_eq!(path_id("//a.com/manga/x/"), "/manga/x/");
+        assert_eq!(path_id("https://a.com"), "/");
+        assert_eq!(path_id(" manga/x/ "), "/manga/x/");
+        assert_eq!(path_id("/manga/x/?a=1"), "/manga/x/?a=1");
+    }
+
+    #[test]
+    fn routes() {
+        assert!(matches!(route("https://a.com/manga/solo-leveling/"), Route::Series));
+        assert!(matches!(route("https://a.com/manga/solo-leveling/chapter-2/"), Route::Chapter));
+        assert!(matches!(route("https://a.com/manga-genre/martial-arts/"), Route::Genre(g) if g == "martial-arts"));
+        assert!(matches!(route("https://a.com/genres/action/page/2/"), Route::Genre(g) if g == "action"));
+        assert!(matches!(route("https://a.com/?s=reader%27s+view&post_type=wp-manga"), Route::Search(q) if q == "reader's view"));
+        assert!(matches!(route("https://a.com/project/"), Route::Listing("Projects")));
+        assert!(matches!(route("https://a.com/a-z-list/?show=A"), Route::Listing("A-Z")));
+        assert!(matches!(route("https://a.com/"), Route::Listing("Latest")));
+        assert!(matches!(route("https://a.com/manga/?m_orderby=trending"), Route::Listing("Popular")));
+        assert!(matches!(route("https://a.com/manga/page/3/?m_orderby=new-manga"), Route::Listing("New Series")));
+    }
+
+    #[test]
+    fn plain_queries_and_browsing() {
+        let params = |query: &str| SearchParams { query: query.into(), ..Default::default() };
+        assert!(params("").is_browse());
+        assert!(!params("").is_plain_query());
+        assert!(params("solo").is_plain_query());
+        assert!(!params("solo").is_browse());
+        // Sorting alone is still browsing, but not a plain query
+        assert!(SearchParams { sort: Some("views"), ..params("") }.is_browse());
+        assert!(!SearchParams { sort: Some("views"), ..params("solo") }.is_plain_query());
+        assert!(!SearchParams { status: "on-going", ..params("solo") }.is_plain_query());
+        assert!(!SearchParams { status: "on-going", ..params("") }.is_browse());
+        assert!(!SearchParams { min_rating: 3.0, ..params("") }.is_browse());
+        assert!(!SearchParams { excluded: vec!["action".into()], ..params("") }.is_browse());
+    }
+}
@@ -0,0 +1,63 @@
+// Archived copies of chapters the site has taken down (DMCA'd or pruned),
+// from the Wayback Machine. Requests go to archive.org directly, without the
+// site's cookies or Referer, and snapshots are fetched raw ("id_"): the page
+// as it was crawled, without the toolbar or rewritten links.
+
+use aidoku::std::{net::HttpMethod, String, Vec};
+use serde::Deserialize;
+
+use crate::{
+    client::{client, HttpRequest},
+    host::Node,
+    http, settings,
+    url::encode,
+};
+
+#[derive(Deserialize)]
+struct Availability {
+    archived_snapshots: Snapshots,
+}
+
+#[derive(Deserialize)]
+struct Snapshots {
+    closest: Option<Closest>,
+}
+
+#[derive(Deserialize)]
+struct Closest {
+    available: bool,
+    status: String,
+    timestamp: String,
+}
+
+fn fetch(url: &str) -> Option<Vec<u8>> {
+    let user_agent = http::user_agent();
+    let headers = [("User-Agent", user_agent.as_str())];
+    let request = HttpRequest { method: HttpMethod::Get, url, headers: &headers, body: None };
+    client().send(&request).filter(|r| r.status == 200).map(|r| r.data)
+}
+
+// Timestamp of the newest snapshot of `url` that was a real page, not a
+// captured error
+fn snapshot(url: &str) -> Option<String> {
+    let body = fetch(&format!("https://archive.org/wayback/available?url={}", encode(url)))?;
+    let availability: Availability = serde_json::from_slice(&body).ok()?;
+    let closest = availability.archived_snapshots.closest?;
+    (closest.available && closest.status == "200").then_some(closest.timestamp)
+}
+
+// The archived chapter page and its snapshot timestamp; None when the
+// setting is off or nothing usable was archived
+pub fn chapter(url: &str) -> Option<(Node, String)> {
+    if !settings::wayback_fallback() { return None; }
+    let timestamp = snapshot(url)?;
+    debug!("wayback: {url} is gone, reading the {timestamp} snapshot");
+    let data = fetch(&format!("https://web.archive.org/web/{timestamp}id_/{url}"))?;
+    Some((http::parse_html(&data).ok()?, timestamp))
+}
+
+// A page image as archived around the chapter's snapshot; the archive
+// serves the capture nearest to `timestamp`
+pub fn image_url(timestamp: &str, url: &str) -> String {
+    format!("https://web.archive.org/web/{timestamp}im_/{url}")
+}